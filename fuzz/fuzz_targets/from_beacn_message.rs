@@ -0,0 +1,20 @@
+#![no_main]
+
+// Responses come from hardware, so decoding any 8 bytes should give a Result, never a panic.
+// Run with `cargo fuzz run from_beacn_message` from the repository root.
+
+use beacn_lib::audio::messages::Message;
+use beacn_lib::manager::ALL_DEVICE_TYPES;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((bytes, rest)) = data.split_first_chunk::<8>() else {
+        return;
+    };
+    let device_type = match rest.first() {
+        Some(index) => ALL_DEVICE_TYPES[*index as usize % ALL_DEVICE_TYPES.len()],
+        None => ALL_DEVICE_TYPES[0],
+    };
+
+    let _ = Message::from_beacn_message_checked(*bytes, device_type);
+});
@@ -0,0 +1,50 @@
+use crate::audio::messages::Message;
+use crate::manager::DeviceType;
+
+/// The feature blocks available on a device type, so a UI can be built before a device is
+/// connected. Use supports() to check individual messages.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    device_type: DeviceType,
+
+    /// Whether the device handles audio messages at all
+    pub audio: bool,
+
+    /// Phantom Power for an XLR microphone
+    pub phantom_power: bool,
+
+    /// Class Compliant (Mic) or Driverless (Studio) mode
+    pub class_compliant: bool,
+
+    /// Moving PC2 Apps between Link channels
+    pub app_linking: bool,
+
+    /// A display, buttons and dials
+    pub controls: bool,
+}
+
+impl Capabilities {
+    pub fn get_device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    /// Returns true if the message applies to this device type
+    pub fn supports(&self, message: &Message) -> bool {
+        self.audio && message.is_valid_for(self.device_type)
+    }
+}
+
+/// Returns the capabilities of a device type
+pub fn capabilities(device_type: DeviceType) -> Capabilities {
+    let audio = matches!(device_type, DeviceType::BeacnMic | DeviceType::BeacnStudio);
+    let studio = device_type == DeviceType::BeacnStudio;
+
+    Capabilities {
+        device_type,
+        audio,
+        phantom_power: studio,
+        class_compliant: audio,
+        app_linking: studio,
+        controls: !audio,
+    }
+}
@@ -1,13 +1,24 @@
-use crate::audio::messages::{DeviceMessageType, Message};
-use crate::audio::{BeacnAudioDevice, DeviceDefinition, LinkChannel, LinkedApp};
-use crate::common::{BeacnDeviceHandle, get_device_info};
-use crate::manager::DeviceType;
+use crate::audio::messages::Message;
+use crate::audio::messages::equaliser::{EQBand, EQMode, EqBandConfig, Equaliser};
+use crate::audio::messages::headphones::{HeadphoneTypes, Headphones};
+use crate::audio::transport::{BeacnTransport, UsbTransport};
+use crate::audio::{
+    BeacnAudioDevice, DeviceDefinition, DeviceSnapshot, LinkChannel, LinkedApp, Pc2Status,
+    ProfileDiff,
+};
+use crate::common::{
+    BeacnDeviceHandle, Timeouts, fallback_serial, get_device_info, get_extra_device_info,
+    read_descriptor_strings, with_open_retry,
+};
+use crate::manager::{DeviceLocation, DeviceType};
 use crate::version::VersionNumber;
-use crate::{BResult, beacn_bail};
+use crate::{BResult, BeacnError, UsbError, beacn_bail};
 use byteorder::{ByteOrder, LittleEndian};
 use log::{debug, warn};
-use rusb::{DeviceHandle, GlobalContext};
+use rusb::{DeviceHandle, UsbContext};
+use std::panic::RefUnwindSafe;
 use std::time::Duration;
+use strum::IntoEnumIterator;
 
 // This defines the code needed for connecting to a Beacn Audio Device, it's currently consistent
 // between the Mic and Studio, so we'll have a common base implementation for open()
@@ -16,127 +27,450 @@ pub trait BeacnAudioDeviceAttach {
     // simply used internally for connection up a device, and shouldn't have any visibility
     // from the outside. This also prevents external code from attempting to call connect.
     #[allow(private_interfaces)]
-    fn connect(device: DeviceDefinition) -> BResult<Box<dyn BeacnAudioDevice>>
+    fn connect<C: UsbContext + RefUnwindSafe + 'static>(
+        device: DeviceDefinition<C>,
+    ) -> BResult<Box<dyn BeacnAudioDevice>>
     where
         Self: Sized;
 
     fn get_product_id(&self) -> u16;
     fn get_serial(&self) -> String;
     fn get_version(&self) -> VersionNumber;
+
+    /// The raw bytes following the serial in the device's info response, see
+    /// `BeacnControlDeviceAttach::get_extra_device_info`
+    fn get_extra_device_info(&self) -> Vec<u8>;
+    fn get_manufacturer_string(&self) -> String;
+    fn get_product_string(&self) -> String;
 }
 
 pub trait BeacnAudioMessageExecute {
     fn get_device_type(&self) -> DeviceType;
-    fn get_usb_handle(&self) -> &DeviceHandle<GlobalContext>;
+    fn get_transport(&self) -> &dyn BeacnTransport;
+
+    /// The timeouts used when talking to this device, see `Timeouts`
+    fn get_timeouts(&self) -> Timeouts;
+}
+
+/// The outcome of setting a value, based on what the device reports when read back
+#[derive(Debug, Copy, Clone)]
+pub enum SetResult {
+    /// The device is holding the value that was sent
+    Applied(Message),
+
+    /// The device accepted the change, but stored a different value (for example, snapped to
+    /// the nearest step it supports, or clamped)
+    Adjusted(Message),
+
+    /// The device didn't change, the value it's still holding is attached
+    Rejected(Message),
+
+    /// The device is holding a different value to the one sent, but whether it adjusted the
+    /// value or ignored it is unknown, as the value before the set wasn't read (VerifyMode::Once)
+    Mismatched(Message),
+
+    /// Nothing was read back (VerifyMode::Never), the value that was sent is attached
+    Unverified(Message),
+}
+
+impl SetResult {
+    /// The value the device is holding, regardless of how the set was handled. For Unverified
+    /// this is the value that was sent, which the device may not actually be holding.
+    pub fn get_message(&self) -> Message {
+        match self {
+            SetResult::Applied(message)
+            | SetResult::Adjusted(message)
+            | SetResult::Rejected(message)
+            | SetResult::Mismatched(message)
+            | SetResult::Unverified(message) => *message,
+        }
+    }
+}
+
+/// How a set is checked against the device. Skipping the checks saves round trips (useful when
+/// applying a lot of values at once), but a value the device rejected or adjusted can then go
+/// unnoticed, so the result returned may not reflect what the device is actually holding.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Also reads the value before the set, so rejected values can be told apart from adjusted
+    /// ones (three round trips). Only worth the extra round trip when the distinction matters.
+    Always,
+
+    /// Only reads the value back after the set (two round trips), if the device is holding a
+    /// different value it's reported as Mismatched, as rejected and adjusted values can't be
+    /// told apart
+    #[default]
+    Once,
+
+    /// Doesn't read anything back (one round trip), the result is always Unverified
+    Never,
 }
 
 // Trait for Sending and Receiving Messages
 #[allow(private_bounds)]
 pub trait BeacnAudioMessaging: BeacnAudioMessageExecute + BeacnAudioMessageLocal {
+    /// Fetches or sets a value. A set fails with BeacnError::ValueMismatch if the device isn't
+    /// holding the value sent afterwards, use set_value_clamped where that's expected.
     fn handle_message(&self, message: Message) -> BResult<Message> {
-        if message.is_device_message_set() {
-            self.set_value(message)
-        } else {
-            self.fetch_value(message)
+        self.process_message(message, None)
+    }
+
+    /// The same as handle_message, but the timeout is used for each USB read and write rather
+    /// than the device's Timeouts.
+    fn handle_message_with_timeout(&self, message: Message, timeout: Duration) -> BResult<Message> {
+        if timeout.is_zero() {
+            // libusb treats a zero timeout as 'wait forever'
+            beacn_bail!("Timeout must be greater than zero");
+        }
+        self.process_message(message, Some(timeout))
+    }
+
+    /// Sets a value on the device, and reports how the device handled it, the caller gets the
+    /// device's current value back in all cases. This uses the default VerifyMode, so a value
+    /// the device didn't take as sent is reported as Mismatched, use set_message_with_verify
+    /// with VerifyMode::Always to tell adjusted values apart from rejected ones.
+    fn set_message(&self, message: Message) -> BResult<SetResult> {
+        if !message.is_device_message_set() {
+            beacn_bail!("{} is not a Set message", message);
+        }
+        self.set_value(message, None, VerifyMode::default())
+    }
+
+    /// Sets a value, and returns the value the device is actually holding. This is intended for
+    /// UIs, where the device may snap a value to a step it supports (or clamp it), and the
+    /// control should be updated to match. Unlike handle_message, a value the device didn't take
+    /// as sent isn't an error here. Use set_message to find out whether the device adjusted or
+    /// ignored the value.
+    fn set_value_clamped(&self, message: Message) -> BResult<Message> {
+        Ok(self.set_message(message)?.get_message())
+    }
+
+    /// The same as set_message, but with control over how the set is verified, see VerifyMode
+    fn set_message_with_verify(&self, message: Message, verify: VerifyMode) -> BResult<SetResult> {
+        if !message.is_device_message_set() {
+            beacn_bail!("{} is not a Set message", message);
+        }
+        self.set_value(message, None, verify)
+    }
+
+    /// Sets only the values which changed between two profiles (see diff_profiles), verifying
+    /// each as set_message does. Parameters only present in one of the profiles aren't touched,
+    /// the caller can check ProfileDiff::has_mismatch before applying.
+    fn apply_diff(&self, diff: &ProfileDiff) -> BResult<Vec<SetResult>> {
+        let mut results = vec![];
+        for message in diff.get_changed() {
+            results.push(self.set_message(*message)?);
+        }
+        Ok(results)
+    }
+
+    /// Returns true if the message applies to this device, for example phantom power is only
+    /// available on the Studio. Firmware requirements aren't checked here.
+    fn supports(&self, message: &Message) -> bool {
+        self.is_command_valid(message)
+    }
+
+    /// Reads every parameter available on this device, along with its serial, firmware version
+    /// and type. Parameters which don't apply to this device, or its firmware, aren't included.
+    fn snapshot(&self) -> BResult<DeviceSnapshot> {
+        let device_type = self.get_device_type();
+        let version = self.get_version();
+
+        let mut snapshot = DeviceSnapshot {
+            serial: self.get_serial(),
+            version,
+            device_type,
+            parameters: vec![],
+        };
+
+        for message in Message::generate_fetch_message(device_type) {
+            if version < message.get_message_minimum_version()
+                || version > message.get_message_maximum_version()
+            {
+                continue;
+            }
+            snapshot.push(self.fetch_value(message, None)?);
         }
+        Ok(snapshot)
     }
 
-    fn get_linked_app_list(&self) -> BResult<Option<Vec<LinkedApp>>> {
+    /// Reads the full configuration of a single EQ band, the result can be passed straight back
+    /// to Equaliser::band to restore it.
+    fn get_eq_band(&self, mode: EQMode, band: EQBand) -> BResult<EqBandConfig> {
+        let fetch = |message| match self.handle_message(Message::Equaliser(message))? {
+            Message::Equaliser(value) => Ok(value),
+            value => beacn_bail!("Unexpected response to {:?}: {}", message, value),
+        };
+
+        let Equaliser::Type(_, _, band_type) = fetch(Equaliser::GetType(mode, band))? else {
+            beacn_bail!("Unexpected response reading the {:?} {:?} Type", mode, band);
+        };
+        let Equaliser::Gain(_, _, gain) = fetch(Equaliser::GetGain(mode, band))? else {
+            beacn_bail!("Unexpected response reading the {:?} {:?} Gain", mode, band);
+        };
+        let Equaliser::Frequency(_, _, frequency) = fetch(Equaliser::GetFrequency(mode, band))?
+        else {
+            beacn_bail!(
+                "Unexpected response reading the {:?} {:?} Frequency",
+                mode,
+                band
+            );
+        };
+        let Equaliser::Q(_, _, q) = fetch(Equaliser::GetQ(mode, band))? else {
+            beacn_bail!("Unexpected response reading the {:?} {:?} Q", mode, band);
+        };
+        let Equaliser::Enabled(_, _, enabled) = fetch(Equaliser::GetEnabled(mode, band))? else {
+            beacn_bail!(
+                "Unexpected response reading the {:?} {:?} Enabled",
+                mode,
+                band
+            );
+        };
+
+        Ok(EqBandConfig::from_values(
+            band_type, gain, frequency, q, enabled,
+        ))
+    }
+
+    /// Switches the EQ to the given mode, and checks the device has actually switched. Each
+    /// mode keeps its own band configuration, so this can be used to A/B two curves.
+    fn activate_eq_mode(&self, mode: EQMode) -> BResult<()> {
+        let message = Message::Equaliser(Equaliser::Mode(mode));
+        let Message::Equaliser(Equaliser::Mode(active)) = self.handle_message(message)? else {
+            beacn_bail!("Unexpected response setting the EQ Mode");
+        };
+        if active != mode {
+            beacn_bail!("EQ Mode is {:?}, expected {:?}", active, mode);
+        }
+        Ok(())
+    }
+
+    /// Reads every band from one EQ mode, and writes them to another, replacing its curve
+    fn copy_eq_mode(&self, from: EQMode, to: EQMode) -> BResult<()> {
+        for band in EQBand::iter() {
+            let config = self.get_eq_band(from, band)?;
+            for message in Equaliser::band(to, band, config) {
+                self.handle_message(message)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_headphone_type(&self) -> BResult<HeadphoneTypes> {
+        let message = Message::Headphones(Headphones::GetHeadphoneType);
+        let Message::Headphones(Headphones::HeadphoneType(value)) = self.handle_message(message)?
+        else {
+            beacn_bail!("Unexpected response reading the Headphone Type");
+        };
+        Ok(value)
+    }
+
+    fn set_headphone_type(&self, headphone_type: HeadphoneTypes) -> BResult<()> {
+        self.handle_message(Message::Headphones(Headphones::HeadphoneType(
+            headphone_type,
+        )))?;
+        Ok(())
+    }
+
+    /// Returns whether a Mic is in class compliant mode. This is sent to the device as a
+    /// DeviceMode (Compliancy, or MicDefault when disabled), and needs firmware 1.2.0 build 188
+    /// or newer.
+    fn is_class_compliant(&self) -> BResult<bool> {
+        let message = Message::Headphones(Headphones::GetMicClassCompliant);
+        let Message::Headphones(Headphones::MicClassCompliant(value)) =
+            self.handle_message(message)?
+        else {
+            beacn_bail!("Unexpected response reading Class Compliant mode");
+        };
+        Ok(value)
+    }
+
+    fn set_class_compliant(&self, enabled: bool) -> BResult<()> {
+        self.handle_message(Message::Headphones(Headphones::MicClassCompliant(enabled)))?;
+        Ok(())
+    }
+
+    /// Returns whether a Studio is in driverless mode. This is sent to the device as a
+    /// DeviceMode (Compliancy, or StudioDefault when disabled).
+    fn is_driverless(&self) -> BResult<bool> {
+        let message = Message::Headphones(Headphones::GetStudioDriverless);
+        let Message::Headphones(Headphones::StudioDriverless(value)) =
+            self.handle_message(message)?
+        else {
+            beacn_bail!("Unexpected response reading Driverless mode");
+        };
+        Ok(value)
+    }
+
+    fn set_driverless(&self, enabled: bool) -> BResult<()> {
+        self.handle_message(Message::Headphones(Headphones::StudioDriverless(enabled)))?;
+        Ok(())
+    }
+
+    /// Returns true if the device has been reconnected since this was last called. A reconnect
+    /// happens when the device briefly drops off the bus (for example, a hub reset), and as the
+    /// device may have reset in the process, the caller should re-read any state it holds.
+    fn check_reconnected(&self) -> bool {
+        self.get_transport().take_reconnected()
+    }
+
+    fn get_linked_app_list(&self) -> BResult<Pc2Status> {
         self.get_linked_apps()
     }
     fn set_linked_app(&self, app: LinkedApp) -> BResult<()> {
         self.set_app_link(app)
     }
+
+    /// Moves an app (by its name in PC2) to a Link channel
+    fn set_app_link_channel(&self, name: &str, channel: LinkChannel) -> BResult<()> {
+        self.set_app_link(LinkedApp {
+            channel,
+            name: name.to_string(),
+        })
+    }
+
+    /// Removes an app's link, returning it to the System channel
+    fn clear_app_link(&self, name: &str) -> BResult<()> {
+        self.set_app_link_channel(name, LinkChannel::System)
+    }
+}
+
+// The raw equivalent of SetResult, carrying the device's response to the lookup
+pub(crate) enum ParamSetResult {
+    Applied([u8; 8]),
+    Adjusted([u8; 8]),
+    Rejected([u8; 8]),
+    Mismatched([u8; 8]),
+    Unverified,
 }
 
 // Stuff that is local to this instance
 pub(crate) trait BeacnAudioMessageLocal:
     BeacnAudioMessageExecute + BeacnAudioDeviceAttach
 {
+    fn process_message(&self, message: Message, timeout: Option<Duration>) -> BResult<Message> {
+        if message.is_device_message_set() {
+            match self.set_value(message, timeout, VerifyMode::default())? {
+                SetResult::Applied(message) | SetResult::Unverified(message) => Ok(message),
+                // Only set_message and set_value_clamped treat a value the device didn't take
+                // as sent as a success, here it's always an error
+                SetResult::Adjusted(found)
+                | SetResult::Rejected(found)
+                | SetResult::Mismatched(found) => {
+                    warn!(serial = self.get_serial().as_str(); "Value was not changed, device is {}", found);
+                    Err(BeacnError::ValueMismatch {
+                        sent: message,
+                        found,
+                    })
+                }
+            }
+        } else {
+            self.fetch_value(message, timeout)
+        }
+    }
+
     fn is_command_valid(&self, message: &Message) -> bool {
-        let message_type = message.get_device_message_type();
-        let device_type = self.get_device_type();
-        match message_type {
-            DeviceMessageType::Common => true,
-            DeviceMessageType::BeacnMic => device_type == DeviceType::BeacnMic,
-            DeviceMessageType::BeacnStudio => device_type == DeviceType::BeacnStudio,
+        message.is_valid_for(self.get_device_type())
+    }
+
+    // Some messages only apply to one device (for example, the Mic and Studio have separate
+    // lighting mode messages as they support different modes), so reject them here rather than
+    // sending something the device won't understand.
+    fn check_command_valid(&self, message: &Message) -> BResult<()> {
+        if !self.is_command_valid(message) {
+            let device_type = self.get_device_type();
+            warn!(serial = self.get_serial().as_str(); "{} is not valid on a {:?}", message, device_type);
+            beacn_bail!("{} is not valid on a {:?}", message, device_type);
         }
+        Ok(())
     }
 
-    fn is_command_firmware_valid(&self, message: &Message) -> bool {
+    fn check_command_firmware(&self, message: &Message) -> BResult<()> {
         let min_version = message.get_message_minimum_version();
         let max_version = message.get_message_maximum_version();
         let device_version = self.get_version();
         if device_version < min_version {
-            warn!("Command Sent not valid for this firmware version:");
-            warn!("Device: {:?} < {:?}", device_version, min_version);
-            warn!("{:?}", message);
-            false
-        } else if device_version > max_version {
-            warn!("Command Sent not valid for this firmware version:");
-            warn!("Device: {:?} > {:?}", device_version, min_version);
-            warn!("{:?}", message);
-            false
-        } else {
-            true
+            warn!(serial = self.get_serial().as_str(); "{} requires firmware >= {}", message, min_version);
+            return Err(BeacnError::FirmwareTooOld {
+                required: min_version,
+                found: device_version,
+            });
         }
+        if device_version > max_version {
+            warn!(serial = self.get_serial().as_str(); "{} requires firmware <= {}", message, max_version);
+            return Err(BeacnError::FirmwareTooNew {
+                maximum: max_version,
+                found: device_version,
+            });
+        }
+        Ok(())
     }
 
-    fn fetch_value(&self, message: Message) -> BResult<Message> {
+    fn fetch_value(&self, message: Message, timeout: Option<Duration>) -> BResult<Message> {
         // Before we do anything, we need to make sure this message is valid on our device
-        if !self.is_command_valid(&message) {
-            warn!("Command Sent not valid for this device:");
-            warn!("{:?}", message);
-            beacn_bail!("Command is not valid for this device");
-        }
+        self.check_command_valid(&message)?;
 
-        if !self.is_command_firmware_valid(&message) {
-            beacn_bail!("Command is not valid for this firmware version");
-        }
+        self.check_command_firmware(&message)?;
 
         // Ok, first we need to deconstruct this message into something more useful
         let key = message.to_beacn_key();
 
         // Lookup the Parameter on the Mic
-        let param = self.param_lookup(key)?;
+        let param = with_reconnect(self.get_transport(), &self.get_serial(), || {
+            self.param_lookup(key, timeout)
+        })?;
 
-        Ok(Message::from_beacn_message(param, self.get_device_type()))
+        Message::from_beacn_message_checked(param, self.get_device_type())
     }
 
-    fn set_value(&self, message: Message) -> BResult<Message> {
-        if !self.is_command_valid(&message) {
-            warn!("Command Sent not valid for this device:");
-            warn!("{:?}", message);
-            beacn_bail!("Command is not valid for this device");
-        }
+    fn set_value(
+        &self,
+        message: Message,
+        timeout: Option<Duration>,
+        verify: VerifyMode,
+    ) -> BResult<SetResult> {
+        self.check_command_valid(&message)?;
 
-        if !self.is_command_firmware_valid(&message) {
-            beacn_bail!("Command is not valid for this firmware version");
-        }
+        self.check_command_firmware(&message)?;
 
         let key = message.to_beacn_key();
-        let value = message.to_beacn_value();
-
-        let result = self.param_set(key, value)?;
+        let value = message.try_to_beacn_value()?;
 
-        // This can generally be ignored, because in most cases it'll be identical to the
-        // original request (except fed from the Mic), but passing back anyway just in case.
-        Ok(Message::from_beacn_message(result, self.get_device_type()))
+        let device_type = self.get_device_type();
+        let result = with_reconnect(self.get_transport(), &self.get_serial(), || {
+            self.param_set(key, value, timeout, verify)
+        })?;
+        Ok(match result {
+            ParamSetResult::Applied(v) => {
+                SetResult::Applied(Message::from_beacn_message_checked(v, device_type)?)
+            }
+            ParamSetResult::Adjusted(v) => {
+                SetResult::Adjusted(Message::from_beacn_message_checked(v, device_type)?)
+            }
+            ParamSetResult::Rejected(v) => {
+                SetResult::Rejected(Message::from_beacn_message_checked(v, device_type)?)
+            }
+            ParamSetResult::Mismatched(v) => {
+                SetResult::Mismatched(Message::from_beacn_message_checked(v, device_type)?)
+            }
+            ParamSetResult::Unverified => SetResult::Unverified(message),
+        })
     }
 
-    fn param_lookup(&self, key: [u8; 3]) -> BResult<[u8; 8]> {
-        let timeout = Duration::from_secs(3);
+    // A timeout of None uses the default for the operation
+    fn param_lookup(&self, key: [u8; 3], timeout: Option<Duration>) -> BResult<[u8; 8]> {
+        let read_timeout = timeout.unwrap_or(self.get_timeouts().get_lookup());
 
         let mut request = [0; 4];
         request[0..3].copy_from_slice(&key);
         request[3] = 0xa3;
 
         // Write out the command request
-        self.get_usb_handle().write_bulk(0x03, &request, timeout)?;
+        self.get_transport().write(&request, read_timeout)?;
 
         // Grab the response into a buffer
         let mut buf = [0; 8];
-        self.get_usb_handle().read_bulk(0x83, &mut buf, timeout)?;
+        self.get_transport().read(&mut buf, read_timeout)?;
 
         // Validate the header...
         if buf[0..2] != request[0..2] || buf[3] != 0xa4 {
@@ -146,8 +480,22 @@ pub(crate) trait BeacnAudioMessageLocal:
         Ok(buf)
     }
 
-    fn param_set(&self, key: [u8; 3], value: [u8; 4]) -> BResult<[u8; 8]> {
-        let timeout = Duration::from_millis(200);
+    fn param_set(
+        &self,
+        key: [u8; 3],
+        value: [u8; 4],
+        timeout: Option<Duration>,
+        verify: VerifyMode,
+    ) -> BResult<ParamSetResult> {
+        let write_timeout = timeout.unwrap_or(self.get_timeouts().get_set());
+
+        // When asked to, grab the current value so we can tell whether the device ignored the
+        // change. This is only used to describe a mismatch, so failing to read it shouldn't
+        // prevent the set.
+        let previous = match verify {
+            VerifyMode::Always => self.param_lookup(key, timeout).ok(),
+            VerifyMode::Once | VerifyMode::Never => None,
+        };
 
         // Build the Set Request
         let mut request = [0; 8];
@@ -156,48 +504,61 @@ pub(crate) trait BeacnAudioMessageLocal:
         request[4..].copy_from_slice(&value);
 
         // Write out the command request
-        self.get_usb_handle().write_bulk(0x03, &request, timeout)?;
+        self.get_transport().write(&request, write_timeout)?;
+
+        // Without verification there's no response to report
+        if verify == VerifyMode::Never {
+            return Ok(ParamSetResult::Unverified);
+        }
 
         // Check whether the value has changed
-        let new_value = self.param_lookup(key)?;
+        let new_value = self.param_lookup(key, timeout)?;
 
         let old = &request[4..8];
         let new = &new_value[4..8];
 
         // Compare the new response
-        if old != new {
-            warn!(
-                "Value Set: {:?} does not match value on Device: {:?}",
-                old, new
-            );
-            beacn_bail!("Value was not changed on the device!");
+        if old == new {
+            return Ok(ParamSetResult::Applied(new_value));
+        }
+
+        warn!(
+            serial = self.get_serial().as_str();
+            "Value Set: {:?} does not match value on Device: {:?}",
+            old, new
+        );
+        if verify == VerifyMode::Once {
+            return Ok(ParamSetResult::Mismatched(new_value));
         }
-        Ok(new_value)
+        if previous.is_some_and(|previous| previous[4..8] == *new) {
+            return Ok(ParamSetResult::Rejected(new_value));
+        }
+        Ok(ParamSetResult::Adjusted(new_value))
     }
 
     /// Returns the Apps and their link configuration from PC2
-    fn get_linked_apps(&self) -> BResult<Option<Vec<LinkedApp>>> {
+    fn get_linked_apps(&self) -> BResult<Pc2Status> {
         let mut apps = vec![];
 
         if self.get_device_type() != DeviceType::BeacnStudio {
             beacn_bail!("This can only be executed on a Beacn Studio")
         }
 
-        let timeout = Duration::from_secs(3);
+        let timeout = self.get_timeouts().get_linked_apps();
 
         // Build the request
         let request = [0x00, 0x00, 0x01, 0xAC];
-        self.get_usb_handle().write_bulk(0x03, &request, timeout)?;
+        self.get_transport().write(&request, timeout)?;
 
         // TODO: Assuming max length of 1024, it might be higher
         let mut buf = [0; 1024];
-        self.get_usb_handle().read_bulk(0x83, &mut buf, timeout)?;
+        self.get_transport().read(&mut buf, timeout)?;
 
         // Extract the header
         let data_length = LittleEndian::read_u24(&buf[0..3]) as usize;
         if data_length == 0xFFFFFF {
             // No PC2 Connection
-            return Ok(None);
+            return Ok(Pc2Status::NotConnected);
         }
 
         let data = &buf[4..4 + data_length];
@@ -228,7 +589,7 @@ pub(crate) trait BeacnAudioMessageLocal:
 
         // Sort alphabetically
         apps.sort_by_key(|app| app.name.to_lowercase());
-        Ok(Some(apps))
+        Ok(Pc2Status::Connected(apps))
     }
 
     fn set_app_link(&self, link: LinkedApp) -> BResult<()> {
@@ -238,15 +599,28 @@ pub(crate) trait BeacnAudioMessageLocal:
 
         // Build the packet
         let name_bytes = link.name.as_bytes();
+        if name_bytes.is_empty() {
+            beacn_bail!("App name cannot be empty");
+        }
 
         // I'm honestly unsure about this, it seems to appear with every packet when moving
         // apps between channels, so I'll include it.
         let extra = [0x00, 0xcd, 0xcd, 0xcd, 0xcd, 0x00];
+
+        // The length is a single byte, so long names would wrap and corrupt the packet
+        let max_length = u8::MAX as usize - extra.len();
+        if name_bytes.len() > max_length {
+            beacn_bail!(
+                "App name is {} bytes, the maximum is {}",
+                name_bytes.len(),
+                max_length
+            );
+        }
         let length: u8 = (name_bytes.len() + extra.len()) as u8;
 
         let mut packet: Vec<u8> = Vec::with_capacity(2 + name_bytes.len() + 1 + extra.len());
         packet.push(length);
-        packet.push(link.channel as u8);
+        packet.push(link.channel.as_index());
         packet.extend_from_slice(name_bytes);
         packet.extend_from_slice(&extra);
 
@@ -254,44 +628,62 @@ pub(crate) trait BeacnAudioMessageLocal:
         LittleEndian::write_u24(&mut message[0..3], packet.len() as u32);
         message.extend_from_slice(&packet);
 
-        let timeout = Duration::from_secs(3);
-        self.get_usb_handle().write_bulk(0x03, &message, timeout)?;
+        let timeout = self.get_timeouts().get_linked_apps();
+        self.get_transport().write(&message, timeout)?;
 
         Ok(())
     }
 }
 
+// Runs an operation, and if it fails because the endpoint has stalled, clears the stall and runs
+// it one more time. If the device has gone away, we instead attempt to re-attach to it first.
+fn with_reconnect<T>(
+    transport: &dyn BeacnTransport,
+    serial: &str,
+    operation: impl Fn() -> BResult<T>,
+) -> BResult<T> {
+    match operation() {
+        Err(BeacnError::Usb(UsbError::Pipe)) => {
+            // The device is still attached, so we only need to clear the stalled endpoint
+            warn!(serial = serial; "USB Error: Pipe, attempting to clear the stall");
+            transport.clear_stall()?;
+            operation()
+        }
+        Err(BeacnError::Usb(UsbError::NoDevice)) => {
+            warn!(serial = serial; "USB Error: No Device, attempting to reconnect");
+            transport.reconnect()?;
+            operation()
+        }
+        result => result,
+    }
+}
+
 /// Simple function to Open a libusb connection to a Beacn Audio device, do initial setup and
 /// grab the firmware version from the device.
-pub(crate) fn open_beacn(def: DeviceDefinition, product_id: &[u16]) -> BResult<BeacnDeviceHandle> {
+pub(crate) fn open_beacn<C: UsbContext>(
+    def: DeviceDefinition<C>,
+    product_id: &[u16],
+) -> BResult<BeacnDeviceHandle<UsbTransport<C>, C>> {
     if !product_id.contains(&def.descriptor.product_id()) {
-        beacn_bail!(
-            "Expecting PIDs {:?} but got {}",
-            product_id,
-            def.descriptor.product_id()
-        );
+        return Err(BeacnError::UnexpectedPid {
+            expected: product_id.to_vec(),
+            found: def.descriptor.product_id(),
+        });
     }
 
-    let handle = def.device.open()?;
-    handle.claim_interface(3)?;
-    handle.set_alternate_setting(3, 1)?;
-    handle.clear_halt(0x83)?;
-
-    let setup_timeout = Duration::from_millis(2000);
-
-    let request = [0x00, 0x00, 0x00, 0xa0];
-    handle.write_bulk(0x03, &request, setup_timeout)?;
-
-    // Mic and Studio use bulk reads to get this data
-    let mut input = [0; 512];
-    let request = [0x00, 0x00, 0x00, 0xa1];
-    handle.write_bulk(0x03, &request, setup_timeout)?;
-    handle.read_bulk(0x83, &mut input, setup_timeout)?;
+    let timeouts = def.get_timeouts();
+    timeouts.validate()?;
 
-    // So, this is consistent between the Mix Create and the Mic :D
-    let (version, serial) = get_device_info(&input)?;
+    let location = DeviceLocation::from(def.device.clone());
+    let (handle, version, serial, extra_info) = with_open_retry(|| {
+        let handle = def.device.open()?;
+        let (version, serial, extra_info) = attach_beacn(&handle, timeouts.get_setup())?;
+        Ok((handle, version, serial, extra_info))
+    })?;
+    let serial = serial.unwrap_or_else(|| fallback_serial(location));
 
     debug!(
+        serial = serial.as_str();
         "Loaded Device, Location: {}.{}, Serial: {}, Version: {}",
         def.device.bus_number(),
         def.device.address(),
@@ -299,11 +691,42 @@ pub(crate) fn open_beacn(def: DeviceDefinition, product_id: &[u16]) -> BResult<B
         version
     );
 
+    let (manufacturer, product) = read_descriptor_strings(&handle, &def.descriptor);
+
     Ok(BeacnDeviceHandle {
         descriptor: def.descriptor,
+        handle: UsbTransport::new(handle, location, serial.clone(), timeouts.get_setup()),
         device: def.device,
-        handle,
         version,
         serial,
+        extra_info,
+        manufacturer,
+        product,
+        timeouts,
     })
 }
+
+/// Claims the audio interface on an open handle and performs the initial setup, returning the
+/// firmware version, serial and extra info reported by the device. This is used both when opening a device,
+/// and when re-attaching after the device has been lost.
+pub(crate) fn attach_beacn<C: UsbContext>(
+    handle: &DeviceHandle<C>,
+    setup_timeout: Duration,
+) -> BResult<(VersionNumber, Option<String>, Vec<u8>)> {
+    handle.claim_interface(3)?;
+    handle.set_alternate_setting(3, 1)?;
+    handle.clear_halt(0x83)?;
+
+    let request = [0x00, 0x00, 0x00, 0xa0];
+    handle.write_bulk(0x03, &request, setup_timeout)?;
+
+    // Mic and Studio use bulk reads to get this data
+    let mut input = [0; 512];
+    let request = [0x00, 0x00, 0x00, 0xa1];
+    handle.write_bulk(0x03, &request, setup_timeout)?;
+    let length = handle.read_bulk(0x83, &mut input, setup_timeout)?;
+
+    // So, this is consistent between the Mix Create and the Mic :D
+    let (version, serial) = get_device_info(&input[..length])?;
+    Ok((version, serial, get_extra_device_info(&input[..length])))
+}
@@ -0,0 +1,84 @@
+use crate::audio::messages::Message;
+use std::collections::HashMap;
+
+/// The differences between two sets of values, see diff_profiles.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileDiff {
+    changed: Vec<Message>,
+    only_in_old: Vec<Message>,
+    only_in_new: Vec<Message>,
+}
+
+impl ProfileDiff {
+    /// The new values for parameters which are in both profiles, but have a different value
+    pub fn get_changed(&self) -> &[Message] {
+        &self.changed
+    }
+
+    /// Values for parameters which are missing from the new profile
+    pub fn get_only_in_old(&self) -> &[Message] {
+        &self.only_in_old
+    }
+
+    /// Values for parameters which are missing from the old profile
+    pub fn get_only_in_new(&self) -> &[Message] {
+        &self.only_in_new
+    }
+
+    /// Returns true if both profiles hold the same parameters, with the same values
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.only_in_old.is_empty() && self.only_in_new.is_empty()
+    }
+
+    /// Returns true if a parameter is only present in one of the profiles, this normally means
+    /// the profiles came from different device types (or firmware versions)
+    pub fn has_mismatch(&self) -> bool {
+        !self.only_in_old.is_empty() || !self.only_in_new.is_empty()
+    }
+}
+
+/// Compares two sets of values (for example, from DeviceSnapshot::get_messages), matching them
+/// up by parameter. Only messages carrying a value are compared, getters are ignored, and if a
+/// parameter appears more than once in a profile, the last value is used.
+///
+/// Parameters which are only present in one of the profiles are reported separately, rather
+/// than being treated as a change, see ProfileDiff::has_mismatch.
+pub fn diff_profiles(old: &[Message], new: &[Message]) -> ProfileDiff {
+    let old_values = by_parameter(old);
+    let new_values = by_parameter(new);
+    let old_lookup: HashMap<_, _> = old_values.iter().copied().collect();
+    let new_lookup: HashMap<_, _> = new_values.iter().copied().collect();
+
+    let mut diff = ProfileDiff::default();
+    for (key, message) in &new_values {
+        match old_lookup.get(key) {
+            Some(old_message) if old_message == message => {}
+            Some(_) => diff.changed.push(*message),
+            None => diff.only_in_new.push(*message),
+        }
+    }
+    for (key, message) in &old_values {
+        if !new_lookup.contains_key(key) {
+            diff.only_in_old.push(*message);
+        }
+    }
+    diff
+}
+
+// Keeps the value carrying messages in their original order, with later values for a parameter
+// replacing earlier ones
+fn by_parameter(messages: &[Message]) -> Vec<([u8; 3], Message)> {
+    let mut values: Vec<([u8; 3], Message)> = vec![];
+    let mut positions = HashMap::new();
+    for message in messages.iter().filter(|m| m.is_device_message_set()) {
+        let key = message.to_beacn_key();
+        match positions.get(&key) {
+            Some(&position) => values[position] = (key, *message),
+            None => {
+                positions.insert(key, values.len());
+                values.push((key, *message));
+            }
+        }
+    }
+    values
+}
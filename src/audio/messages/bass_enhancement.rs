@@ -1,13 +1,15 @@
 use crate::audio::messages::bass_enhancement::BassPreset::{Preset1, Preset2, Preset3, Preset4};
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
 use crate::types::{
-    BeacnValue, MakeUpGain, Percent, ReadBeacn, TimeFrame, WriteBeacn, read_value, write_value,
+    BeacnValue, HasRange, MakeUpGain, Percent, TimeFrame, TryReadBeacn, WriteBeacn, beacn_enum,
+    try_read_value, try_write_value,
 };
-use byteorder::{ByteOrder, LittleEndian};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
-use strum::{EnumIter, IntoEnumIterator};
+use std::fmt::{Display, Formatter};
+use strum::EnumIter;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum BassEnhancement {
@@ -61,6 +63,43 @@ pub enum BassEnhancement {
     LowerQ(BassQ),
 }
 
+impl Display for BassEnhancement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BassEnhancement::GetDrive => write!(f, "Drive"),
+            BassEnhancement::Drive(v) => write!(f, "Drive = {}", v),
+            BassEnhancement::GetMix => write!(f, "Mix"),
+            BassEnhancement::Mix(v) => write!(f, "Mix = {}", v),
+            BassEnhancement::GetEnabled => write!(f, "Enabled"),
+            BassEnhancement::Enabled(v) => write!(f, "Enabled = {}", v),
+            BassEnhancement::GetPreset => write!(f, "Preset"),
+            BassEnhancement::Preset(v) => write!(f, "Preset = {:?}", v),
+            BassEnhancement::GetAmount => write!(f, "Amount"),
+            BassEnhancement::Amount(v) => write!(f, "Amount = {}", v),
+            BassEnhancement::GetAttack => write!(f, "Attack"),
+            BassEnhancement::Attack(v) => write!(f, "Attack = {}", v),
+            BassEnhancement::GetRelease => write!(f, "Release"),
+            BassEnhancement::Release(v) => write!(f, "Release = {}", v),
+            BassEnhancement::GetThreshold => write!(f, "Threshold"),
+            BassEnhancement::Threshold(v) => write!(f, "Threshold = {}", v),
+            BassEnhancement::GetKnee => write!(f, "Knee"),
+            BassEnhancement::Knee(v) => write!(f, "Knee = {}", v),
+            BassEnhancement::GetMakeupGain => write!(f, "Makeup Gain"),
+            BassEnhancement::MakeupGain(v) => write!(f, "Makeup Gain = {}", v),
+            BassEnhancement::GetRatio => write!(f, "Ratio"),
+            BassEnhancement::Ratio(v) => write!(f, "Ratio = {}", v),
+            BassEnhancement::GetCutoff => write!(f, "Cutoff"),
+            BassEnhancement::Cutoff(v) => write!(f, "Cutoff = {}", v),
+            BassEnhancement::GetQ => write!(f, "Q"),
+            BassEnhancement::Q(v) => write!(f, "Q = {}", v),
+            BassEnhancement::GetLowerCutoff => write!(f, "Lower Cutoff"),
+            BassEnhancement::LowerCutoff(v) => write!(f, "Lower Cutoff = {}", v),
+            BassEnhancement::GetLowerQ => write!(f, "Lower Q"),
+            BassEnhancement::LowerQ(v) => write!(f, "Lower Q = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for BassEnhancement {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -87,6 +126,67 @@ impl BeacnSubMessage for BassEnhancement {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            BassEnhancement::Drive(_) => BassEnhancement::GetDrive,
+            BassEnhancement::Mix(_) => BassEnhancement::GetMix,
+            BassEnhancement::Enabled(_) => BassEnhancement::GetEnabled,
+            BassEnhancement::Preset(_) => BassEnhancement::GetPreset,
+            BassEnhancement::Amount(_) => BassEnhancement::GetAmount,
+            BassEnhancement::Attack(_) => BassEnhancement::GetAttack,
+            BassEnhancement::Release(_) => BassEnhancement::GetRelease,
+            BassEnhancement::Threshold(_) => BassEnhancement::GetThreshold,
+            BassEnhancement::Knee(_) => BassEnhancement::GetKnee,
+            BassEnhancement::MakeupGain(_) => BassEnhancement::GetMakeupGain,
+            BassEnhancement::Ratio(_) => BassEnhancement::GetRatio,
+            BassEnhancement::Cutoff(_) => BassEnhancement::GetCutoff,
+            BassEnhancement::Q(_) => BassEnhancement::GetQ,
+            BassEnhancement::LowerCutoff(_) => BassEnhancement::GetLowerCutoff,
+            BassEnhancement::LowerQ(_) => BassEnhancement::GetLowerQ,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            BassEnhancement::GetDrive | BassEnhancement::Drive(..) => {
+                ValueType::float::<BassDrive>()
+            }
+            BassEnhancement::GetMix | BassEnhancement::Mix(..) => ValueType::float::<Percent>(),
+            BassEnhancement::GetEnabled | BassEnhancement::Enabled(..) => ValueType::Bool,
+            BassEnhancement::GetPreset | BassEnhancement::Preset(..) => {
+                ValueType::enumerated::<BassPreset>()
+            }
+            BassEnhancement::GetAmount | BassEnhancement::Amount(..) => {
+                ValueType::float::<BassAmount>()
+            }
+            BassEnhancement::GetAttack | BassEnhancement::Attack(..) => {
+                ValueType::float::<TimeFrame>()
+            }
+            BassEnhancement::GetRelease | BassEnhancement::Release(..) => {
+                ValueType::float::<TimeFrame>()
+            }
+            BassEnhancement::GetThreshold | BassEnhancement::Threshold(..) => {
+                ValueType::float::<BassThreshold>()
+            }
+            BassEnhancement::GetKnee | BassEnhancement::Knee(..) => ValueType::float::<BassKnee>(),
+            BassEnhancement::GetMakeupGain | BassEnhancement::MakeupGain(..) => {
+                ValueType::float::<MakeUpGain>()
+            }
+            BassEnhancement::GetRatio | BassEnhancement::Ratio(..) => {
+                ValueType::float::<BassRatio>()
+            }
+            BassEnhancement::GetCutoff | BassEnhancement::Cutoff(..) => {
+                ValueType::float::<BassCutoff>()
+            }
+            BassEnhancement::GetQ | BassEnhancement::Q(..) => ValueType::float::<BassQ>(),
+            BassEnhancement::GetLowerCutoff | BassEnhancement::LowerCutoff(..) => {
+                ValueType::float::<BassCutoff>()
+            }
+            BassEnhancement::GetLowerQ | BassEnhancement::LowerQ(..) => ValueType::float::<BassQ>(),
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             BassEnhancement::Attack(_) | BassEnhancement::GetAttack => [0x00, 0x00],
@@ -107,46 +207,46 @@ impl BeacnSubMessage for BassEnhancement {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            BassEnhancement::Drive(v) => write_value(v),
-            BassEnhancement::Mix(v) => write_value(v),
-            BassEnhancement::Enabled(v) => v.write_beacn(),
-            BassEnhancement::Preset(v) => v.write_beacn(),
-            BassEnhancement::Amount(v) => write_value(v),
-            BassEnhancement::Attack(v) => write_value(v),
-            BassEnhancement::Release(v) => write_value(v),
-            BassEnhancement::Threshold(v) => write_value(v),
-            BassEnhancement::Knee(v) => write_value(v),
-            BassEnhancement::MakeupGain(v) => write_value(v),
-            BassEnhancement::Ratio(v) => write_value(v),
-            BassEnhancement::Cutoff(v) => write_value(v),
-            BassEnhancement::Q(v) => write_value(v),
-            BassEnhancement::LowerCutoff(v) => write_value(v),
-            BassEnhancement::LowerQ(v) => write_value(v),
-            _ => panic!("Attempting to Set value for Getter"),
+            BassEnhancement::Drive(v) => try_write_value(v),
+            BassEnhancement::Mix(v) => try_write_value(v),
+            BassEnhancement::Enabled(v) => Ok(v.write_beacn()),
+            BassEnhancement::Preset(v) => Ok(v.write_beacn()),
+            BassEnhancement::Amount(v) => try_write_value(v),
+            BassEnhancement::Attack(v) => try_write_value(v),
+            BassEnhancement::Release(v) => try_write_value(v),
+            BassEnhancement::Threshold(v) => try_write_value(v),
+            BassEnhancement::Knee(v) => try_write_value(v),
+            BassEnhancement::MakeupGain(v) => try_write_value(v),
+            BassEnhancement::Ratio(v) => try_write_value(v),
+            BassEnhancement::Cutoff(v) => try_write_value(v),
+            BassEnhancement::Q(v) => try_write_value(v),
+            BassEnhancement::LowerCutoff(v) => try_write_value(v),
+            BassEnhancement::LowerQ(v) => try_write_value(v),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        match key[0] {
-            0x00 => Self::Attack(read_value(&value)),
-            0x01 => Self::Release(read_value(&value)),
-            0x02 => Self::Threshold(read_value(&value)),
-            0x03 => Self::Knee(read_value(&value)),
-            0x04 => Self::MakeupGain(read_value(&value)),
-            0x05 => Self::Ratio(read_value(&value)),
-            0x06 => Self::Cutoff(read_value(&value)),
-            0x07 => Self::Q(read_value(&value)),
-            0x08 => Self::LowerCutoff(read_value(&value)),
-            0x09 => Self::LowerQ(read_value(&value)),
-            0x0a => Self::Drive(read_value(&value)),
-            0x0b => Self::Mix(read_value(&value)),
-            0x0c => Self::Enabled(bool::read_beacn(&value)),
-            0x0d => Self::Preset(BassPreset::read_beacn(&value)),
-            0x0e => Self::Amount(read_value(&value)),
-            _ => panic!("Unexpected Bass Enhancement Key: {}", key[0]),
-        }
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x00 => Self::Attack(try_read_value(&value)?),
+            0x01 => Self::Release(try_read_value(&value)?),
+            0x02 => Self::Threshold(try_read_value(&value)?),
+            0x03 => Self::Knee(try_read_value(&value)?),
+            0x04 => Self::MakeupGain(try_read_value(&value)?),
+            0x05 => Self::Ratio(try_read_value(&value)?),
+            0x06 => Self::Cutoff(try_read_value(&value)?),
+            0x07 => Self::Q(try_read_value(&value)?),
+            0x08 => Self::LowerCutoff(try_read_value(&value)?),
+            0x09 => Self::LowerQ(try_read_value(&value)?),
+            0x0a => Self::Drive(try_read_value(&value)?),
+            0x0b => Self::Mix(try_read_value(&value)?),
+            0x0c => Self::Enabled(bool::try_read_beacn(&value)?),
+            0x0d => Self::Preset(BassPreset::try_read_beacn(&value)?),
+            0x0e => Self::Amount(try_read_value(&value)?),
+            _ => beacn_bail!("Unexpected Bass Enhancement Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -235,6 +335,16 @@ impl BassEnhancement {
             Message::BassEnhancement(BassEnhancement::Mix(Percent(amount * 10.0))),
         ]
     }
+
+    /// The reverse of get_amount, returns the amount that would produce this drive and mix, or
+    /// None if they don't correspond to an amount
+    pub fn amount_from_values(drive: BassDrive, mix: Percent) -> Option<f32> {
+        let amount = mix.0 / 10.0;
+        if !BassAmount::range().contains(&amount) || (drive.0 - 3.2 * amount).abs() > 0.01 {
+            return None;
+        }
+        Some(amount)
+    }
 }
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
@@ -246,31 +356,12 @@ pub enum BassPreset {
     Preset4 = 0x03,
 }
 
-impl crate::types::sealed::Sealed for BassPreset {}
-impl WriteBeacn for BassPreset {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_f32(&mut buf, *self as u8 as f32);
-        buf
-    }
-}
-
-impl ReadBeacn for BassPreset {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_f32(buf);
-        for var in Self::iter() {
-            if var as u8 as f32 == value {
-                return var;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
+beacn_enum!(BassPreset, f32);
 
 generate_range!(BassDrive, f32, 0.0..=32.0);
 generate_range!(BassAmount, f32, 0.0..=10.0);
-generate_range!(BassThreshold, f32, -50.0..=0.0);
-generate_range!(BassKnee, f32, 0.0..=5.0);
+generate_range!(BassThreshold, f32, -50.0..=0.0, "dB");
+generate_range!(BassKnee, f32, 0.0..=5.0, "dB");
 generate_range!(BassRatio, f32, 0.0..=16.0);
-generate_range!(BassCutoff, f32, 0.0..=160.0);
+generate_range!(BassCutoff, f32, 0.0..=160.0, "Hz");
 generate_range!(BassQ, f32, 0.0..=16.0);
@@ -1,13 +1,13 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
 use crate::types::{
-    BeacnValue, MakeUpGain, PackedEnumKey, ReadBeacn, TimeFrame, WriteBeacn, read_value,
-    write_value,
+    BeacnValue, MakeUpGain, PackedEnumKey, TimeFrame, TryReadBeacn, WriteBeacn, beacn_enum,
+    try_read_value, try_write_value,
 };
-use byteorder::{ByteOrder, LittleEndian};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use strum::{EnumIter, IntoEnumIterator};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -34,6 +34,27 @@ pub enum Compressor {
     Enabled(CompressorMode, bool),
 }
 
+impl Display for Compressor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compressor::GetMode => write!(f, "Mode"),
+            Compressor::Mode(v) => write!(f, "Mode = {:?}", v),
+            Compressor::GetAttack(a) => write!(f, "{:?} Attack", a),
+            Compressor::Attack(a, v) => write!(f, "{:?} Attack = {}", a, v),
+            Compressor::GetRelease(a) => write!(f, "{:?} Release", a),
+            Compressor::Release(a, v) => write!(f, "{:?} Release = {}", a, v),
+            Compressor::GetThreshold(a) => write!(f, "{:?} Threshold", a),
+            Compressor::Threshold(a, v) => write!(f, "{:?} Threshold = {}", a, v),
+            Compressor::GetRatio(a) => write!(f, "{:?} Ratio", a),
+            Compressor::Ratio(a, v) => write!(f, "{:?} Ratio = {}", a, v),
+            Compressor::GetMakeupGain(a) => write!(f, "{:?} Makeup Gain", a),
+            Compressor::MakeupGain(a, v) => write!(f, "{:?} Makeup Gain = {}", a, v),
+            Compressor::GetEnabled(a) => write!(f, "{:?} Enabled", a),
+            Compressor::Enabled(a, v) => write!(f, "{:?} Enabled = {}", a, v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Compressor {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -52,6 +73,37 @@ impl BeacnSubMessage for Compressor {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Compressor::Mode(_) => Compressor::GetMode,
+            Compressor::Attack(m, _) => Compressor::GetAttack(*m),
+            Compressor::Release(m, _) => Compressor::GetRelease(*m),
+            Compressor::Threshold(m, _) => Compressor::GetThreshold(*m),
+            Compressor::Ratio(m, _) => Compressor::GetRatio(*m),
+            Compressor::MakeupGain(m, _) => Compressor::GetMakeupGain(*m),
+            Compressor::Enabled(m, _) => Compressor::GetEnabled(*m),
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Compressor::GetMode | Compressor::Mode(..) => ValueType::enumerated::<CompressorMode>(),
+            Compressor::GetAttack(..) | Compressor::Attack(..) => ValueType::float::<TimeFrame>(),
+            Compressor::GetRelease(..) | Compressor::Release(..) => ValueType::float::<TimeFrame>(),
+            Compressor::GetThreshold(..) | Compressor::Threshold(..) => {
+                ValueType::float::<CompressorThreshold>()
+            }
+            Compressor::GetRatio(..) | Compressor::Ratio(..) => {
+                ValueType::float::<CompressorRatio>()
+            }
+            Compressor::GetMakeupGain(..) | Compressor::MakeupGain(..) => {
+                ValueType::float::<MakeUpGain>()
+            }
+            Compressor::GetEnabled(..) | Compressor::Enabled(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Compressor::Mode(_) | Compressor::GetMode => [0, 0],
@@ -77,35 +129,37 @@ impl BeacnSubMessage for Compressor {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Compressor::Mode(v) => v.write_beacn(),
-            Compressor::Attack(_, v) => write_value(v),
-            Compressor::Release(_, v) => write_value(v),
-            Compressor::Threshold(_, v) => write_value(v),
-            Compressor::Ratio(_, v) => write_value(v),
-            Compressor::MakeupGain(_, v) => write_value(v),
-            Compressor::Enabled(_, v) => v.write_beacn(),
-            _ => panic!("Attempted to Set on a Get"),
+            Compressor::Mode(v) => Ok(v.write_beacn()),
+            Compressor::Attack(_, v) => try_write_value(v),
+            Compressor::Release(_, v) => try_write_value(v),
+            Compressor::Threshold(_, v) => try_write_value(v),
+            Compressor::Ratio(_, v) => try_write_value(v),
+            Compressor::MakeupGain(_, v) => try_write_value(v),
+            Compressor::Enabled(_, v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
         if key == [0, 0] {
-            return Self::Mode(CompressorMode::read_beacn(&value));
+            return Ok(Self::Mode(CompressorMode::try_read_beacn(&value)?));
         }
 
         // For any other value, we need to unpack the key.
-        let key = PackedEnumKey::from_encoded(key[0]).unwrap();
+        let Some(key) = PackedEnumKey::from_encoded(key[0]) else {
+            beacn_bail!("Unable to decode packed key {:#04x}", key[0]);
+        };
         let mode = key.get_upper();
-        match key.get_lower() {
-            CompressorKeys::Attack => Self::Attack(mode, read_value(&value)),
-            CompressorKeys::Release => Self::Release(mode, read_value(&value)),
-            CompressorKeys::Threshold => Self::Threshold(mode, read_value(&value)),
-            CompressorKeys::Ratio => Self::Ratio(mode, read_value(&value)),
-            CompressorKeys::MakeupGain => Self::MakeupGain(mode, read_value(&value)),
-            CompressorKeys::Enabled => Self::Enabled(mode, bool::read_beacn(&value)),
-        }
+        Ok(match key.get_lower() {
+            CompressorKeys::Attack => Self::Attack(mode, try_read_value(&value)?),
+            CompressorKeys::Release => Self::Release(mode, try_read_value(&value)?),
+            CompressorKeys::Threshold => Self::Threshold(mode, try_read_value(&value)?),
+            CompressorKeys::Ratio => Self::Ratio(mode, try_read_value(&value)?),
+            CompressorKeys::MakeupGain => Self::MakeupGain(mode, try_read_value(&value)?),
+            CompressorKeys::Enabled => Self::Enabled(mode, bool::try_read_beacn(&value)?),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -123,7 +177,33 @@ impl BeacnSubMessage for Compressor {
     }
 }
 
-generate_range!(CompressorThreshold, f32, -50.0..=0.0);
+impl Compressor {
+    /// Generates the Simple mode messages for a single 0..=100 'amount', in the same way the
+    /// Subwoofer and Bass Enhancement amounts work. The ratio follows the firmware's simple
+    /// mode formula (see CompressorKeys below), with the percentage taken as 0..=1. At 0 the
+    /// formula gives a ratio of 0, which isn't valid, so 1:1 is used and the compressor is
+    /// disabled instead.
+    ///
+    /// Only the ratio is derived from the amount, the attack, release, threshold and makeup
+    /// gain the Beacn App uses alongside it are unknown, so they're left as they are.
+    pub fn simple_amount(amount: f32) -> Vec<Message> {
+        let amount = amount.clamp(0.0, 100.0);
+        let mode = CompressorMode::Simple;
+
+        let ratio = if amount == 0.0 {
+            1.0
+        } else {
+            1.0 + (amount / 100.0 * 0.9)
+        };
+
+        vec![
+            Message::Compressor(Compressor::Ratio(mode, CompressorRatio(ratio))),
+            Message::Compressor(Compressor::Enabled(mode, amount > 0.0)),
+        ]
+    }
+}
+
+generate_range!(CompressorThreshold, f32, -50.0..=0.0, "dB");
 generate_range!(CompressorRatio, f32, 1.0..=16.0);
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
@@ -138,25 +218,7 @@ impl From<CompressorMode> for u8 {
     }
 }
 
-impl Sealed for CompressorMode {}
-impl WriteBeacn for CompressorMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
-impl ReadBeacn for CompressorMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
-            }
-        }
-        panic!("Unable to Locate Value")
-    }
-}
+beacn_enum!(CompressorMode);
 
 #[derive(Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 enum CompressorKeys {
@@ -172,3 +234,28 @@ impl From<CompressorKeys> for u8 {
         value as u8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simple_ratio(amount: f32) -> f32 {
+        let ratio =
+            Compressor::simple_amount(amount)
+                .into_iter()
+                .find_map(|message| match message {
+                    Message::Compressor(Compressor::Ratio(CompressorMode::Simple, ratio)) => {
+                        Some(ratio)
+                    }
+                    _ => None,
+                });
+        ratio.expect("simple_amount didn't produce a ratio").0
+    }
+
+    #[test]
+    fn simple_amount_ratio() {
+        assert_eq!(simple_ratio(0.0), 1.0);
+        assert!((simple_ratio(50.0) - 1.45).abs() < f32::EPSILON);
+        assert!((simple_ratio(100.0) - 1.9).abs() < f32::EPSILON);
+    }
+}
@@ -1,6 +1,11 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
 use crate::manager::DeviceType;
-use crate::types::{BeacnValue, Percent, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, Percent, TryReadBeacn, WriteBeacn, try_read_value, try_write_value,
+};
+use crate::{BResult, beacn_bail};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum DeEsser {
@@ -11,6 +16,17 @@ pub enum DeEsser {
     Enabled(bool),
 }
 
+impl Display for DeEsser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeEsser::GetAmount => write!(f, "Amount"),
+            DeEsser::Amount(v) => write!(f, "Amount = {}", v),
+            DeEsser::GetEnabled => write!(f, "Enabled"),
+            DeEsser::Enabled(v) => write!(f, "Enabled = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for DeEsser {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -20,6 +36,21 @@ impl BeacnSubMessage for DeEsser {
         matches!(self, DeEsser::Enabled(_) | DeEsser::Amount(_))
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            DeEsser::Amount(_) => DeEsser::GetAmount,
+            DeEsser::Enabled(_) => DeEsser::GetEnabled,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            DeEsser::GetAmount | DeEsser::Amount(..) => ValueType::float::<Percent>(),
+            DeEsser::GetEnabled | DeEsser::Enabled(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             DeEsser::Amount(_) | DeEsser::GetAmount => [0x03, 0x00],
@@ -27,20 +58,20 @@ impl BeacnSubMessage for DeEsser {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            DeEsser::Amount(v) => write_value(v),
-            DeEsser::Enabled(v) => v.write_beacn(),
-            _ => panic!("Attmpted to Set a Get"),
+            DeEsser::Amount(v) => try_write_value(v),
+            DeEsser::Enabled(v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        match key[0] {
-            0x03 => Self::Amount(read_value(&value)),
-            0x04 => Self::Enabled(bool::read_beacn(&value)),
-            _ => panic!("Unexpected Key: {}", key[0]),
-        }
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x03 => Self::Amount(try_read_value(&value)?),
+            0x04 => Self::Enabled(bool::try_read_beacn(&value)?),
+            _ => beacn_bail!("Unexpected De-Esser Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -1,11 +1,14 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::types::{BeacnValue, PackedEnumKey, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, HasRange, PackedEnumKey, TryReadBeacn, WriteBeacn, beacn_enum, try_read_value,
+    try_write_value,
+};
 
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
-use byteorder::{ByteOrder, LittleEndian};
+use crate::{BResult, beacn_bail, check_finite, generate_range, out_of_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use strum::{EnumIter, IntoEnumIterator};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -29,6 +32,25 @@ pub enum Equaliser {
     Enabled(EQMode, EQBand, bool),
 }
 
+impl Display for Equaliser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Equaliser::GetMode => write!(f, "Mode"),
+            Equaliser::Mode(v) => write!(f, "Mode = {:?}", v),
+            Equaliser::GetType(a, b) => write!(f, "{:?} {:?} Type", a, b),
+            Equaliser::Type(a, b, v) => write!(f, "{:?} {:?} Type = {:?}", a, b, v),
+            Equaliser::GetGain(a, b) => write!(f, "{:?} {:?} Gain", a, b),
+            Equaliser::Gain(a, b, v) => write!(f, "{:?} {:?} Gain = {}", a, b, v),
+            Equaliser::GetFrequency(a, b) => write!(f, "{:?} {:?} Frequency", a, b),
+            Equaliser::Frequency(a, b, v) => write!(f, "{:?} {:?} Frequency = {}", a, b, v),
+            Equaliser::GetQ(a, b) => write!(f, "{:?} {:?} Q", a, b),
+            Equaliser::Q(a, b, v) => write!(f, "{:?} {:?} Q = {}", a, b, v),
+            Equaliser::GetEnabled(a, b) => write!(f, "{:?} {:?} Enabled", a, b),
+            Equaliser::Enabled(a, b, v) => write!(f, "{:?} {:?} Enabled = {}", a, b, v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Equaliser {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -46,6 +68,31 @@ impl BeacnSubMessage for Equaliser {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Equaliser::Mode(_) => Equaliser::GetMode,
+            Equaliser::Type(m, b, _) => Equaliser::GetType(*m, *b),
+            Equaliser::Gain(m, b, _) => Equaliser::GetGain(*m, *b),
+            Equaliser::Frequency(m, b, _) => Equaliser::GetFrequency(*m, *b),
+            Equaliser::Q(m, b, _) => Equaliser::GetQ(*m, *b),
+            Equaliser::Enabled(m, b, _) => Equaliser::GetEnabled(*m, *b),
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Equaliser::GetMode | Equaliser::Mode(..) => ValueType::enumerated::<EQMode>(),
+            Equaliser::GetType(..) | Equaliser::Type(..) => ValueType::enumerated::<EQBandType>(),
+            Equaliser::GetGain(..) | Equaliser::Gain(..) => ValueType::float::<EQGain>(),
+            Equaliser::GetFrequency(..) | Equaliser::Frequency(..) => {
+                ValueType::float::<EQFrequency>()
+            }
+            Equaliser::GetQ(..) | Equaliser::Q(..) => ValueType::float::<EQQ>(),
+            Equaliser::GetEnabled(..) | Equaliser::Enabled(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Equaliser::Mode(_) | Equaliser::GetMode => [0x00, 0x00],
@@ -71,34 +118,38 @@ impl BeacnSubMessage for Equaliser {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Equaliser::Mode(v) => v.write_beacn(),
-            Equaliser::Type(_, _, v) => v.write_beacn(),
-            Equaliser::Gain(_, _, v) => write_value(v),
-            Equaliser::Frequency(_, _, v) => write_value(v),
-            Equaliser::Q(_, _, v) => write_value(v),
-            Equaliser::Enabled(_, _, v) => v.write_beacn(),
-            _ => panic!("Attempted to Set a Getter"),
+            Equaliser::Mode(v) => Ok(v.write_beacn()),
+            Equaliser::Type(_, _, v) => Ok(v.write_beacn()),
+            Equaliser::Gain(_, _, v) => try_write_value(v),
+            Equaliser::Frequency(_, _, v) => try_write_value(v),
+            Equaliser::Q(_, _, v) => try_write_value(v),
+            Equaliser::Enabled(_, _, v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
         // This one's kinda interesting, we need to first check for 00,00..
         if key == [0x00, 0x00] {
-            return Self::Mode(EQMode::read_beacn(&value));
+            return Ok(Self::Mode(EQMode::try_read_beacn(&value)?));
         }
 
-        let mode = EQMode::from(key[1]);
-        let key = PackedEnumKey::from_encoded(key[0]).unwrap();
+        let Some(mode) = EQMode::iter().find(|mode| *mode as u8 == key[1]) else {
+            beacn_bail!("Unknown EQ Mode {:#04x}", key[1]);
+        };
+        let Some(key) = PackedEnumKey::from_encoded(key[0]) else {
+            beacn_bail!("Unable to decode packed key {:#04x}", key[0]);
+        };
         let band = key.get_upper();
-        match key.get_lower() {
-            EqualiserKeys::Q => Self::Q(mode, band, read_value(&value)),
-            EqualiserKeys::Type => Self::Type(mode, band, EQBandType::read_beacn(&value)),
-            EqualiserKeys::Gain => Self::Gain(mode, band, read_value(&value)),
-            EqualiserKeys::Frequency => Self::Frequency(mode, band, read_value(&value)),
-            EqualiserKeys::Enabled => Self::Enabled(mode, band, bool::read_beacn(&value)),
-        }
+        Ok(match key.get_lower() {
+            EqualiserKeys::Q => Self::Q(mode, band, try_read_value(&value)?),
+            EqualiserKeys::Type => Self::Type(mode, band, EQBandType::try_read_beacn(&value)?),
+            EqualiserKeys::Gain => Self::Gain(mode, band, try_read_value(&value)?),
+            EqualiserKeys::Frequency => Self::Frequency(mode, band, try_read_value(&value)?),
+            EqualiserKeys::Enabled => Self::Enabled(mode, band, bool::try_read_beacn(&value)?),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -120,8 +171,151 @@ impl BeacnSubMessage for Equaliser {
     }
 }
 
-generate_range!(EQGain, f32, -12.0..=12.0);
-generate_range!(EQFrequency, f32, 20.0..=20000.0);
+impl Equaliser {
+    /// Generates the messages needed to fully configure a single band, in the order the
+    /// values should be sent.
+    pub fn band(mode: EQMode, band: EQBand, config: EqBandConfig) -> Vec<Message> {
+        vec![
+            Message::Equaliser(Equaliser::Type(mode, band, config.band_type)),
+            Message::Equaliser(Equaliser::Gain(mode, band, config.gain)),
+            Message::Equaliser(Equaliser::Frequency(mode, band, config.frequency)),
+            Message::Equaliser(Equaliser::Q(mode, band, config.q)),
+            Message::Equaliser(Equaliser::Enabled(mode, band, config.enabled)),
+        ]
+    }
+
+    /// Generates a set of messages which will 'flatten' all bands of the EQ in the specified
+    /// mode, every band is reset to a 0dB Bell, and disabled.
+    pub fn flat(mode: EQMode) -> Vec<Message> {
+        let frequencies = [60.0, 150.0, 400.0, 1000.0, 2400.0, 5000.0, 10000.0, 16000.0];
+
+        let mut messages = vec![];
+        for (band, frequency) in EQBand::iter().zip(frequencies) {
+            let config = EqBandConfig {
+                band_type: EQBandType::BellBand,
+                gain: EQGain(0.0),
+                frequency: EQFrequency(frequency),
+                q: EQQ(0.7),
+                enabled: false,
+            };
+            messages.append(&mut Self::band(mode, band, config));
+        }
+        messages
+    }
+
+    /// A general 'Broadcast' style preset, cuts the low rumble and mud, and adds some presence
+    /// and air to the top end.
+    pub fn broadcast_preset(mode: EQMode) -> Vec<Message> {
+        use EQBandType::{BellBand, HighPassFilter, HighShelf, LowPassFilter, LowShelf};
+
+        // Type, Gain, Frequency, Q, Enabled
+        let bands = [
+            (HighPassFilter, 0.0, 80.0, 0.7, true),
+            (LowShelf, 2.0, 120.0, 0.7, true),
+            (BellBand, -2.5, 300.0, 1.0, true),
+            (BellBand, -1.0, 800.0, 1.4, true),
+            (BellBand, 2.0, 3000.0, 1.0, true),
+            (BellBand, -1.5, 6500.0, 3.0, true),
+            (HighShelf, 2.0, 10000.0, 0.7, true),
+            (LowPassFilter, 0.0, 18000.0, 0.7, false),
+        ];
+
+        let mut messages = vec![];
+        for (band, (band_type, gain, frequency, q, enabled)) in EQBand::iter().zip(bands) {
+            let config = EqBandConfig {
+                band_type,
+                gain: EQGain(gain),
+                frequency: EQFrequency(frequency),
+                q: EQQ(q),
+                enabled,
+            };
+            messages.append(&mut Self::band(mode, band, config));
+        }
+        messages
+    }
+}
+
+/// The full configuration of a single EQ band, values are checked against their ranges when
+/// this is created, so the messages produced by `Equaliser::band` are always valid.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EqBandConfig {
+    band_type: EQBandType,
+    gain: EQGain,
+    frequency: EQFrequency,
+    q: EQQ,
+    enabled: bool,
+}
+
+impl EqBandConfig {
+    pub fn new(
+        band_type: EQBandType,
+        gain: f32,
+        frequency: f32,
+        q: f32,
+        enabled: bool,
+    ) -> BResult<Self> {
+        check_finite("Gain", gain)?;
+        check_finite("Frequency", frequency)?;
+        check_finite("Q", q)?;
+        if !EQGain::range().contains(&gain) {
+            return Err(out_of_range("Gain", gain, &EQGain::range()));
+        }
+        if !EQFrequency::range().contains(&frequency) {
+            return Err(out_of_range("Frequency", frequency, &EQFrequency::range()));
+        }
+        if !EQQ::range().contains(&q) {
+            return Err(out_of_range("Q", q, &EQQ::range()));
+        }
+
+        Ok(Self {
+            band_type,
+            gain: EQGain(gain),
+            frequency: EQFrequency(frequency),
+            q: EQQ(q),
+            enabled,
+        })
+    }
+
+    // Used when the values have come from the device, so are already in range
+    pub(crate) fn from_values(
+        band_type: EQBandType,
+        gain: EQGain,
+        frequency: EQFrequency,
+        q: EQQ,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            band_type,
+            gain,
+            frequency,
+            q,
+            enabled,
+        }
+    }
+
+    pub fn get_band_type(&self) -> EQBandType {
+        self.band_type
+    }
+
+    pub fn get_gain(&self) -> EQGain {
+        self.gain
+    }
+
+    pub fn get_frequency(&self) -> EQFrequency {
+        self.frequency
+    }
+
+    pub fn get_q(&self) -> EQQ {
+        self.q
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+generate_range!(EQGain, f32, -12.0..=12.0, "dB");
+generate_range!(EQFrequency, f32, 20.0..=20000.0, "Hz");
 generate_range!(EQQ, f32, -0.1..=10.0);
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
@@ -131,26 +325,7 @@ pub enum EQMode {
     Advanced = 0x01,
 }
 
-impl Sealed for EQMode {}
-impl WriteBeacn for EQMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
-
-impl ReadBeacn for EQMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
-            }
-        }
-        panic!("Unable to Locate Value")
-    }
-}
+beacn_enum!(EQMode);
 
 impl From<u8> for EQMode {
     fn from(value: u8) -> Self {
@@ -172,6 +347,9 @@ pub enum EQBand {
     Band5 = 0x04,
     Band6 = 0x05,
     Band7 = 0x06,
+
+    // The device skips 0x07 for the eighth band, so 0x07 is treated as reserved, and a
+    // packed key containing it will fail to decode rather than being mapped to a band.
     Band8 = 0x08,
 }
 impl From<EQBand> for u8 {
@@ -206,22 +384,45 @@ pub enum EQBandType {
     HighShelf = 0x06,
 }
 
-impl Sealed for EQBandType {}
-impl WriteBeacn for EQBandType {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
+beacn_enum!(EQBandType);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BeacnError;
+
+    #[test]
+    fn reserved_band_fails_to_decode() {
+        // Band nibble 0x07 with the Gain key, in Simple mode
+        let key = [0x72, EQMode::Simple as u8];
+        let value = try_write_value(&EQGain(0.0)).unwrap();
+
+        let result = Equaliser::from_beacn(key, value, DeviceType::BeacnMic);
+        assert!(result.is_err());
     }
-}
-impl ReadBeacn for EQBandType {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
+
+    #[test]
+    fn presets_round_trip() {
+        for mode in EQMode::iter() {
+            let messages = Equaliser::flat(mode)
+                .into_iter()
+                .chain(Equaliser::broadcast_preset(mode));
+
+            for message in messages {
+                let Message::Equaliser(message) = message else {
+                    panic!("Preset produced a non EQ message: {:?}", message);
+                };
+                let key = message.to_beacn_key();
+                let value = message.try_to_beacn_value().unwrap();
+                let decoded = Equaliser::from_beacn(key, value, DeviceType::BeacnMic).unwrap();
+                assert_eq!(decoded, message);
             }
         }
-        panic!("Unable to Locate Value {:?}", value)
+    }
+
+    #[test]
+    fn band_config_rejects_nan() {
+        let result = EqBandConfig::new(EQBandType::BellBand, f32::NAN, 1000.0, 1.0, true);
+        assert!(matches!(result, Err(BeacnError::ValueNotFinite { .. })));
     }
 }
@@ -1,8 +1,12 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::{BeacnValue, Percent, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, Percent, TryReadBeacn, WriteBeacn, try_read_value, try_write_value,
+};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use strum::EnumIter;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -17,6 +21,19 @@ pub enum Exciter {
     Enabled(bool),
 }
 
+impl Display for Exciter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exciter::GetAmount => write!(f, "Amount"),
+            Exciter::Amount(v) => write!(f, "Amount = {}", v),
+            Exciter::GetFrequency => write!(f, "Frequency"),
+            Exciter::Frequency(v) => write!(f, "Frequency = {}", v),
+            Exciter::GetEnabled => write!(f, "Enabled"),
+            Exciter::Enabled(v) => write!(f, "Enabled = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Exciter {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -29,6 +46,23 @@ impl BeacnSubMessage for Exciter {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Exciter::Amount(_) => Exciter::GetAmount,
+            Exciter::Frequency(_) => Exciter::GetFrequency,
+            Exciter::Enabled(_) => Exciter::GetEnabled,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Exciter::GetAmount | Exciter::Amount(..) => ValueType::float::<Percent>(),
+            Exciter::GetFrequency | Exciter::Frequency(..) => ValueType::float::<ExciterFreq>(),
+            Exciter::GetEnabled | Exciter::Enabled(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Exciter::Amount(_) | Exciter::GetAmount => [0x01, 0x00],
@@ -37,22 +71,22 @@ impl BeacnSubMessage for Exciter {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Exciter::Amount(v) => write_value(v),
-            Exciter::Frequency(v) => write_value(v),
-            Exciter::Enabled(v) => v.write_beacn(),
-            _ => panic!("Attempted to Set a Getter"),
+            Exciter::Amount(v) => try_write_value(v),
+            Exciter::Frequency(v) => try_write_value(v),
+            Exciter::Enabled(v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        match key[0] {
-            0x01 => Self::Amount(read_value(&value)),
-            0x02 => Self::Frequency(read_value(&value)),
-            0x03 => Self::Enabled(bool::read_beacn(&value)),
-            _ => panic!("Couldn't Find Key {}", key[0]),
-        }
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x01 => Self::Amount(try_read_value(&value)?),
+            0x02 => Self::Frequency(try_read_value(&value)?),
+            0x03 => Self::Enabled(bool::try_read_beacn(&value)?),
+            _ => beacn_bail!("Unexpected Exciter Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -71,4 +105,28 @@ pub enum ExciterKeys {
     Enabled = 0x03,   // bool
 }
 
-generate_range!(ExciterFreq, f32, 0.0..=5000.0);
+generate_range!(ExciterFreq, f32, 0.0..=5000.0, "Hz");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: Exciter) -> Exciter {
+        let key = message.to_beacn_key();
+        let value = message.try_to_beacn_value().unwrap();
+        Exciter::from_beacn(key, value, DeviceType::BeacnMic).unwrap()
+    }
+
+    #[test]
+    fn exciter_round_trip() {
+        let messages = [
+            Exciter::Amount(Percent(50.0)),
+            Exciter::Frequency(ExciterFreq(1200.0)),
+            Exciter::Enabled(false),
+            Exciter::Enabled(true),
+        ];
+        for message in messages {
+            assert_eq!(round_trip(message), message);
+        }
+    }
+}
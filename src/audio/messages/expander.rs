@@ -1,12 +1,13 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
 use crate::types::{
-    BeacnValue, PackedEnumKey, ReadBeacn, TimeFrame, WriteBeacn, read_value, write_value,
+    BeacnValue, PackedEnumKey, TimeFrame, TryReadBeacn, WriteBeacn, beacn_enum, try_read_value,
+    try_write_value,
 };
-use byteorder::{ByteOrder, LittleEndian};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use std::iter::Iterator;
 use strum::{EnumIter, IntoEnumIterator};
 
@@ -31,6 +32,25 @@ pub enum Expander {
     Release(ExpanderMode, TimeFrame),
 }
 
+impl Display for Expander {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expander::GetMode => write!(f, "Mode"),
+            Expander::Mode(v) => write!(f, "Mode = {:?}", v),
+            Expander::GetThreshold(a) => write!(f, "{:?} Threshold", a),
+            Expander::Threshold(a, v) => write!(f, "{:?} Threshold = {}", a, v),
+            Expander::GetRatio(a) => write!(f, "{:?} Ratio", a),
+            Expander::Ratio(a, v) => write!(f, "{:?} Ratio = {}", a, v),
+            Expander::GetEnabled(a) => write!(f, "{:?} Enabled", a),
+            Expander::Enabled(a, v) => write!(f, "{:?} Enabled = {}", a, v),
+            Expander::GetAttack(a) => write!(f, "{:?} Attack", a),
+            Expander::Attack(a, v) => write!(f, "{:?} Attack = {}", a, v),
+            Expander::GetRelease(a) => write!(f, "{:?} Release", a),
+            Expander::Release(a, v) => write!(f, "{:?} Release = {}", a, v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Expander {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -48,6 +68,31 @@ impl BeacnSubMessage for Expander {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Expander::Mode(_) => Expander::GetMode,
+            Expander::Threshold(m, _) => Expander::GetThreshold(*m),
+            Expander::Ratio(m, _) => Expander::GetRatio(*m),
+            Expander::Enabled(m, _) => Expander::GetEnabled(*m),
+            Expander::Attack(m, _) => Expander::GetAttack(*m),
+            Expander::Release(m, _) => Expander::GetRelease(*m),
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Expander::GetMode | Expander::Mode(..) => ValueType::enumerated::<ExpanderMode>(),
+            Expander::GetThreshold(..) | Expander::Threshold(..) => {
+                ValueType::float::<ExpanderThreshold>()
+            }
+            Expander::GetRatio(..) | Expander::Ratio(..) => ValueType::float::<ExpanderRatio>(),
+            Expander::GetEnabled(..) | Expander::Enabled(..) => ValueType::Bool,
+            Expander::GetAttack(..) | Expander::Attack(..) => ValueType::float::<TimeFrame>(),
+            Expander::GetRelease(..) | Expander::Release(..) => ValueType::float::<TimeFrame>(),
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Expander::GetMode | Expander::Mode(_) => [0x00, 0x00],
@@ -69,33 +114,35 @@ impl BeacnSubMessage for Expander {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Expander::Mode(v) => v.write_beacn(),
-            Expander::Threshold(_, v) => write_value(v),
-            Expander::Ratio(_, v) => write_value(v),
-            Expander::Enabled(_, v) => v.write_beacn(),
-            Expander::Attack(_, v) => write_value(v),
-            Expander::Release(_, v) => write_value(v),
-            _ => panic!("Attempted to Set a Getter"),
+            Expander::Mode(v) => Ok(v.write_beacn()),
+            Expander::Threshold(_, v) => try_write_value(v),
+            Expander::Ratio(_, v) => try_write_value(v),
+            Expander::Enabled(_, v) => Ok(v.write_beacn()),
+            Expander::Attack(_, v) => try_write_value(v),
+            Expander::Release(_, v) => try_write_value(v),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
         if key == [0x00, 0x00] {
-            return Self::Mode(ExpanderMode::read_beacn(&value));
+            return Ok(Self::Mode(ExpanderMode::try_read_beacn(&value)?));
         }
 
         // For any other value, we need to unpack the key.
-        let key = PackedEnumKey::from_encoded(key[0]).unwrap();
+        let Some(key) = PackedEnumKey::from_encoded(key[0]) else {
+            beacn_bail!("Unable to decode packed key {:#04x}", key[0]);
+        };
         let mode = key.get_upper();
-        match key.get_lower() {
-            ExpanderKeys::Threshold => Expander::Threshold(mode, read_value(&value)),
-            ExpanderKeys::Ratio => Expander::Ratio(mode, read_value(&value)),
-            ExpanderKeys::Enabled => Expander::Enabled(mode, bool::read_beacn(&value)),
-            ExpanderKeys::Attack => Expander::Attack(mode, read_value(&value)),
-            ExpanderKeys::Release => Expander::Release(mode, read_value(&value)),
-        }
+        Ok(match key.get_lower() {
+            ExpanderKeys::Threshold => Expander::Threshold(mode, try_read_value(&value)?),
+            ExpanderKeys::Ratio => Expander::Ratio(mode, try_read_value(&value)?),
+            ExpanderKeys::Enabled => Expander::Enabled(mode, bool::try_read_beacn(&value)?),
+            ExpanderKeys::Attack => Expander::Attack(mode, try_read_value(&value)?),
+            ExpanderKeys::Release => Expander::Release(mode, try_read_value(&value)?),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -115,7 +162,7 @@ impl BeacnSubMessage for Expander {
 }
 
 generate_range!(ExpanderRatio, f32, 1.0..=10.0);
-generate_range!(ExpanderThreshold, f32, -90.0..=0.0);
+generate_range!(ExpanderThreshold, f32, -90.0..=0.0, "dB");
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum ExpanderMode {
@@ -129,25 +176,7 @@ impl From<ExpanderMode> for u8 {
     }
 }
 
-impl Sealed for ExpanderMode {}
-impl WriteBeacn for ExpanderMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
-impl ReadBeacn for ExpanderMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
-            }
-        }
-        panic!("Unable to Locate Value")
-    }
-}
+beacn_enum!(ExpanderMode);
 
 #[derive(Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum ExpanderKeys {
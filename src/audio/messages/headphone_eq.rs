@@ -1,8 +1,12 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::{BeacnValue, PackedEnumKey, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, PackedEnumKey, TryReadBeacn, WriteBeacn, try_read_value, try_write_value,
+};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use strum::{EnumIter, IntoEnumIterator};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -14,6 +18,17 @@ pub enum HeadphoneEQ {
     Enabled(HPEQType, bool),
 }
 
+impl Display for HeadphoneEQ {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadphoneEQ::GetAmount(a) => write!(f, "{:?} Amount", a),
+            HeadphoneEQ::Amount(a, v) => write!(f, "{:?} Amount = {}", a, v),
+            HeadphoneEQ::GetEnabled(a) => write!(f, "{:?} Enabled", a),
+            HeadphoneEQ::Enabled(a, v) => write!(f, "{:?} Enabled = {}", a, v),
+        }
+    }
+}
+
 impl BeacnSubMessage for HeadphoneEQ {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -23,6 +38,21 @@ impl BeacnSubMessage for HeadphoneEQ {
         matches!(self, HeadphoneEQ::Amount(_, _) | HeadphoneEQ::Enabled(_, _))
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            HeadphoneEQ::Amount(m, _) => HeadphoneEQ::GetAmount(*m),
+            HeadphoneEQ::Enabled(m, _) => HeadphoneEQ::GetEnabled(*m),
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            HeadphoneEQ::GetAmount(..) | HeadphoneEQ::Amount(..) => ValueType::float::<HPEQValue>(),
+            HeadphoneEQ::GetEnabled(..) | HeadphoneEQ::Enabled(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             HeadphoneEQ::GetAmount(t) | HeadphoneEQ::Amount(t, _) => {
@@ -34,21 +64,23 @@ impl BeacnSubMessage for HeadphoneEQ {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            HeadphoneEQ::Amount(_, v) => write_value(v),
-            HeadphoneEQ::Enabled(_, v) => v.write_beacn(),
-            _ => panic!("Attempted to Set a Getter"),
+            HeadphoneEQ::Amount(_, v) => try_write_value(v),
+            HeadphoneEQ::Enabled(_, v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        let key = PackedEnumKey::from_encoded(key[0]).unwrap();
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        let Some(key) = PackedEnumKey::from_encoded(key[0]) else {
+            beacn_bail!("Unable to decode packed key {:#04x}", key[0]);
+        };
         let eq_type = key.get_upper();
-        match key.get_lower() {
-            HPEQKeys::Enabled => HeadphoneEQ::Enabled(eq_type, bool::read_beacn(&value)),
-            HPEQKeys::Amount => HeadphoneEQ::Amount(eq_type, read_value(&value)),
-        }
+        Ok(match key.get_lower() {
+            HPEQKeys::Enabled => HeadphoneEQ::Enabled(eq_type, bool::try_read_beacn(&value)?),
+            HPEQKeys::Amount => HeadphoneEQ::Amount(eq_type, try_read_value(&value)?),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -61,7 +93,7 @@ impl BeacnSubMessage for HeadphoneEQ {
     }
 }
 
-generate_range!(HPEQValue, f32, -12.0..=12.0);
+generate_range!(HPEQValue, f32, -12.0..=12.0, "dB");
 
 #[derive(Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum HPEQType {
@@ -1,13 +1,15 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message, VERSION_MIN_ALL};
 
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
-use crate::types::{BeacnValue, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, TryReadBeacn, WriteBeacn, beacn_enum, try_read_value, try_write_value,
+};
 use crate::version::VersionNumber;
-use crate::{MIC_CLASS_COMPLIANT_VERSION, generate_range};
-use byteorder::{ByteOrder, LittleEndian};
+use crate::{BResult, MIC_CLASS_COMPLIANT_VERSION, beacn_bail, generate_range};
 use enum_map::Enum;
-use strum::{EnumIter, IntoEnumIterator};
+use std::fmt::{Display, Formatter};
+use strum::EnumIter;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Headphones {
@@ -42,6 +44,33 @@ pub enum Headphones {
     MicClassCompliant(bool),
 }
 
+impl Display for Headphones {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Headphones::GetHeadphoneLevel => write!(f, "Headphone Level"),
+            Headphones::HeadphoneLevel(v) => write!(f, "Headphone Level = {}", v),
+            Headphones::GetMicMonitor => write!(f, "Mic Monitor"),
+            Headphones::MicMonitor(v) => write!(f, "Mic Monitor = {}", v),
+            Headphones::GetStudioMicMonitor => write!(f, "Studio Mic Monitor"),
+            Headphones::StudioMicMonitor(v) => write!(f, "Studio Mic Monitor = {}", v),
+            Headphones::GetMicChannelsLinked => write!(f, "Mic Channels Linked"),
+            Headphones::MicChannelsLinked(v) => write!(f, "Mic Channels Linked = {}", v),
+            Headphones::GetStudioChannelsLinked => write!(f, "Studio Channels Linked"),
+            Headphones::StudioChannelsLinked(v) => write!(f, "Studio Channels Linked = {}", v),
+            Headphones::GetMicOutputGain => write!(f, "Mic Output Gain"),
+            Headphones::MicOutputGain(v) => write!(f, "Mic Output Gain = {}", v),
+            Headphones::GetHeadphoneType => write!(f, "Headphone Type"),
+            Headphones::HeadphoneType(v) => write!(f, "Headphone Type = {:?}", v),
+            Headphones::GetFXEnabled => write!(f, "FXEnabled"),
+            Headphones::FXEnabled(v) => write!(f, "FXEnabled = {}", v),
+            Headphones::GetStudioDriverless => write!(f, "Studio Driverless"),
+            Headphones::StudioDriverless(v) => write!(f, "Studio Driverless = {}", v),
+            Headphones::GetMicClassCompliant => write!(f, "Mic Class Compliant"),
+            Headphones::MicClassCompliant(v) => write!(f, "Mic Class Compliant = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Headphones {
     fn get_device_message_type(&self) -> DeviceMessageType {
         match self {
@@ -86,6 +115,49 @@ impl BeacnSubMessage for Headphones {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Headphones::HeadphoneLevel(_) => Headphones::GetHeadphoneLevel,
+            Headphones::MicMonitor(_) => Headphones::GetMicMonitor,
+            Headphones::StudioMicMonitor(_) => Headphones::GetStudioMicMonitor,
+            Headphones::MicChannelsLinked(_) => Headphones::GetMicChannelsLinked,
+            Headphones::StudioChannelsLinked(_) => Headphones::GetStudioChannelsLinked,
+            Headphones::MicOutputGain(_) => Headphones::GetMicOutputGain,
+            Headphones::HeadphoneType(_) => Headphones::GetHeadphoneType,
+            Headphones::FXEnabled(_) => Headphones::GetFXEnabled,
+            Headphones::StudioDriverless(_) => Headphones::GetStudioDriverless,
+            Headphones::MicClassCompliant(_) => Headphones::GetMicClassCompliant,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Headphones::GetHeadphoneLevel | Headphones::HeadphoneLevel(..) => {
+                ValueType::float::<HPLevel>()
+            }
+            Headphones::GetMicMonitor | Headphones::MicMonitor(..) => {
+                ValueType::float::<HPMicMonitorLevel>()
+            }
+            Headphones::GetStudioMicMonitor | Headphones::StudioMicMonitor(..) => {
+                ValueType::float::<HPMicMonitorLevel>()
+            }
+            Headphones::GetMicChannelsLinked | Headphones::MicChannelsLinked(..) => ValueType::Bool,
+            Headphones::GetStudioChannelsLinked | Headphones::StudioChannelsLinked(..) => {
+                ValueType::Bool
+            }
+            Headphones::GetMicOutputGain | Headphones::MicOutputGain(..) => {
+                ValueType::float::<HPMicOutputGain>()
+            }
+            Headphones::GetHeadphoneType | Headphones::HeadphoneType(..) => {
+                ValueType::enumerated::<HeadphoneTypes>()
+            }
+            Headphones::GetFXEnabled | Headphones::FXEnabled(..) => ValueType::Bool,
+            Headphones::GetStudioDriverless | Headphones::StudioDriverless(..) => ValueType::Bool,
+            Headphones::GetMicClassCompliant | Headphones::MicClassCompliant(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Headphones::HeadphoneLevel(_) | Headphones::GetHeadphoneLevel => [0x04, 0x00],
@@ -105,51 +177,51 @@ impl BeacnSubMessage for Headphones {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Headphones::HeadphoneLevel(v) => write_value(v),
-            Headphones::MicMonitor(v) => write_value(v),
-            Headphones::StudioMicMonitor(v) => write_value(v),
-            Headphones::MicChannelsLinked(v) => v.write_beacn(),
-            Headphones::StudioChannelsLinked(v) => v.write_beacn(),
-            Headphones::MicOutputGain(v) => write_value(v),
-            Headphones::HeadphoneType(v) => v.write_beacn(),
-            Headphones::FXEnabled(v) => v.write_beacn(),
+            Headphones::HeadphoneLevel(v) => try_write_value(v),
+            Headphones::MicMonitor(v) => try_write_value(v),
+            Headphones::StudioMicMonitor(v) => try_write_value(v),
+            Headphones::MicChannelsLinked(v) => Ok(v.write_beacn()),
+            Headphones::StudioChannelsLinked(v) => Ok(v.write_beacn()),
+            Headphones::MicOutputGain(v) => try_write_value(v),
+            Headphones::HeadphoneType(v) => Ok(v.write_beacn()),
+            Headphones::FXEnabled(v) => Ok(v.write_beacn()),
             Headphones::StudioDriverless(v) => {
                 if v == &true {
-                    DeviceMode::Compliancy.write_beacn()
+                    Ok(DeviceMode::Compliancy.write_beacn())
                 } else {
-                    DeviceMode::StudioDefault.write_beacn()
+                    Ok(DeviceMode::StudioDefault.write_beacn())
                 }
             }
             Headphones::MicClassCompliant(v) => {
                 if v == &true {
-                    DeviceMode::Compliancy.write_beacn()
+                    Ok(DeviceMode::Compliancy.write_beacn())
                 } else {
-                    DeviceMode::MicDefault.write_beacn()
+                    Ok(DeviceMode::MicDefault.write_beacn())
                 }
             }
-            _ => panic!("Attempted to get Value on Setter"),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> Self {
-        match key[0] {
-            0x04 => Self::HeadphoneLevel(read_value(&value)),
-            0x06 => Self::MicMonitor(read_value(&value)),
+    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x04 => Self::HeadphoneLevel(try_read_value(&value)?),
+            0x06 => Self::MicMonitor(try_read_value(&value)?),
             0x07 => match device_type {
-                DeviceType::BeacnMic => Self::MicChannelsLinked(bool::read_beacn(&value)),
-                DeviceType::BeacnStudio => Self::StudioMicMonitor(read_value(&value)),
-                _ => panic!("This isn't an Audio Device!"),
+                DeviceType::BeacnMic => Self::MicChannelsLinked(bool::try_read_beacn(&value)?),
+                DeviceType::BeacnStudio => Self::StudioMicMonitor(try_read_value(&value)?),
+                _ => beacn_bail!("{:?} isn't an Audio Device", device_type),
             },
-            0x08 => Self::StudioChannelsLinked(bool::read_beacn(&value)),
-            0x10 => Self::MicOutputGain(read_value(&value)),
-            0x11 => Self::HeadphoneType(HeadphoneTypes::read_beacn(&value)),
-            0x12 => Self::FXEnabled(bool::read_beacn(&value)),
+            0x08 => Self::StudioChannelsLinked(bool::try_read_beacn(&value)?),
+            0x10 => Self::MicOutputGain(try_read_value(&value)?),
+            0x11 => Self::HeadphoneType(HeadphoneTypes::try_read_beacn(&value)?),
+            0x12 => Self::FXEnabled(bool::try_read_beacn(&value)?),
             0x14 => {
                 // The values on this are a little ominous, it's technically an enum, but it's
                 // also a boolean,
-                let mode = DeviceMode::read_beacn(&value);
+                let mode = DeviceMode::try_read_beacn(&value)?;
                 match device_type {
                     DeviceType::BeacnMic => {
                         if mode == DeviceMode::MicDefault {
@@ -165,11 +237,11 @@ impl BeacnSubMessage for Headphones {
                             Self::StudioDriverless(true)
                         }
                     }
-                    _ => panic!("This isn't an Audio Device!"),
+                    _ => beacn_bail!("{:?} isn't an Audio Device", device_type),
                 }
             }
-            _ => panic!("Unexpected Key: {}", key[0]),
-        }
+            _ => beacn_bail!("Unexpected Headphones Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(device_type: DeviceType) -> Vec<Message> {
@@ -197,9 +269,9 @@ impl BeacnSubMessage for Headphones {
     }
 }
 
-generate_range!(HPLevel, f32, -70.0..=-0.0);
-generate_range!(HPMicMonitorLevel, f32, -100.0..=6.0);
-generate_range!(HPMicOutputGain, f32, 0.0..=12.0);
+generate_range!(HPLevel, f32, -70.0..=0.0, "dB");
+generate_range!(HPMicMonitorLevel, f32, -100.0..=6.0, "dB");
+generate_range!(HPMicOutputGain, f32, 0.0..=12.0, "dB");
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum HeadphoneTypes {
@@ -210,27 +282,25 @@ pub enum HeadphoneTypes {
     InEarMonitors = 0x03,
 }
 
-impl Sealed for HeadphoneTypes {}
-impl WriteBeacn for HeadphoneTypes {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
-
-impl ReadBeacn for HeadphoneTypes {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
+impl HeadphoneTypes {
+    /// A short description of what each type is intended for, suitable for showing in a UI. The
+    /// device doesn't report the impedance thresholds it uses, so these are guidance only.
+    pub fn description(&self) -> &'static str {
+        match self {
+            HeadphoneTypes::LineLevel => {
+                "Line level output, for amplifiers and other audio devices"
+            }
+            HeadphoneTypes::NormalPower => "Typical low impedance headphones",
+            HeadphoneTypes::HighImpedance => "High impedance headphones which need more power",
+            HeadphoneTypes::InEarMonitors => {
+                "Sensitive in-ear monitors which need very little power"
             }
         }
-        panic!("Could not Find Value");
     }
 }
 
+beacn_enum!(HeadphoneTypes);
+
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum DeviceMode {
     #[default]
@@ -238,23 +308,73 @@ pub enum DeviceMode {
     StudioDefault = 0x01,
     MicDefault = 0x02,
 }
-impl Sealed for DeviceMode {}
-impl WriteBeacn for DeviceMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
+beacn_enum!(DeviceMode);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::write_value;
+
+    fn round_trip(message: Headphones, device_type: DeviceType) -> Headphones {
+        let key = message.to_beacn_key();
+        let value = message.try_to_beacn_value().unwrap();
+        Headphones::from_beacn(key, value, device_type).unwrap()
     }
-}
 
-impl ReadBeacn for DeviceMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
-            }
+    #[test]
+    fn mic_class_compliant_round_trip() {
+        for enabled in [false, true] {
+            let message = Headphones::MicClassCompliant(enabled);
+            assert_eq!(round_trip(message, DeviceType::BeacnMic), message);
+        }
+    }
+
+    #[test]
+    fn studio_driverless_round_trip() {
+        for enabled in [false, true] {
+            let message = Headphones::StudioDriverless(enabled);
+            assert_eq!(round_trip(message, DeviceType::BeacnStudio), message);
+        }
+    }
+
+    #[test]
+    fn headphone_level_signed_zero() {
+        // write_value panics on an out of range value, so both of these must be accepted
+        let positive = write_value(&HPLevel(0.0));
+        let negative = write_value(&HPLevel(-0.0));
+        assert_eq!(
+            try_read_value::<HPLevel, f32>(&positive).unwrap(),
+            try_read_value::<HPLevel, f32>(&negative).unwrap()
+        );
+    }
+
+    #[test]
+    fn mic_monitor_positive_level() {
+        let message = Headphones::MicMonitor(HPMicMonitorLevel(3.0));
+        assert_eq!(round_trip(message, DeviceType::BeacnMic), message);
+
+        let message = Headphones::StudioMicMonitor(HPMicMonitorLevel(3.0));
+        assert_eq!(round_trip(message, DeviceType::BeacnStudio), message);
+    }
+
+    #[test]
+    fn getter_keys_match_setters() {
+        let setters = [
+            Headphones::HeadphoneLevel(HPLevel(-20.0)),
+            Headphones::MicMonitor(HPMicMonitorLevel(-20.0)),
+            Headphones::StudioMicMonitor(HPMicMonitorLevel(-20.0)),
+            Headphones::MicChannelsLinked(true),
+            Headphones::StudioChannelsLinked(true),
+            Headphones::MicOutputGain(HPMicOutputGain(6.0)),
+            Headphones::HeadphoneType(HeadphoneTypes::NormalPower),
+            Headphones::FXEnabled(true),
+            Headphones::StudioDriverless(true),
+            Headphones::MicClassCompliant(true),
+        ];
+        for setter in setters {
+            let getter = setter.to_getter();
+            assert!(!getter.is_device_message_set(), "{:?}", setter);
+            assert_eq!(getter.to_beacn_key(), setter.to_beacn_key(), "{:?}", setter);
         }
-        panic!("Could not Find Value");
     }
 }
@@ -1,11 +1,14 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
-use crate::types::{BeacnValue, RGBA, ReadBeacn, WriteBeacn, read_value, write_value};
-use byteorder::{ByteOrder, LittleEndian};
+use crate::types::{
+    BeacnValue, RGBA, ReadBeacn, TryReadBeacn, WriteBeacn, beacn_enum, try_read_value,
+    try_write_value,
+};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
-use strum::{EnumIter, IntoEnumIterator};
+use std::fmt::{Display, Formatter};
+use strum::EnumIter;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Lighting {
@@ -46,6 +49,37 @@ pub enum Lighting {
     SuspendBrightness(LightingSuspendBrightness),
 }
 
+impl Display for Lighting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lighting::GetMode => write!(f, "Mode"),
+            Lighting::Mode(v) => write!(f, "Mode = {:?}", v),
+            Lighting::GetStudioMode => write!(f, "Studio Mode"),
+            Lighting::StudioMode(v) => write!(f, "Studio Mode = {:?}", v),
+            Lighting::GetColour1 => write!(f, "Colour1"),
+            Lighting::Colour1(v) => write!(f, "Colour1 = {}", v),
+            Lighting::GetColour2 => write!(f, "Colour2"),
+            Lighting::Colour2(v) => write!(f, "Colour2 = {}", v),
+            Lighting::GetSpeed => write!(f, "Speed"),
+            Lighting::Speed(v) => write!(f, "Speed = {}", v),
+            Lighting::GetBrightness => write!(f, "Brightness"),
+            Lighting::Brightness(v) => write!(f, "Brightness = {}", v),
+            Lighting::GetMeterSource => write!(f, "Meter Source"),
+            Lighting::MeterSource(v) => write!(f, "Meter Source = {:?}", v),
+            Lighting::GetMeterSensitivity => write!(f, "Meter Sensitivity"),
+            Lighting::MeterSensitivity(v) => write!(f, "Meter Sensitivity = {}", v),
+            Lighting::GetMuteMode => write!(f, "Mute Mode"),
+            Lighting::MuteMode(v) => write!(f, "Mute Mode = {:?}", v),
+            Lighting::GetMuteColour => write!(f, "Mute Colour"),
+            Lighting::MuteColour(v) => write!(f, "Mute Colour = {}", v),
+            Lighting::GetSuspendMode => write!(f, "Suspend Mode"),
+            Lighting::SuspendMode(v) => write!(f, "Suspend Mode = {:?}", v),
+            Lighting::GetSuspendBrightness => write!(f, "Suspend Brightness"),
+            Lighting::SuspendBrightness(v) => write!(f, "Suspend Brightness = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Lighting {
     fn get_device_message_type(&self) -> DeviceMessageType {
         match self {
@@ -73,6 +107,55 @@ impl BeacnSubMessage for Lighting {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Lighting::Mode(_) => Lighting::GetMode,
+            Lighting::StudioMode(_) => Lighting::GetStudioMode,
+            Lighting::Colour1(_) => Lighting::GetColour1,
+            Lighting::Colour2(_) => Lighting::GetColour2,
+            Lighting::Speed(_) => Lighting::GetSpeed,
+            Lighting::Brightness(_) => Lighting::GetBrightness,
+            Lighting::MeterSource(_) => Lighting::GetMeterSource,
+            Lighting::MeterSensitivity(_) => Lighting::GetMeterSensitivity,
+            Lighting::MuteMode(_) => Lighting::GetMuteMode,
+            Lighting::MuteColour(_) => Lighting::GetMuteColour,
+            Lighting::SuspendMode(_) => Lighting::GetSuspendMode,
+            Lighting::SuspendBrightness(_) => Lighting::GetSuspendBrightness,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Lighting::GetMode | Lighting::Mode(..) => ValueType::enumerated::<LightingMode>(),
+            Lighting::GetStudioMode | Lighting::StudioMode(..) => {
+                ValueType::enumerated::<StudioLightingMode>()
+            }
+            Lighting::GetColour1 | Lighting::Colour1(..) => ValueType::Colour,
+            Lighting::GetColour2 | Lighting::Colour2(..) => ValueType::Colour,
+            Lighting::GetSpeed | Lighting::Speed(..) => ValueType::signed::<LightingSpeed>(),
+            Lighting::GetBrightness | Lighting::Brightness(..) => {
+                ValueType::signed::<LightingBrightness>()
+            }
+            Lighting::GetMeterSource | Lighting::MeterSource(..) => {
+                ValueType::enumerated::<LightingMeterSource>()
+            }
+            Lighting::GetMeterSensitivity | Lighting::MeterSensitivity(..) => {
+                ValueType::float::<LightingMeterSensitivty>()
+            }
+            Lighting::GetMuteMode | Lighting::MuteMode(..) => {
+                ValueType::enumerated::<LightingMuteMode>()
+            }
+            Lighting::GetMuteColour | Lighting::MuteColour(..) => ValueType::Colour,
+            Lighting::GetSuspendMode | Lighting::SuspendMode(..) => {
+                ValueType::enumerated::<LightingSuspendMode>()
+            }
+            Lighting::GetSuspendBrightness | Lighting::SuspendBrightness(..) => {
+                ValueType::unsigned::<LightingSuspendBrightness>()
+            }
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Lighting::GetMode | Lighting::Mode(_) => [0x00, 0x00],
@@ -90,43 +173,45 @@ impl BeacnSubMessage for Lighting {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Lighting::Mode(v) => v.write_beacn(),
-            Lighting::StudioMode(v) => v.write_beacn(),
-            Lighting::Colour1(v) => v.write_beacn(),
-            Lighting::Colour2(v) => v.write_beacn(),
-            Lighting::Speed(v) => write_value(v),
-            Lighting::Brightness(v) => write_value(v),
-            Lighting::MeterSource(v) => v.write_beacn(),
-            Lighting::MeterSensitivity(v) => write_value(v),
-            Lighting::MuteMode(v) => v.write_beacn(),
-            Lighting::MuteColour(v) => v.write_beacn(),
-            Lighting::SuspendMode(v) => v.write_beacn(),
-            Lighting::SuspendBrightness(v) => write_value(v),
-            _ => panic!("Attempting to Set a Get"),
+            Lighting::Mode(v) => Ok(v.write_beacn()),
+            Lighting::StudioMode(v) => Ok(v.write_beacn()),
+            Lighting::Colour1(v) => Ok(v.write_beacn()),
+            Lighting::Colour2(v) => Ok(v.write_beacn()),
+            Lighting::Speed(v) => try_write_value(v),
+            Lighting::Brightness(v) => try_write_value(v),
+            Lighting::MeterSource(v) => Ok(v.write_beacn()),
+            Lighting::MeterSensitivity(v) => try_write_value(v),
+            Lighting::MuteMode(v) => Ok(v.write_beacn()),
+            Lighting::MuteColour(v) => Ok(v.write_beacn()),
+            Lighting::SuspendMode(v) => Ok(v.write_beacn()),
+            Lighting::SuspendBrightness(v) => try_write_value(v),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> Self {
-        match key[0] {
+    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
             0x00 => match device_type {
-                DeviceType::BeacnMic => Self::Mode(LightingMode::read_beacn(&value)),
-                DeviceType::BeacnStudio => Self::StudioMode(StudioLightingMode::read_beacn(&value)),
-                _ => panic!("This isn't an Audio Device!"),
+                DeviceType::BeacnMic => Self::Mode(LightingMode::try_read_beacn(&value)?),
+                DeviceType::BeacnStudio => {
+                    Self::StudioMode(StudioLightingMode::try_read_beacn(&value)?)
+                }
+                _ => beacn_bail!("{:?} isn't an Audio Device", device_type),
             },
             0x01 => Self::Colour1(RGBA::read_beacn(&value)),
             0x02 => Self::Colour2(RGBA::read_beacn(&value)),
-            0x04 => Self::Speed(read_value(&value)),
-            0x05 => Self::Brightness(read_value(&value)),
-            0x06 => Self::MeterSource(LightingMeterSource::read_beacn(&value)),
-            0x07 => Self::MeterSensitivity(read_value(&value)),
-            0x08 => Self::MuteMode(LightingMuteMode::read_beacn(&value)),
+            0x04 => Self::Speed(try_read_value(&value)?),
+            0x05 => Self::Brightness(try_read_value(&value)?),
+            0x06 => Self::MeterSource(LightingMeterSource::try_read_beacn(&value)?),
+            0x07 => Self::MeterSensitivity(try_read_value(&value)?),
+            0x08 => Self::MuteMode(LightingMuteMode::try_read_beacn(&value)?),
             0x09 => Self::MuteColour(RGBA::read_beacn(&value)),
-            0x0b => Self::SuspendMode(LightingSuspendMode::read_beacn(&value)),
-            0x0c => Self::SuspendBrightness(read_value(&value)),
-            _ => panic!("Unexpected Key: {}", key[0]),
-        }
+            0x0b => Self::SuspendMode(LightingSuspendMode::try_read_beacn(&value)?),
+            0x0c => Self::SuspendBrightness(try_read_value(&value)?),
+            _ => beacn_bail!("Unexpected Lighting Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(device_type: DeviceType) -> Vec<Message> {
@@ -152,10 +237,150 @@ impl BeacnSubMessage for Lighting {
     }
 }
 
+impl Lighting {
+    /// Sets the Mic to a gradient between two colours
+    pub fn gradient(colour1: RGBA, colour2: RGBA) -> Vec<Message> {
+        Self::mode_with_colours(LightingMode::Gradient, colour1, colour2)
+    }
+
+    /// Sets a Mic lighting mode along with both of its colours. The device applies these one at
+    /// a time, so the colours are sent before the mode, that way the new mode is only shown
+    /// once its colours are in place, rather than briefly appearing with the old ones.
+    pub fn mode_with_colours(mode: LightingMode, colour1: RGBA, colour2: RGBA) -> Vec<Message> {
+        vec![
+            Message::Lighting(Lighting::Colour1(colour1)),
+            Message::Lighting(Lighting::Colour2(colour2)),
+            Message::Lighting(Lighting::Mode(mode)),
+        ]
+    }
+
+    /// As mode_with_colours, but for the Studio's lighting modes
+    pub fn studio_mode_with_colours(
+        mode: StudioLightingMode,
+        colour1: RGBA,
+        colour2: RGBA,
+    ) -> Vec<Message> {
+        vec![
+            Message::Lighting(Lighting::Colour1(colour1)),
+            Message::Lighting(Lighting::Colour2(colour2)),
+            Message::Lighting(Lighting::StudioMode(mode)),
+        ]
+    }
+}
+
+/// The full lighting configuration of a Mic or Studio, built from the responses to the
+/// Lighting fetch messages. The Mic and Studio have different mode sets, so both are held and
+/// the one matching the device is used by to_messages.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightingState {
+    pub mode: LightingMode,
+    pub studio_mode: StudioLightingMode,
+    pub colour1: RGBA,
+    pub colour2: RGBA,
+    pub speed: LightingSpeed,
+    pub brightness: LightingBrightness,
+    pub meter_source: LightingMeterSource,
+    pub meter_sensitivity: LightingMeterSensitivty,
+    pub mute_mode: LightingMuteMode,
+    pub mute_colour: RGBA,
+    pub suspend_mode: LightingSuspendMode,
+    pub suspend_brightness: LightingSuspendBrightness,
+}
+
+impl LightingState {
+    /// Builds the state from a set of messages (for example, the results of the Lighting fetch
+    /// messages), non-lighting messages are ignored. Every parameter, and one of Mode or Studio
+    /// Mode, needs to be present.
+    pub fn from_messages(messages: &[Message]) -> BResult<Self> {
+        let mut mode = None;
+        let mut studio_mode = None;
+        let mut colour1 = None;
+        let mut colour2 = None;
+        let mut speed = None;
+        let mut brightness = None;
+        let mut meter_source = None;
+        let mut meter_sensitivity = None;
+        let mut mute_mode = None;
+        let mut mute_colour = None;
+        let mut suspend_mode = None;
+        let mut suspend_brightness = None;
+
+        for message in messages {
+            let Message::Lighting(lighting) = message else {
+                continue;
+            };
+            match *lighting {
+                Lighting::Mode(v) => mode = Some(v),
+                Lighting::StudioMode(v) => studio_mode = Some(v),
+                Lighting::Colour1(v) => colour1 = Some(v),
+                Lighting::Colour2(v) => colour2 = Some(v),
+                Lighting::Speed(v) => speed = Some(v),
+                Lighting::Brightness(v) => brightness = Some(v),
+                Lighting::MeterSource(v) => meter_source = Some(v),
+                Lighting::MeterSensitivity(v) => meter_sensitivity = Some(v),
+                Lighting::MuteMode(v) => mute_mode = Some(v),
+                Lighting::MuteColour(v) => mute_colour = Some(v),
+                Lighting::SuspendMode(v) => suspend_mode = Some(v),
+                Lighting::SuspendBrightness(v) => suspend_brightness = Some(v),
+                _ => {}
+            }
+        }
+
+        if mode.is_none() && studio_mode.is_none() {
+            beacn_bail!("Lighting state is missing Mode");
+        }
+
+        Ok(Self {
+            mode: mode.unwrap_or_default(),
+            studio_mode: studio_mode.unwrap_or_default(),
+            colour1: required(colour1, "Colour1")?,
+            colour2: required(colour2, "Colour2")?,
+            speed: required(speed, "Speed")?,
+            brightness: required(brightness, "Brightness")?,
+            meter_source: required(meter_source, "Meter Source")?,
+            meter_sensitivity: required(meter_sensitivity, "Meter Sensitivity")?,
+            mute_mode: required(mute_mode, "Mute Mode")?,
+            mute_colour: required(mute_colour, "Mute Colour")?,
+            suspend_mode: required(suspend_mode, "Suspend Mode")?,
+            suspend_brightness: required(suspend_brightness, "Suspend Brightness")?,
+        })
+    }
+
+    /// Returns the messages needed to apply this state to a device, in the same order as the
+    /// Lighting fetch messages
+    pub fn to_messages(&self, device_type: DeviceType) -> Vec<Message> {
+        let mode = match device_type {
+            DeviceType::BeacnStudio => Lighting::StudioMode(self.studio_mode),
+            _ => Lighting::Mode(self.mode),
+        };
+
+        vec![
+            Message::Lighting(mode),
+            Message::Lighting(Lighting::Colour1(self.colour1)),
+            Message::Lighting(Lighting::Colour2(self.colour2)),
+            Message::Lighting(Lighting::Speed(self.speed)),
+            Message::Lighting(Lighting::Brightness(self.brightness)),
+            Message::Lighting(Lighting::MeterSource(self.meter_source)),
+            Message::Lighting(Lighting::MeterSensitivity(self.meter_sensitivity)),
+            Message::Lighting(Lighting::MuteMode(self.mute_mode)),
+            Message::Lighting(Lighting::MuteColour(self.mute_colour)),
+            Message::Lighting(Lighting::SuspendMode(self.suspend_mode)),
+            Message::Lighting(Lighting::SuspendBrightness(self.suspend_brightness)),
+        ]
+    }
+}
+
+fn required<T>(value: Option<T>, name: &str) -> BResult<T> {
+    match value {
+        Some(value) => Ok(value),
+        None => beacn_bail!("Lighting state is missing {}", name),
+    }
+}
+
 generate_range!(LightingSpeed, i32, -10..=10);
-generate_range!(LightingBrightness, i32, 0..=100);
+generate_range!(LightingBrightness, i32, 0..=100, "%");
 generate_range!(LightingMeterSensitivty, f32, 0.0..=10.);
-generate_range!(LightingSuspendBrightness, u32, 0..=100);
+generate_range!(LightingSuspendBrightness, u32, 0..=100, "%");
 
 // enum LightingK {
 //     Mode = 0x00,              // LightingMode
@@ -183,25 +408,7 @@ pub enum LightingMode {
     SparkleRandom = 0x0a,
     SparkleMeter = 0x0b,
 }
-impl Sealed for LightingMode {}
-impl ReadBeacn for LightingMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for mode in Self::iter() {
-            if mode as u32 == value {
-                return mode;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
-impl WriteBeacn for LightingMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
+beacn_enum!(LightingMode);
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum StudioLightingMode {
@@ -210,26 +417,12 @@ pub enum StudioLightingMode {
     PeakMeter = 0x05,
     SolidSpectrum = 0x0d,
 }
-impl Sealed for StudioLightingMode {}
-impl ReadBeacn for StudioLightingMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for mode in Self::iter() {
-            if mode as u32 == value {
-                return mode;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
-impl WriteBeacn for StudioLightingMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
+beacn_enum!(StudioLightingMode);
 
+/// How the lighting reacts while the device is muted. This only configures the lighting, no
+/// parameter for the mute state itself has been found in the protocol, and the device doesn't
+/// report mute button presses to the host, so the library can neither read nor change whether
+/// the device is muted.
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum LightingMuteMode {
     #[default]
@@ -238,25 +431,7 @@ pub enum LightingMuteMode {
     Off = 0x02,
 }
 
-impl Sealed for LightingMuteMode {}
-impl ReadBeacn for LightingMuteMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for mode in Self::iter() {
-            if mode as u32 == value {
-                return mode;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
-impl WriteBeacn for LightingMuteMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
+beacn_enum!(LightingMuteMode);
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum LightingSuspendMode {
@@ -265,25 +440,7 @@ pub enum LightingSuspendMode {
     Off = 0x01,
     Brightness = 0x02,
 }
-impl Sealed for LightingSuspendMode {}
-impl ReadBeacn for LightingSuspendMode {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for mode in Self::iter() {
-            if mode as u32 == value {
-                return mode;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
-impl WriteBeacn for LightingSuspendMode {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
+beacn_enum!(LightingSuspendMode);
 
 #[derive(Default, Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
 pub enum LightingMeterSource {
@@ -291,22 +448,4 @@ pub enum LightingMeterSource {
     Microphone = 0x00,
     Headphones = 0x01,
 }
-impl Sealed for LightingMeterSource {}
-impl ReadBeacn for LightingMeterSource {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for mode in Self::iter() {
-            if mode as u32 == value {
-                return mode;
-            }
-        }
-        panic!("Unable to Find Mode")
-    }
-}
-impl WriteBeacn for LightingMeterSource {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
-    }
-}
+beacn_enum!(LightingMeterSource);
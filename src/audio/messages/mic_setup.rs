@@ -1,7 +1,9 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::{BeacnValue, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{BeacnValue, TryReadBeacn, WriteBeacn, try_read_value, try_write_value};
+use crate::{BResult, beacn_bail, generate_range};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MicSetup {
@@ -15,6 +17,19 @@ pub enum MicSetup {
     StudioPhantomPower(bool),
 }
 
+impl Display for MicSetup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MicSetup::GetMicGain => write!(f, "Mic Gain"),
+            MicSetup::MicGain(v) => write!(f, "Mic Gain = {}", v),
+            MicSetup::GetStudioMicGain => write!(f, "Studio Mic Gain"),
+            MicSetup::StudioMicGain(v) => write!(f, "Studio Mic Gain = {}", v),
+            MicSetup::GetStudioPhantomPower => write!(f, "Studio Phantom Power"),
+            MicSetup::StudioPhantomPower(v) => write!(f, "Studio Phantom Power = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for MicSetup {
     fn get_device_message_type(&self) -> DeviceMessageType {
         match self {
@@ -34,6 +49,25 @@ impl BeacnSubMessage for MicSetup {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            MicSetup::MicGain(_) => MicSetup::GetMicGain,
+            MicSetup::StudioMicGain(_) => MicSetup::GetStudioMicGain,
+            MicSetup::StudioPhantomPower(_) => MicSetup::GetStudioPhantomPower,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            MicSetup::GetMicGain | MicSetup::MicGain(..) => ValueType::unsigned::<MicGain>(),
+            MicSetup::GetStudioMicGain | MicSetup::StudioMicGain(..) => {
+                ValueType::unsigned::<StudioMicGain>()
+            }
+            MicSetup::GetStudioPhantomPower | MicSetup::StudioPhantomPower(..) => ValueType::Bool,
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             MicSetup::GetMicGain | MicSetup::MicGain(_) => [0x00, 0x00],
@@ -42,25 +76,25 @@ impl BeacnSubMessage for MicSetup {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            MicSetup::MicGain(v) => write_value(v),
-            MicSetup::StudioMicGain(v) => write_value(v),
-            MicSetup::StudioPhantomPower(v) => v.write_beacn(),
-            _ => panic!("Attempted to Set a Getter"),
+            MicSetup::MicGain(v) => try_write_value(v),
+            MicSetup::StudioMicGain(v) => try_write_value(v),
+            MicSetup::StudioPhantomPower(v) => Ok(v.write_beacn()),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> Self {
-        match key[0] {
+    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
             0x00 => match device_type {
-                DeviceType::BeacnMic => Self::MicGain(read_value(&value)),
-                DeviceType::BeacnStudio => Self::StudioMicGain(read_value(&value)),
-                _ => panic!("This isn't an Audio Device!"),
+                DeviceType::BeacnMic => Self::MicGain(try_read_value(&value)?),
+                DeviceType::BeacnStudio => Self::StudioMicGain(try_read_value(&value)?),
+                _ => beacn_bail!("{:?} isn't an Audio Device", device_type),
             },
-            0x02 => Self::StudioPhantomPower(bool::read_beacn(&value)),
-            _ => panic!("Unknown Key"),
-        }
+            0x02 => Self::StudioPhantomPower(bool::try_read_beacn(&value)?),
+            _ => beacn_bail!("Unexpected Mic Setup Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(device_type: DeviceType) -> Vec<Message> {
@@ -75,5 +109,5 @@ impl BeacnSubMessage for MicSetup {
     }
 }
 
-generate_range!(MicGain, u32, 3..=20);
-generate_range!(StudioMicGain, u32, 0..=69); // NICE.
+generate_range!(MicGain, u32, 3..=20, "dB");
+generate_range!(StudioMicGain, u32, 0..=69, "dB"); // NICE.
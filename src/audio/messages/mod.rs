@@ -8,11 +8,15 @@ use crate::audio::messages::headphone_eq::HeadphoneEQ;
 use crate::audio::messages::headphones::Headphones;
 use crate::audio::messages::lighting::Lighting;
 use crate::audio::messages::mic_setup::MicSetup;
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::subwoofer::Subwoofer;
 use crate::audio::messages::suppressor::Suppressor;
 use crate::manager::DeviceType;
 use crate::types::BeacnValue;
 use crate::version::VersionNumber;
+use crate::{BResult, beacn_bail};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 
 pub mod bass_enhancement;
 pub mod compressor;
@@ -24,13 +28,20 @@ pub mod headphone_eq;
 pub mod headphones;
 pub mod lighting;
 pub mod mic_setup;
+mod parameters;
 pub mod subwoofer;
 pub mod suppressor;
 
+pub use parameters::{ParameterInfo, ParameterKind, parameter_catalog};
+
 const VERSION_MIN_ALL: VersionNumber = VersionNumber(0, 0, 0, 0);
 const VERSION_MAX_ALL: VersionNumber = VersionNumber(u32::MAX, u32::MAX, u32::MAX, u32::MAX);
 
-#[derive(Debug, Copy, Clone)]
+/// Messages can be compared with `==`, but as many carry an f32 (which has no total equality,
+/// NaN isn't equal to itself), they can't be Eq or Hash. For sets and map keys, either use
+/// `to_beacn_key()` (which identifies the parameter, regardless of value), or wrap the message
+/// in a HashableMessage.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Message {
     BassEnhancement(BassEnhancement),
     Compressor(Compressor),
@@ -47,7 +58,8 @@ pub enum Message {
 }
 
 impl Message {
-    pub(crate) fn is_device_message_set(&self) -> bool {
+    /// Returns true if this message carries a value to set on the device, false for getters
+    pub fn is_device_message_set(&self) -> bool {
         match self {
             Message::BassEnhancement(v) => v.is_device_message_set(),
             Message::Compressor(v) => v.is_device_message_set(),
@@ -64,6 +76,52 @@ impl Message {
         }
     }
 
+    /// Converts a message carrying a value into the getter for the same parameter, getters are
+    /// returned unchanged. This allows a set of values (for example, a profile) to be turned
+    /// into the list of messages needed to fetch them from a device.
+    pub fn into_getter(&self) -> Message {
+        match self {
+            Message::BassEnhancement(v) => Message::BassEnhancement(v.to_getter()),
+            Message::Compressor(v) => Message::Compressor(v.to_getter()),
+            Message::DeEsser(v) => Message::DeEsser(v.to_getter()),
+            Message::Equaliser(v) => Message::Equaliser(v.to_getter()),
+            Message::Exciter(v) => Message::Exciter(v.to_getter()),
+            Message::Expander(v) => Message::Expander(v.to_getter()),
+            Message::HeadphoneEQ(v) => Message::HeadphoneEQ(v.to_getter()),
+            Message::Headphones(v) => Message::Headphones(v.to_getter()),
+            Message::Lighting(v) => Message::Lighting(v.to_getter()),
+            Message::MicSetup(v) => Message::MicSetup(v.to_getter()),
+            Message::Subwoofer(v) => Message::Subwoofer(v.to_getter()),
+            Message::Suppressor(v) => Message::Suppressor(v.to_getter()),
+        }
+    }
+
+    pub(crate) fn get_value_type(&self) -> ValueType {
+        match self {
+            Message::BassEnhancement(v) => v.get_value_type(),
+            Message::Compressor(v) => v.get_value_type(),
+            Message::DeEsser(v) => v.get_value_type(),
+            Message::Equaliser(v) => v.get_value_type(),
+            Message::Exciter(v) => v.get_value_type(),
+            Message::Expander(v) => v.get_value_type(),
+            Message::HeadphoneEQ(v) => v.get_value_type(),
+            Message::Headphones(v) => v.get_value_type(),
+            Message::Lighting(v) => v.get_value_type(),
+            Message::MicSetup(v) => v.get_value_type(),
+            Message::Subwoofer(v) => v.get_value_type(),
+            Message::Suppressor(v) => v.get_value_type(),
+        }
+    }
+
+    // Whether this message applies to the given device type, regardless of firmware
+    pub(crate) fn is_valid_for(&self, device_type: DeviceType) -> bool {
+        match self.get_device_message_type() {
+            DeviceMessageType::Common => true,
+            DeviceMessageType::BeacnMic => device_type == DeviceType::BeacnMic,
+            DeviceMessageType::BeacnStudio => device_type == DeviceType::BeacnStudio,
+        }
+    }
+
     pub(crate) fn get_device_message_type(&self) -> DeviceMessageType {
         match self {
             Message::BassEnhancement(v) => v.get_device_message_type(),
@@ -139,6 +197,8 @@ impl Message {
         key
     }
 
+    /// Encodes the value of a Set message, this will panic if called on a getter or if the
+    /// value is out of range, see try_to_beacn_value for a non-panicking version.
     pub fn to_beacn_value(&self) -> BeacnValue {
         match self {
             Message::BassEnhancement(v) => v.to_beacn_value(),
@@ -156,7 +216,38 @@ impl Message {
         }
     }
 
+    /// Encodes the value of a Set message, returning an error if called on a getter or if the
+    /// value is out of range.
+    pub fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
+        match self {
+            Message::BassEnhancement(v) => v.try_to_beacn_value(),
+            Message::Compressor(v) => v.try_to_beacn_value(),
+            Message::DeEsser(v) => v.try_to_beacn_value(),
+            Message::Equaliser(v) => v.try_to_beacn_value(),
+            Message::Exciter(v) => v.try_to_beacn_value(),
+            Message::Expander(v) => v.try_to_beacn_value(),
+            Message::HeadphoneEQ(v) => v.try_to_beacn_value(),
+            Message::Headphones(v) => v.try_to_beacn_value(),
+            Message::Lighting(v) => v.try_to_beacn_value(),
+            Message::MicSetup(v) => v.try_to_beacn_value(),
+            Message::Subwoofer(v) => v.try_to_beacn_value(),
+            Message::Suppressor(v) => v.try_to_beacn_value(),
+        }
+    }
+
+    /// Decodes a message from a device response, panicking if it can't be decoded. See
+    /// from_beacn_message_checked.
     pub fn from_beacn_message(bytes: [u8; 8], device_type: DeviceType) -> Self {
+        match Self::from_beacn_message_checked(bytes, device_type) {
+            Ok(message) => message,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Decodes a message from a device response, returning an error if the message type or
+    /// key isn't one we know about, or the value isn't valid for the parameter. This doesn't
+    /// panic on any input, as responses could be corrupted (see fuzz/ for the fuzz target).
+    pub fn from_beacn_message_checked(bytes: [u8; 8], device_type: DeviceType) -> BResult<Self> {
         // Grab the initial type
         let message = bytes[0];
 
@@ -164,20 +255,75 @@ impl Message {
         let key: [u8; 2] = bytes[1..3].try_into().unwrap();
         let value: BeacnValue = bytes[4..8].try_into().unwrap();
 
-        match message {
-            0x00 => Self::Headphones(Headphones::from_beacn(key, value, device_type)),
-            0x01 => Self::Lighting(Lighting::from_beacn(key, value, device_type)),
-            0x02 => Self::Equaliser(Equaliser::from_beacn(key, value, device_type)),
-            0x03 => Self::HeadphoneEQ(HeadphoneEQ::from_beacn(key, value, device_type)),
-            0x04 => Self::BassEnhancement(BassEnhancement::from_beacn(key, value, device_type)),
-            0x05 => Self::Compressor(Compressor::from_beacn(key, value, device_type)),
-            0x06 => Self::DeEsser(DeEsser::from_beacn(key, value, device_type)),
-            0x07 => Self::Exciter(Exciter::from_beacn(key, value, device_type)),
-            0x08 => Self::Expander(Expander::from_beacn(key, value, device_type)),
-            0x09 => Self::Suppressor(Suppressor::from_beacn(key, value, device_type)),
-            0x0a => Self::MicSetup(MicSetup::from_beacn(key, value, device_type)),
-            0x0b => Self::Subwoofer(Subwoofer::from_beacn(key, value, device_type)),
-            _ => panic!("Not Found!"),
+        Ok(match message {
+            0x00 => Self::Headphones(Headphones::from_beacn(key, value, device_type)?),
+            0x01 => Self::Lighting(Lighting::from_beacn(key, value, device_type)?),
+            0x02 => Self::Equaliser(Equaliser::from_beacn(key, value, device_type)?),
+            0x03 => Self::HeadphoneEQ(HeadphoneEQ::from_beacn(key, value, device_type)?),
+            0x04 => Self::BassEnhancement(BassEnhancement::from_beacn(key, value, device_type)?),
+            0x05 => Self::Compressor(Compressor::from_beacn(key, value, device_type)?),
+            0x06 => Self::DeEsser(DeEsser::from_beacn(key, value, device_type)?),
+            0x07 => Self::Exciter(Exciter::from_beacn(key, value, device_type)?),
+            0x08 => Self::Expander(Expander::from_beacn(key, value, device_type)?),
+            0x09 => Self::Suppressor(Suppressor::from_beacn(key, value, device_type)?),
+            0x0a => Self::MicSetup(MicSetup::from_beacn(key, value, device_type)?),
+            0x0b => Self::Subwoofer(Subwoofer::from_beacn(key, value, device_type)?),
+            _ => beacn_bail!("Unknown message type {:#04x}", message),
+        })
+    }
+
+    /// Describes a raw three byte key (as used in lookups and responses) in readable terms, for
+    /// example [0x02, 0x82, 0x00] is "EQ Simple Band8 Gain". Keys which don't match a known
+    /// parameter are broken down as far as possible. This is intended for logging unexpected
+    /// responses, and doesn't need the key to be valid.
+    pub fn describe_key(key: [u8; 3]) -> String {
+        // A few keys mean different things on the Mic and Studio, so we check both
+        let mut names: Vec<String> = vec![];
+        for device_type in [DeviceType::BeacnMic, DeviceType::BeacnStudio] {
+            for message in Message::generate_fetch_message(device_type) {
+                let name = message.to_string();
+                if message.to_beacn_key() == key && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        if !names.is_empty() {
+            return names.join(" / ");
+        }
+
+        let (family, packed) = match key[0] {
+            0x00 => ("Headphones", false),
+            0x01 => ("Lighting", false),
+            0x02 => ("EQ", true),
+            0x03 => ("Headphone EQ", true),
+            0x04 => ("Bass Enhancement", false),
+            0x05 => ("Compressor", true),
+            0x06 => ("De-Esser", false),
+            0x07 => ("Exciter", false),
+            0x08 => ("Expander", true),
+            0x09 => ("Suppressor", false),
+            0x0a => ("Mic Setup", false),
+            0x0b => ("Subwoofer", false),
+            _ => {
+                return format!(
+                    "Unknown message type {:#04x}, key {:02x?}",
+                    key[0],
+                    &key[1..]
+                );
+            }
+        };
+
+        if packed {
+            // The upper nibble is the band or mode, and the lower nibble the parameter
+            format!(
+                "{} unknown key {:02x?} (upper {:#x}, lower {:#x})",
+                family,
+                &key[1..],
+                key[1] >> 4,
+                key[1] & 0x0f
+            )
+        } else {
+            format!("{} unknown key {:02x?}", family, &key[1..])
         }
     }
 
@@ -200,6 +346,74 @@ impl Message {
     }
 }
 
+/// Wraps a Message so it can be used in a HashSet or as a HashMap key. Rather than comparing the
+/// values directly, messages are compared by what would be sent to the device (their key and
+/// encoded value), so 0.0 and -0.0 are different.
+///
+/// Messages which can't be encoded (getters, or values which are out of range or not finite)
+/// are compared by key alone, so two NaN values for the same parameter are treated as equal.
+#[derive(Debug, Copy, Clone)]
+pub struct HashableMessage(Message);
+
+impl HashableMessage {
+    pub fn new(message: Message) -> Self {
+        Self(message)
+    }
+
+    pub fn get_message(&self) -> Message {
+        self.0
+    }
+
+    fn identity(&self) -> ([u8; 3], bool, Option<BeacnValue>) {
+        let message = &self.0;
+        let value = message.try_to_beacn_value().ok();
+        (
+            message.to_beacn_key(),
+            message.is_device_message_set(),
+            value,
+        )
+    }
+}
+
+impl From<Message> for HashableMessage {
+    fn from(message: Message) -> Self {
+        Self(message)
+    }
+}
+
+impl PartialEq for HashableMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Eq for HashableMessage {}
+
+impl Hash for HashableMessage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::BassEnhancement(v) => write!(f, "Bass Enhancement {}", v),
+            Message::Compressor(v) => write!(f, "Compressor {}", v),
+            Message::DeEsser(v) => write!(f, "De-Esser {}", v),
+            Message::Equaliser(v) => write!(f, "EQ {}", v),
+            Message::Exciter(v) => write!(f, "Exciter {}", v),
+            Message::Expander(v) => write!(f, "Expander {}", v),
+            Message::HeadphoneEQ(v) => write!(f, "Headphone EQ {}", v),
+            Message::Headphones(v) => write!(f, "Headphones {}", v),
+            Message::Lighting(v) => write!(f, "Lighting {}", v),
+            Message::MicSetup(v) => write!(f, "Mic Setup {}", v),
+            Message::Subwoofer(v) => write!(f, "Subwoofer {}", v),
+            Message::Suppressor(v) => write!(f, "Suppressor {}", v),
+        }
+    }
+}
+
 pub enum BeacnMessage {
     Headphones = 0x00, // HeadphoneMessage
     Lighting = 0x01,
@@ -231,10 +445,20 @@ trait BeacnSubMessage {
     }
 
     fn is_device_message_set(&self) -> bool;
+    fn to_getter(&self) -> Self;
+    fn get_value_type(&self) -> ValueType;
 
     fn to_beacn_key(&self) -> [u8; 2];
-    fn to_beacn_value(&self) -> BeacnValue;
+    fn to_beacn_value(&self) -> BeacnValue {
+        match self.try_to_beacn_value() {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue>;
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> Self;
+    fn from_beacn(key: [u8; 2], value: BeacnValue, device_type: DeviceType) -> BResult<Self>
+    where
+        Self: Sized;
     fn generate_fetch_message(device_type: DeviceType) -> Vec<Message>;
 }
@@ -0,0 +1,333 @@
+use crate::audio::messages::Message;
+use crate::manager::DeviceType;
+use crate::types::{BeacnValue, HasRange, RGBA, WriteBeacn};
+use crate::{BResult, beacn_bail, check_finite, out_of_range};
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use strum::IntoEnumIterator;
+
+/// Describes the type of value a parameter carries, used for parsing and the parameter catalog
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ValueType {
+    Bool,
+    Float(RangeInclusive<f32>),
+    Signed(RangeInclusive<i32>),
+    Unsigned(RangeInclusive<u32>),
+    Enum(Vec<(String, BeacnValue)>),
+    Colour,
+}
+
+impl ValueType {
+    pub(crate) fn float<T: HasRange<f32>>() -> Self {
+        Self::Float(T::range())
+    }
+
+    pub(crate) fn signed<T: HasRange<i32>>() -> Self {
+        Self::Signed(T::range())
+    }
+
+    pub(crate) fn unsigned<T: HasRange<u32>>() -> Self {
+        Self::Unsigned(T::range())
+    }
+
+    pub(crate) fn enumerated<T: IntoEnumIterator + Debug + WriteBeacn>() -> Self {
+        Self::Enum(
+            T::iter()
+                .map(|v| (format!("{:?}", v), v.write_beacn()))
+                .collect(),
+        )
+    }
+
+    /// Parses and range checks a value, returning it encoded for the device
+    fn parse(&self, path: &str, value: &str) -> BResult<BeacnValue> {
+        match self {
+            ValueType::Bool => match value.to_lowercase().as_str() {
+                "true" | "on" | "1" => Ok(true.write_beacn()),
+                "false" | "off" | "0" => Ok(false.write_beacn()),
+                _ => beacn_bail!(
+                    "Invalid value '{}' for {}, expected true or false",
+                    value,
+                    path
+                ),
+            },
+            ValueType::Float(range) => {
+                let Ok(parsed) = value.parse::<f32>() else {
+                    beacn_bail!("Invalid value '{}' for {}, expected a number", value, path);
+                };
+                check_finite(path, parsed)?;
+                if !range.contains(&parsed) {
+                    return Err(out_of_range(path, parsed, range));
+                }
+                Ok(parsed.write_beacn())
+            }
+            ValueType::Signed(range) => {
+                let Ok(parsed) = value.parse::<i32>() else {
+                    beacn_bail!(
+                        "Invalid value '{}' for {}, expected an integer",
+                        value,
+                        path
+                    );
+                };
+                if !range.contains(&parsed) {
+                    return Err(out_of_range(path, parsed, range));
+                }
+                Ok(parsed.write_beacn())
+            }
+            ValueType::Unsigned(range) => {
+                let Ok(parsed) = value.parse::<u32>() else {
+                    beacn_bail!(
+                        "Invalid value '{}' for {}, expected an integer",
+                        value,
+                        path
+                    );
+                };
+                if !range.contains(&parsed) {
+                    return Err(out_of_range(path, parsed, range));
+                }
+                Ok(parsed.write_beacn())
+            }
+            ValueType::Enum(options) => {
+                let wanted = normalise(value);
+                if let Some((_, encoded)) =
+                    options.iter().find(|(name, _)| normalise(name) == wanted)
+                {
+                    return Ok(*encoded);
+                }
+                let names: Vec<&str> = options.iter().map(|(name, _)| name.as_str()).collect();
+                beacn_bail!(
+                    "Invalid value '{}' for {}, expected one of: {}",
+                    value,
+                    path,
+                    names.join(", ")
+                );
+            }
+            ValueType::Colour => {
+                let hex = value.trim_start_matches('#');
+                if !(hex.len() == 6 || hex.len() == 8)
+                    || !hex.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    beacn_bail!("Invalid colour '{}' for {}, expected #RRGGBB", value, path);
+                }
+
+                // The device doesn't store alpha, so if it's provided it's ignored
+                let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+                let colour = RGBA {
+                    red: channel(0),
+                    green: channel(2),
+                    blue: channel(4),
+                    alpha: 0,
+                };
+                Ok(colour.write_beacn())
+            }
+        }
+    }
+}
+
+/// The kind of value a parameter carries
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterKind {
+    Bool,
+    Float,
+    Int,
+    /// An enumerated value, with the names of the available options
+    Enum(Vec<String>),
+    Colour,
+}
+
+/// Describes a single parameter available on a device, see parameter_catalog
+#[derive(Debug, Clone)]
+pub struct ParameterInfo {
+    message: Message,
+    category: &'static str,
+    kind: ParameterKind,
+    range: Option<RangeInclusive<f32>>,
+}
+
+impl ParameterInfo {
+    /// The getter message for this parameter
+    pub fn get_message(&self) -> Message {
+        self.message
+    }
+
+    /// A label for the effect or area this parameter belongs to, for example "Compressor"
+    pub fn get_category(&self) -> &'static str {
+        self.category
+    }
+
+    pub fn get_kind(&self) -> ParameterKind {
+        self.kind.clone()
+    }
+
+    /// The valid range for Float and Int parameters, None for everything else
+    pub fn get_range(&self) -> Option<RangeInclusive<f32>> {
+        self.range.clone()
+    }
+}
+
+/// Returns every parameter available on a device type, in the same order as
+/// Message::generate_fetch_message, along with the information needed to present it.
+pub fn parameter_catalog(device_type: DeviceType) -> Vec<ParameterInfo> {
+    Message::generate_fetch_message(device_type)
+        .into_iter()
+        .map(|message| {
+            let (kind, range) = match message.get_value_type() {
+                ValueType::Bool => (ParameterKind::Bool, None),
+                ValueType::Float(r) => (ParameterKind::Float, Some(r)),
+                ValueType::Signed(r) => (
+                    ParameterKind::Int,
+                    Some(*r.start() as f32..=*r.end() as f32),
+                ),
+                ValueType::Unsigned(r) => (
+                    ParameterKind::Int,
+                    Some(*r.start() as f32..=*r.end() as f32),
+                ),
+                ValueType::Enum(options) => {
+                    let names = options.into_iter().map(|(name, _)| name).collect();
+                    (ParameterKind::Enum(names), None)
+                }
+                ValueType::Colour => (ParameterKind::Colour, None),
+            };
+
+            ParameterInfo {
+                message,
+                category: message.get_category(),
+                kind,
+                range,
+            }
+        })
+        .collect()
+}
+
+impl Message {
+    /// Parses a 'module.parameter[.mode][.band]=value' string into a Message, for example
+    /// "compressor.ratio.advanced=4.5", "lighting.brightness=80" or "lighting.mode=spectrum".
+    ///
+    /// Paths match those returned by get_parameter_path, and only parameters available on the
+    /// given device type are accepted. Values are range checked before the message is built.
+    pub fn parse(input: &str, device_type: DeviceType) -> BResult<Message> {
+        let Some((path, value)) = input.split_once('=') else {
+            beacn_bail!("Expected 'module.parameter=value', got '{}'", input);
+        };
+        let path = path.trim().to_lowercase();
+        let value = value.trim();
+
+        let Some(getter) = Message::generate_fetch_message(device_type)
+            .into_iter()
+            .find(|m| m.get_parameter_path() == path)
+        else {
+            beacn_bail!("Unknown parameter '{}' for {:?}", path, device_type);
+        };
+
+        let encoded = getter.get_value_type().parse(&path, value)?;
+
+        // Build this up as if it was a response from the device, so the existing decoding
+        // is used to produce the message
+        let mut bytes = [0; 8];
+        bytes[0..3].copy_from_slice(&getter.to_beacn_key());
+        bytes[4..8].copy_from_slice(&encoded);
+        Message::from_beacn_message_checked(bytes, device_type)
+    }
+
+    /// Returns the 'module.parameter[.mode][.band]' path for this message, as accepted by
+    /// parse. Getters and setters for the same parameter share a path.
+    pub fn get_parameter_path(&self) -> String {
+        let module = match self {
+            Message::BassEnhancement(_) => "bass_enhancement",
+            Message::Compressor(_) => "compressor",
+            Message::DeEsser(_) => "deesser",
+            Message::Equaliser(_) => "equaliser",
+            Message::Exciter(_) => "exciter",
+            Message::Expander(_) => "expander",
+            Message::HeadphoneEQ(_) => "headphone_eq",
+            Message::Headphones(_) => "headphones",
+            Message::Lighting(_) => "lighting",
+            Message::MicSetup(_) => "mic_setup",
+            Message::Subwoofer(_) => "subwoofer",
+            Message::Suppressor(_) => "suppressor",
+        };
+
+        // The getter's debug output is 'GetName' or 'GetName(Mode, Band)', which carries
+        // everything needed to name the parameter
+        let getter = match self.into_getter() {
+            Message::BassEnhancement(v) => format!("{:?}", v),
+            Message::Compressor(v) => format!("{:?}", v),
+            Message::DeEsser(v) => format!("{:?}", v),
+            Message::Equaliser(v) => format!("{:?}", v),
+            Message::Exciter(v) => format!("{:?}", v),
+            Message::Expander(v) => format!("{:?}", v),
+            Message::HeadphoneEQ(v) => format!("{:?}", v),
+            Message::Headphones(v) => format!("{:?}", v),
+            Message::Lighting(v) => format!("{:?}", v),
+            Message::MicSetup(v) => format!("{:?}", v),
+            Message::Subwoofer(v) => format!("{:?}", v),
+            Message::Suppressor(v) => format!("{:?}", v),
+        };
+        let getter = getter.strip_prefix("Get").unwrap_or(&getter);
+
+        let mut path = String::from(module);
+        for part in getter.split(['(', ',', ')']).map(str::trim) {
+            if !part.is_empty() {
+                path.push('.');
+                path.push_str(&to_snake_case(part));
+            }
+        }
+        path
+    }
+
+    /// A label for the effect or area this message belongs to, for example "Compressor", this
+    /// matches the prefix used when the message is displayed
+    pub fn get_category(&self) -> &'static str {
+        match self {
+            Message::BassEnhancement(_) => "Bass Enhancement",
+            Message::Compressor(_) => "Compressor",
+            Message::DeEsser(_) => "De-Esser",
+            Message::Equaliser(_) => "EQ",
+            Message::Exciter(_) => "Exciter",
+            Message::Expander(_) => "Expander",
+            Message::HeadphoneEQ(_) => "Headphone EQ",
+            Message::Headphones(_) => "Headphones",
+            Message::Lighting(_) => "Lighting",
+            Message::MicSetup(_) => "Mic Setup",
+            Message::Subwoofer(_) => "Subwoofer",
+            Message::Suppressor(_) => "Suppressor",
+        }
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut output = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            // Split on a lower to upper change, or at the end of an acronym (FXEnabled)
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if previous.is_ascii_lowercase() || previous.is_ascii_digit() || next_lower {
+                output.push('_');
+            }
+        }
+        output.push(c.to_ascii_lowercase());
+    }
+    output
+}
+
+fn normalise(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BeacnError;
+    use crate::types::Percent;
+
+    #[test]
+    fn float_parse_rejects_nan() {
+        let result = ValueType::float::<Percent>().parse("exciter.amount", "NaN");
+        assert!(matches!(result, Err(BeacnError::ValueNotFinite { .. })));
+    }
+}
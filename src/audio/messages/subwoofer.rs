@@ -1,7 +1,11 @@
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::{BeacnValue, Percent, ReadBeacn, WriteBeacn, read_value, write_value};
+use crate::types::{
+    BeacnValue, Percent, TryReadBeacn, WriteBeacn, try_read_value, try_write_value,
+};
+use crate::{BResult, beacn_bail, generate_range};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Subwoofer {
@@ -21,6 +25,23 @@ pub enum Subwoofer {
     Amount(SubwooferAmount),
 }
 
+impl Display for Subwoofer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Subwoofer::GetMakeupGain => write!(f, "Makeup Gain"),
+            Subwoofer::MakeupGain(v) => write!(f, "Makeup Gain = {}", v),
+            Subwoofer::GetRatio => write!(f, "Ratio"),
+            Subwoofer::Ratio(v) => write!(f, "Ratio = {}", v),
+            Subwoofer::GetMix => write!(f, "Mix"),
+            Subwoofer::Mix(v) => write!(f, "Mix = {}", v),
+            Subwoofer::GetEnabled => write!(f, "Enabled"),
+            Subwoofer::Enabled(v) => write!(f, "Enabled = {}", v),
+            Subwoofer::GetAmount => write!(f, "Amount"),
+            Subwoofer::Amount(v) => write!(f, "Amount = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Subwoofer {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -37,6 +58,29 @@ impl BeacnSubMessage for Subwoofer {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Subwoofer::MakeupGain(_) => Subwoofer::GetMakeupGain,
+            Subwoofer::Ratio(_) => Subwoofer::GetRatio,
+            Subwoofer::Mix(_) => Subwoofer::GetMix,
+            Subwoofer::Enabled(_) => Subwoofer::GetEnabled,
+            Subwoofer::Amount(_) => Subwoofer::GetAmount,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Subwoofer::GetMakeupGain | Subwoofer::MakeupGain(..) => {
+                ValueType::float::<SubwooferMakeupGain>()
+            }
+            Subwoofer::GetRatio | Subwoofer::Ratio(..) => ValueType::float::<SubwooferRatio>(),
+            Subwoofer::GetMix | Subwoofer::Mix(..) => ValueType::float::<Percent>(),
+            Subwoofer::GetEnabled | Subwoofer::Enabled(..) => ValueType::Bool,
+            Subwoofer::GetAmount | Subwoofer::Amount(..) => ValueType::signed::<SubwooferAmount>(),
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Subwoofer::GetMakeupGain | Subwoofer::MakeupGain(_) => [0x04, 0x00],
@@ -47,26 +91,26 @@ impl BeacnSubMessage for Subwoofer {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Subwoofer::MakeupGain(v) => write_value(v),
-            Subwoofer::Ratio(v) => write_value(v),
-            Subwoofer::Mix(v) => write_value(v),
-            Subwoofer::Enabled(v) => v.write_beacn(),
-            Subwoofer::Amount(v) => write_value(v),
-            _ => panic!("Attempted to Set a Getter"),
+            Subwoofer::MakeupGain(v) => try_write_value(v),
+            Subwoofer::Ratio(v) => try_write_value(v),
+            Subwoofer::Mix(v) => try_write_value(v),
+            Subwoofer::Enabled(v) => Ok(v.write_beacn()),
+            Subwoofer::Amount(v) => try_write_value(v),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        match key[0] {
-            0x04 => Self::MakeupGain(read_value(&value)),
-            0x05 => Self::Ratio(read_value(&value)),
-            0x0b => Self::Mix(read_value(&value)),
-            0x0c => Self::Enabled(bool::read_beacn(&value)),
-            0x0e => Self::Amount(read_value(&value)),
-            _ => panic!("Unexpected Key: {}", key[0]),
-        }
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x04 => Self::MakeupGain(try_read_value(&value)?),
+            0x05 => Self::Ratio(try_read_value(&value)?),
+            0x0b => Self::Mix(try_read_value(&value)?),
+            0x0c => Self::Enabled(bool::try_read_beacn(&value)?),
+            0x0e => Self::Amount(try_read_value(&value)?),
+            _ => beacn_bail!("Unexpected Subwoofer Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -81,7 +125,9 @@ impl BeacnSubMessage for Subwoofer {
 }
 
 impl Subwoofer {
+    /// Builds the messages for an amount (0..=10), anything higher is treated as 10
     pub fn get_amount_messages(amount: u8) -> Vec<Message> {
+        let amount = amount.min(10);
         let gain = if amount < 6 { 2 } else { amount + 1 };
         let ratio = 12 - amount;
         let mix = amount * 10;
@@ -95,9 +141,32 @@ impl Subwoofer {
 
         messages
     }
+
+    /// The reverse of get_amount_messages, returns the amount that would produce these values,
+    /// or None if they don't match any amount (for example, if they've been set individually)
+    pub fn amount_from_values(
+        mix: Percent,
+        ratio: SubwooferRatio,
+        gain: SubwooferMakeupGain,
+    ) -> Option<u8> {
+        let amount = 12.0 - ratio.0;
+        if amount.fract() != 0.0 || !(0.0..=10.0).contains(&amount) {
+            return None;
+        }
+
+        let amount = amount as u8;
+        let expected_gain = if amount < 6 { 2 } else { amount + 1 };
+        let matches = |value: f32, expected: u8| (value - expected as f32).abs() < 0.01;
+        if matches(mix.0, amount * 10) && matches(gain.0, expected_gain) {
+            return Some(amount);
+        }
+        None
+    }
 }
 
-generate_range!(SubwooferMakeupGain, f32, 0.0..=12.0);
+// The hardware range is 2..=11 (see below), which get_amount_messages keeps to. This range is
+// also used when reading values back, so it's left wider to decode anything already on a device.
+generate_range!(SubwooferMakeupGain, f32, 0.0..=12.0, "dB");
 generate_range!(SubwooferRatio, f32, 0.0..=12.0);
 generate_range!(SubwooferAmount, i32, 0..=10);
 
@@ -108,3 +177,23 @@ generate_range!(SubwooferAmount, i32, 0..=10);
 //     Enabled = 0x0c,    // bool
 //     Amount = 0x0e,     // int (0..10)
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount_messages_in_range() {
+        for amount in 0..=10 {
+            for message in Subwoofer::get_amount_messages(amount) {
+                let Message::Subwoofer(message) = message else {
+                    panic!("Unexpected message: {:?}", message);
+                };
+                assert!(message.try_to_beacn_value().is_ok(), "{:?}", message);
+                if let Subwoofer::MakeupGain(gain) = message {
+                    assert!((2.0..=11.0).contains(&gain.0), "{:?}", message);
+                }
+            }
+        }
+    }
+}
@@ -3,13 +3,15 @@
 // assuming these values are in milliseconds.
 // I did *NOT* during this time check data received, I might need to ask Beacn how this is handled.
 
+use crate::audio::messages::parameters::ValueType;
 use crate::audio::messages::{BeacnSubMessage, DeviceMessageType, Message};
-use crate::generate_range;
 use crate::manager::DeviceType;
-use crate::types::sealed::Sealed;
-use crate::types::{BeacnValue, Percent, ReadBeacn, WriteBeacn, read_value, write_value};
-use byteorder::{ByteOrder, LittleEndian};
+use crate::types::{
+    BeacnValue, Percent, TryReadBeacn, WriteBeacn, beacn_enum, try_read_value, try_write_value,
+};
+use crate::{BResult, beacn_bail, generate_range};
 use enum_map::Enum;
+use std::fmt::{Display, Formatter};
 use strum::{EnumIter, IntoEnumIterator};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -30,6 +32,23 @@ pub enum Suppressor {
     AdaptTime(SupressorAdaptTime),
 }
 
+impl Display for Suppressor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Suppressor::GetEnabled => write!(f, "Enabled"),
+            Suppressor::Enabled(v) => write!(f, "Enabled = {}", v),
+            Suppressor::GetAmount => write!(f, "Amount"),
+            Suppressor::Amount(v) => write!(f, "Amount = {}", v),
+            Suppressor::GetStyle => write!(f, "Style"),
+            Suppressor::Style(v) => write!(f, "Style = {:?}", v),
+            Suppressor::GetSensitivity => write!(f, "Sensitivity"),
+            Suppressor::Sensitivity(v) => write!(f, "Sensitivity = {}", v),
+            Suppressor::GetAdaptTime => write!(f, "Adapt Time"),
+            Suppressor::AdaptTime(v) => write!(f, "Adapt Time = {}", v),
+        }
+    }
+}
+
 impl BeacnSubMessage for Suppressor {
     fn get_device_message_type(&self) -> DeviceMessageType {
         DeviceMessageType::Common
@@ -46,6 +65,33 @@ impl BeacnSubMessage for Suppressor {
         )
     }
 
+    fn to_getter(&self) -> Self {
+        match self {
+            Suppressor::Enabled(_) => Suppressor::GetEnabled,
+            Suppressor::Amount(_) => Suppressor::GetAmount,
+            Suppressor::Style(_) => Suppressor::GetStyle,
+            Suppressor::Sensitivity(_) => Suppressor::GetSensitivity,
+            Suppressor::AdaptTime(_) => Suppressor::GetAdaptTime,
+            _ => *self,
+        }
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        match self {
+            Suppressor::GetEnabled | Suppressor::Enabled(..) => ValueType::Bool,
+            Suppressor::GetAmount | Suppressor::Amount(..) => ValueType::float::<Percent>(),
+            Suppressor::GetStyle | Suppressor::Style(..) => {
+                ValueType::enumerated::<SuppressorStyle>()
+            }
+            Suppressor::GetSensitivity | Suppressor::Sensitivity(..) => {
+                ValueType::float::<SuppressorSensitivity>()
+            }
+            Suppressor::GetAdaptTime | Suppressor::AdaptTime(..) => {
+                ValueType::float::<SupressorAdaptTime>()
+            }
+        }
+    }
+
     fn to_beacn_key(&self) -> [u8; 2] {
         match self {
             Suppressor::GetEnabled | Suppressor::Enabled(_) => [0x00, 0x00],
@@ -56,26 +102,26 @@ impl BeacnSubMessage for Suppressor {
         }
     }
 
-    fn to_beacn_value(&self) -> BeacnValue {
+    fn try_to_beacn_value(&self) -> BResult<BeacnValue> {
         match self {
-            Suppressor::Enabled(v) => v.write_beacn(),
-            Suppressor::Amount(v) => write_value(v),
-            Suppressor::Style(v) => v.write_beacn(),
-            Suppressor::Sensitivity(v) => write_value(v),
-            Suppressor::AdaptTime(v) => write_value(v),
-            _ => panic!("Attempted to Set a Getter"),
+            Suppressor::Enabled(v) => Ok(v.write_beacn()),
+            Suppressor::Amount(v) => try_write_value(v),
+            Suppressor::Style(v) => Ok(v.write_beacn()),
+            Suppressor::Sensitivity(v) => try_write_value(v),
+            Suppressor::AdaptTime(v) => try_write_value(v),
+            _ => beacn_bail!("Attempted to Set a Getter"),
         }
     }
 
-    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> Self {
-        match key[0] {
-            0x00 => Self::Enabled(bool::read_beacn(&value)),
-            0x02 => Self::Amount(read_value(&value)),
-            0x04 => Self::Style(SuppressorStyle::read_beacn(&value)),
-            0x05 => Self::Sensitivity(read_value(&value)),
-            0x08 => Self::AdaptTime(read_value(&value)),
-            _ => panic!("Unexpected Key {}", key[0]),
-        }
+    fn from_beacn(key: [u8; 2], value: BeacnValue, _device_type: DeviceType) -> BResult<Self> {
+        Ok(match key[0] {
+            0x00 => Self::Enabled(bool::try_read_beacn(&value)?),
+            0x02 => Self::Amount(try_read_value(&value)?),
+            0x04 => Self::Style(SuppressorStyle::try_read_beacn(&value)?),
+            0x05 => Self::Sensitivity(try_read_value(&value)?),
+            0x08 => Self::AdaptTime(try_read_value(&value)?),
+            _ => beacn_bail!("Unexpected Suppressor Key: {:#04x}", key[0]),
+        })
     }
 
     fn generate_fetch_message(_device_type: DeviceType) -> Vec<Message> {
@@ -89,8 +135,64 @@ impl BeacnSubMessage for Suppressor {
     }
 }
 
-generate_range!(SuppressorSensitivity, f32, -120.0..=-60.0);
-generate_range!(SupressorAdaptTime, f32, 100.0..=5000.0);
+impl Suppressor {
+    /// Creates an AdaptTime message from one of the observed adapt time steps
+    pub fn adapt_step(step: SuppressorAdaptStep) -> Message {
+        Message::Suppressor(Suppressor::AdaptTime(step.into()))
+    }
+}
+
+generate_range!(SuppressorSensitivity, f32, -120.0..=-60.0, "dB");
+generate_range!(SupressorAdaptTime, f32, 100.0..=5000.0, "ms");
+
+/// The adapt times seen being used by the Beacn App (see the note at the top of this file). The
+/// device accepts anything in the SupressorAdaptTime range, but these are the only values known
+/// to be sensible, so UIs should prefer offering these.
+#[derive(Copy, Clone, Hash, Enum, EnumIter, Debug, Eq, PartialEq)]
+pub enum SuppressorAdaptStep {
+    Ms100,
+    Ms1000,
+    Ms2000,
+    Ms5000,
+}
+
+impl SuppressorAdaptStep {
+    pub fn as_millis(&self) -> f32 {
+        match self {
+            SuppressorAdaptStep::Ms100 => 100.0,
+            SuppressorAdaptStep::Ms1000 => 1000.0,
+            SuppressorAdaptStep::Ms2000 => 2000.0,
+            SuppressorAdaptStep::Ms5000 => 5000.0,
+        }
+    }
+
+    /// Snaps an arbitrary adapt time (in ms) to the closest step, when exactly between two
+    /// steps the shorter one is used. Values outside of the step range (including infinities)
+    /// snap to the shortest or longest step, and NaN snaps to the shortest step.
+    pub fn nearest(millis: f32) -> Self {
+        if millis.is_nan() {
+            return SuppressorAdaptStep::Ms100;
+        }
+
+        let millis = millis.clamp(
+            SuppressorAdaptStep::Ms100.as_millis(),
+            SuppressorAdaptStep::Ms5000.as_millis(),
+        );
+        let mut nearest = SuppressorAdaptStep::Ms100;
+        for step in Self::iter() {
+            if (step.as_millis() - millis).abs() < (nearest.as_millis() - millis).abs() {
+                nearest = step;
+            }
+        }
+        nearest
+    }
+}
+
+impl From<SuppressorAdaptStep> for SupressorAdaptTime {
+    fn from(value: SuppressorAdaptStep) -> Self {
+        SupressorAdaptTime(value.as_millis())
+    }
+}
 
 // enum Suppressor {
 //     Enabled = 0x00,
@@ -104,25 +206,53 @@ pub enum SuppressorStyle {
     #[default]
     Off = 0x00,
     Adaptive = 0x01,
+
+    /// Suppresses against a captured noise profile rather than continuously adapting. No
+    /// separate 'capture now' command has been identified in the protocol, so the library can
+    /// only set this style, it can't request a new capture. When (and how) the device samples
+    /// the ambient noise in this mode hasn't been confirmed, so applications shouldn't assume
+    /// re-sending the style triggers a fresh capture.
     Snapshot = 0x02,
 }
-impl Sealed for SuppressorStyle {}
-impl WriteBeacn for SuppressorStyle {
-    fn write_beacn(&self) -> BeacnValue {
-        let mut buf = [0; 4];
-        LittleEndian::write_u32(&mut buf, *self as u8 as u32);
-        buf
+beacn_enum!(SuppressorStyle);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_step() {
+        assert_eq!(
+            SuppressorAdaptStep::nearest(1500.0),
+            SuppressorAdaptStep::Ms1000
+        );
+        assert_eq!(
+            SuppressorAdaptStep::nearest(1600.0),
+            SuppressorAdaptStep::Ms2000
+        );
+        assert_eq!(
+            SuppressorAdaptStep::nearest(0.0),
+            SuppressorAdaptStep::Ms100
+        );
+        assert_eq!(
+            SuppressorAdaptStep::nearest(9000.0),
+            SuppressorAdaptStep::Ms5000
+        );
     }
-}
 
-impl ReadBeacn for SuppressorStyle {
-    fn read_beacn(buf: &BeacnValue) -> Self {
-        let value = LittleEndian::read_u32(buf);
-        for var in Self::iter() {
-            if var as u32 == value {
-                return var;
-            }
-        }
-        panic!("Could not Find Value");
+    #[test]
+    fn nearest_non_finite() {
+        assert_eq!(
+            SuppressorAdaptStep::nearest(f32::NAN),
+            SuppressorAdaptStep::Ms100
+        );
+        assert_eq!(
+            SuppressorAdaptStep::nearest(f32::NEG_INFINITY),
+            SuppressorAdaptStep::Ms100
+        );
+        assert_eq!(
+            SuppressorAdaptStep::nearest(f32::INFINITY),
+            SuppressorAdaptStep::Ms5000
+        );
     }
 }
@@ -1,22 +1,27 @@
 use crate::BResult;
 use crate::audio::common::{BeacnAudioMessageLocal, open_beacn};
+use crate::audio::transport::{BeacnTransport, UsbTransport};
 use crate::audio::{
     BeacnAudioDevice, BeacnAudioDeviceAttach, BeacnAudioMessageExecute, BeacnAudioMessaging,
     DeviceDefinition,
 };
-use crate::common::BeacnDeviceHandle;
+use crate::common::{BeacnDeviceHandle, Timeouts};
 use crate::manager::{DeviceType, PID_BEACN_MIC};
 use crate::version::VersionNumber;
-use rusb::{DeviceHandle, GlobalContext};
+use log::debug;
+use rusb::{GlobalContext, UsbContext};
+use std::panic::RefUnwindSafe;
 
-pub struct BeacnMic {
-    handle: BeacnDeviceHandle,
+pub struct BeacnMic<C: UsbContext = GlobalContext> {
+    handle: BeacnDeviceHandle<UsbTransport<C>, C>,
 }
 
-impl BeacnAudioDeviceAttach for BeacnMic {
-    fn connect(definition: DeviceDefinition) -> BResult<Box<dyn BeacnAudioDevice>> {
+impl<C: UsbContext> BeacnAudioDeviceAttach for BeacnMic<C> {
+    fn connect<D: UsbContext + RefUnwindSafe + 'static>(
+        definition: DeviceDefinition<D>,
+    ) -> BResult<Box<dyn BeacnAudioDevice>> {
         let handle = open_beacn(definition, PID_BEACN_MIC)?;
-        Ok(Box::new(Self { handle }))
+        Ok(Box::new(BeacnMic { handle }))
     }
 
     fn get_product_id(&self) -> u16 {
@@ -30,18 +35,41 @@ impl BeacnAudioDeviceAttach for BeacnMic {
     fn get_version(&self) -> VersionNumber {
         self.handle.version
     }
+
+    fn get_extra_device_info(&self) -> Vec<u8> {
+        self.handle.extra_info.clone()
+    }
+
+    fn get_manufacturer_string(&self) -> String {
+        self.handle.manufacturer.clone()
+    }
+
+    fn get_product_string(&self) -> String {
+        self.handle.product.clone()
+    }
 }
 
-impl BeacnAudioMessageExecute for BeacnMic {
+impl<C: UsbContext> BeacnAudioMessageExecute for BeacnMic<C> {
     fn get_device_type(&self) -> DeviceType {
         DeviceType::BeacnMic
     }
 
-    fn get_usb_handle(&self) -> &DeviceHandle<GlobalContext> {
+    fn get_transport(&self) -> &dyn BeacnTransport {
         &self.handle.handle
     }
+
+    fn get_timeouts(&self) -> Timeouts {
+        self.handle.timeouts
+    }
 }
 
-impl BeacnAudioMessaging for BeacnMic {}
-impl BeacnAudioMessageLocal for BeacnMic {}
-impl BeacnAudioDevice for BeacnMic {}
+impl<C: UsbContext> BeacnAudioMessaging for BeacnMic<C> {}
+impl<C: UsbContext> BeacnAudioMessageLocal for BeacnMic<C> {}
+impl<C: UsbContext + RefUnwindSafe> BeacnAudioDevice for BeacnMic<C> {}
+
+impl<C: UsbContext> Drop for BeacnMic<C> {
+    fn drop(&mut self) {
+        debug!(serial = self.handle.serial.as_str(); "Dropping BeacnMic");
+        self.handle.handle.release();
+    }
+}
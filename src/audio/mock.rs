@@ -0,0 +1,188 @@
+use crate::audio::common::BeacnAudioMessageLocal;
+use crate::audio::transport::BeacnTransport;
+use crate::audio::{
+    BeacnAudioDevice, BeacnAudioDeviceAttach, BeacnAudioMessageExecute, BeacnAudioMessaging,
+    DeviceDefinition,
+};
+use crate::common::Timeouts;
+use crate::manager::DeviceType;
+use crate::version::VersionNumber;
+use crate::{BResult, beacn_bail};
+use rusb::UsbContext;
+use std::collections::{HashMap, VecDeque};
+use std::panic::RefUnwindSafe;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// An in-memory transport which behaves like a Beacn Audio device, parameters are stored in a
+/// key -> value map, so anything set can be fetched back. Fetching a key which hasn't been set
+/// (or seeded with `set_raw`) will result in a Timeout, the same as a real device which doesn't
+/// respond.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    values: Mutex<HashMap<[u8; 3], [u8; 4]>>,
+    responses: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl MockTransport {
+    pub fn set_raw(&self, key: [u8; 3], value: [u8; 4]) {
+        self.values.lock().unwrap().insert(key, value);
+    }
+
+    pub fn get_raw(&self, key: [u8; 3]) -> Option<[u8; 4]> {
+        self.values.lock().unwrap().get(&key).copied()
+    }
+}
+
+impl BeacnTransport for MockTransport {
+    fn write(&self, data: &[u8], _timeout: Duration) -> BResult<usize> {
+        if data.len() < 4 {
+            beacn_bail!("Mock Transport received a short packet: {:?}", data);
+        }
+
+        let key: [u8; 3] = data[0..3].try_into().unwrap();
+        match data[3] {
+            // Parameter Lookup
+            0xa3 => {
+                if let Some(value) = self.get_raw(key) {
+                    let mut response = vec![key[0], key[1], key[2], 0xa4];
+                    response.extend_from_slice(&value);
+                    self.responses.lock().unwrap().push_back(response);
+                }
+            }
+            // Parameter Set
+            0xa4 => {
+                if data.len() != 8 {
+                    beacn_bail!("Mock Transport received a bad set packet: {:?}", data);
+                }
+                self.set_raw(key, data[4..8].try_into().unwrap());
+            }
+            // Linked Apps request, respond as if PC2 isn't connected
+            0xac if key == [0x00, 0x00, 0x01] => {
+                self.responses
+                    .lock()
+                    .unwrap()
+                    .push_back(vec![0xff, 0xff, 0xff, 0xac]);
+            }
+            // Linked App change, nothing to store
+            0xac => {}
+            command => beacn_bail!("Mock Transport doesn't support command {:#04x}", command),
+        }
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8], _timeout: Duration) -> BResult<usize> {
+        let Some(response) = self.responses.lock().unwrap().pop_front() else {
+            return Err(rusb::Error::Timeout.into());
+        };
+
+        let length = response.len().min(buf.len());
+        buf[..length].copy_from_slice(&response[..length]);
+        Ok(length)
+    }
+}
+
+/// A fake Beacn Audio device backed by a `MockTransport`, useful for testing code which
+/// handles messages without needing a real device attached.
+pub struct MockAudioDevice {
+    device_type: DeviceType,
+    version: VersionNumber,
+    transport: MockTransport,
+}
+
+impl MockAudioDevice {
+    pub fn new(device_type: DeviceType, version: VersionNumber) -> Self {
+        Self {
+            device_type,
+            version,
+            transport: MockTransport::default(),
+        }
+    }
+
+    pub fn get_mock_transport(&self) -> &MockTransport {
+        &self.transport
+    }
+}
+
+impl BeacnAudioDeviceAttach for MockAudioDevice {
+    #[allow(private_interfaces)]
+    fn connect<C: UsbContext + RefUnwindSafe + 'static>(
+        _device: DeviceDefinition<C>,
+    ) -> BResult<Box<dyn BeacnAudioDevice>> {
+        beacn_bail!("Mock Devices cannot be connected, use MockAudioDevice::new")
+    }
+
+    fn get_product_id(&self) -> u16 {
+        0
+    }
+
+    fn get_serial(&self) -> String {
+        String::from("MOCK")
+    }
+
+    fn get_version(&self) -> VersionNumber {
+        self.version
+    }
+
+    fn get_extra_device_info(&self) -> Vec<u8> {
+        vec![]
+    }
+
+    fn get_manufacturer_string(&self) -> String {
+        String::from("Beacn")
+    }
+
+    fn get_product_string(&self) -> String {
+        String::from("Mock Audio Device")
+    }
+}
+
+impl BeacnAudioMessageExecute for MockAudioDevice {
+    fn get_device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    fn get_transport(&self) -> &dyn BeacnTransport {
+        &self.transport
+    }
+
+    fn get_timeouts(&self) -> Timeouts {
+        Timeouts::for_device(self.device_type)
+    }
+}
+
+impl BeacnAudioMessaging for MockAudioDevice {}
+impl BeacnAudioMessageLocal for MockAudioDevice {}
+impl BeacnAudioDevice for MockAudioDevice {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::messages::Message;
+    use crate::audio::messages::equaliser::{EQBand, EQGain, EQMode, Equaliser};
+    use crate::audio::{SetResult, VerifyMode};
+
+    #[test]
+    fn equaliser_gain_round_trip() {
+        let device = MockAudioDevice::new(DeviceType::BeacnMic, VersionNumber(1, 2, 0, 80));
+
+        let gain = Equaliser::Gain(EQMode::Simple, EQBand::Band3, EQGain(-4.5));
+        let result = device.set_message(Message::Equaliser(gain)).unwrap();
+        assert!(matches!(result, SetResult::Applied(Message::Equaliser(value)) if value == gain));
+
+        let fetch = Equaliser::GetGain(EQMode::Simple, EQBand::Band3);
+        let value = device.handle_message(Message::Equaliser(fetch)).unwrap();
+        assert_eq!(value, Message::Equaliser(gain));
+    }
+
+    #[test]
+    fn unverified_set_returns_sent_value() {
+        let device = MockAudioDevice::new(DeviceType::BeacnMic, VersionNumber(1, 2, 0, 80));
+
+        let gain = Message::Equaliser(Equaliser::Gain(EQMode::Simple, EQBand::Band1, EQGain(2.0)));
+        let result = device
+            .set_message_with_verify(gain, VerifyMode::Never)
+            .unwrap();
+        assert!(matches!(result, SetResult::Unverified(value) if value == gain));
+    }
+}
@@ -1,15 +1,36 @@
+mod capabilities;
 mod common;
+mod diff;
 pub mod messages;
 mod mic;
+mod mock;
+#[cfg(feature = "raw-access")]
+mod raw;
+mod shared;
+mod snapshot;
 mod studio;
+mod transport;
 
+pub use crate::audio::capabilities::{Capabilities, capabilities};
+pub(crate) use crate::audio::common::attach_beacn;
 use crate::audio::common::{BeacnAudioDeviceAttach, BeacnAudioMessageExecute, BeacnAudioMessaging};
+pub use crate::audio::common::{SetResult, VerifyMode};
+pub use crate::audio::diff::{ProfileDiff, diff_profiles};
 use crate::audio::mic::BeacnMic;
+pub use crate::audio::mock::{MockAudioDevice, MockTransport};
+#[cfg(feature = "raw-access")]
+pub use crate::audio::raw::BeacnAudioRawAccess;
+pub use crate::audio::shared::SharedAudioDevice;
+pub use crate::audio::snapshot::DeviceSnapshot;
 use crate::audio::studio::BeacnStudio;
-use crate::common::{DeviceDefinition, find_device};
-use crate::manager::{DeviceLocation, PID_BEACN_MIC, PID_BEACN_STUDIO};
-use crate::{BResult, beacn_bail};
+pub use crate::audio::studio::BeacnStudioControls;
+pub use crate::audio::transport::BeacnTransport;
+use crate::common::{DeviceDefinition, Timeouts, find_device};
+use crate::manager::{DeviceLocation, PID_BEACN_MIC, PID_BEACN_STUDIO, find_device_by_serial};
+use crate::{BResult, BeacnError, beacn_bail};
 use enum_map::Enum;
+use rusb::{GlobalContext, UsbContext};
+use std::fmt::{Display, Formatter};
 use std::panic::RefUnwindSafe;
 use strum::EnumIter;
 
@@ -19,17 +40,57 @@ pub trait BeacnAudioDevice:
 }
 
 pub fn open_audio_device(location: DeviceLocation) -> BResult<Box<dyn BeacnAudioDevice>> {
-    if let Some(device) = find_device(location) {
+    open_audio_device_with_context(&GlobalContext::default(), location)
+}
+
+/// The same as open_audio_device, but the device is found and opened using the given libusb
+/// context, rather than the global one.
+pub fn open_audio_device_with_context<C: UsbContext + RefUnwindSafe + 'static>(
+    context: &C,
+    location: DeviceLocation,
+) -> BResult<Box<dyn BeacnAudioDevice>> {
+    open_audio(context, location, None)
+}
+
+/// The same as open_audio_device, but the device uses the given timeouts rather than the
+/// defaults for its type, see Timeouts.
+pub fn open_audio_device_with_timeouts(
+    location: DeviceLocation,
+    timeouts: Timeouts,
+) -> BResult<Box<dyn BeacnAudioDevice>> {
+    open_audio(&GlobalContext::default(), location, Some(timeouts))
+}
+
+fn open_audio<C: UsbContext + RefUnwindSafe + 'static>(
+    context: &C,
+    location: DeviceLocation,
+    timeouts: Option<Timeouts>,
+) -> BResult<Box<dyn BeacnAudioDevice>> {
+    if let Some(mut device) = find_device(context, location) {
+        device.timeouts = timeouts;
+
         // We need to return the correct type
-        return if PID_BEACN_MIC.contains(&device.descriptor.product_id()) {
-            BeacnMic::connect(device)
-        } else if PID_BEACN_STUDIO.contains(&device.descriptor.product_id()) {
-            BeacnStudio::connect(device)
+        let product_id = device.descriptor.product_id();
+        return if PID_BEACN_MIC.contains(&product_id) {
+            BeacnMic::<C>::connect(device)
+        } else if PID_BEACN_STUDIO.contains(&product_id) {
+            BeacnStudio::<C>::connect(device)
         } else {
-            beacn_bail!("Unknown Device")
+            Err(BeacnError::UnexpectedPid {
+                expected: [PID_BEACN_MIC, PID_BEACN_STUDIO].concat(),
+                found: product_id,
+            })
         };
     }
-    beacn_bail!("Unknown Device")
+    Err(BeacnError::DeviceNotFound(location))
+}
+
+/// Opens the Beacn Mic or Studio with the given USB serial number, see find_device_by_serial
+pub fn open_audio_device_by_serial(serial: &str) -> BResult<Box<dyn BeacnAudioDevice>> {
+    let Some(location) = find_device_by_serial(serial) else {
+        beacn_bail!("No Beacn device found with serial {}", serial);
+    };
+    open_audio_device(location)
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +100,14 @@ pub struct LinkedApp {
     pub name: String,
 }
 
+/// Whether PC2 (Beacn's desktop app) is connected to the Studio, and if so, the apps it knows
+/// about and the channel they're linked to.
+#[derive(Debug, Clone)]
+pub enum Pc2Status {
+    NotConnected,
+    Connected(Vec<LinkedApp>),
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Enum, EnumIter)]
 pub enum LinkChannel {
     System,
@@ -58,4 +127,21 @@ impl LinkChannel {
             _ => LinkChannel::System,
         }
     }
+
+    /// The channel's index as sent to the device, System is 0 and the Links are 1 to 4
+    pub fn as_index(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl Display for LinkChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkChannel::System => write!(f, "System"),
+            LinkChannel::Link1 => write!(f, "Link 1"),
+            LinkChannel::Link2 => write!(f, "Link 2"),
+            LinkChannel::Link3 => write!(f, "Link 3"),
+            LinkChannel::Link4 => write!(f, "Link 4"),
+        }
+    }
 }
@@ -0,0 +1,27 @@
+use crate::audio::BeacnAudioMessaging;
+use crate::audio::common::{ParamSetResult, VerifyMode};
+use crate::{BResult, beacn_bail};
+
+/// Direct access to the device's parameters by their raw key, bypassing the typed Message
+/// layer. This is intended for exploring the protocol (for example, finding parameters added in
+/// new firmware), so no validation is performed on the keys or values, and anything written
+/// goes straight to the device.
+pub trait BeacnAudioRawAccess: BeacnAudioMessaging {
+    /// Looks up a parameter, returning the device's full 8 byte response
+    fn read_raw(&self, key: [u8; 3]) -> BResult<[u8; 8]> {
+        self.param_lookup(key, None)
+    }
+
+    /// Sets a parameter, and returns the device's full 8 byte response when it's read back
+    fn write_raw(&self, key: [u8; 3], value: [u8; 4]) -> BResult<[u8; 8]> {
+        match self.param_set(key, value, None, VerifyMode::Once)? {
+            ParamSetResult::Applied(response)
+            | ParamSetResult::Adjusted(response)
+            | ParamSetResult::Rejected(response)
+            | ParamSetResult::Mismatched(response) => Ok(response),
+            ParamSetResult::Unverified => beacn_bail!("Value was not read back from the device"),
+        }
+    }
+}
+
+impl<T: BeacnAudioMessaging + ?Sized> BeacnAudioRawAccess for T {}
@@ -0,0 +1,334 @@
+use crate::audio::BeacnAudioDevice;
+use crate::audio::messages::Message;
+use crate::audio::{LinkedApp, Pc2Status, SetResult, VerifyMode, open_audio_device_with_context};
+use crate::manager::{DeviceLocation, DeviceType};
+use crate::types::BeacnValue;
+use crate::version::VersionNumber;
+use crate::{BResult, beacn_bail};
+use crossbeam::channel::{Receiver, Sender, bounded, never, tick, unbounded};
+use crossbeam::select;
+use log::debug;
+use rusb::{GlobalContext, UsbContext};
+use std::collections::HashMap;
+use std::panic::RefUnwindSafe;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Messages sent to the thread which owns the USB handle, each carries a channel for the response
+enum AudioThreadMessage {
+    HandleMessage(Message, Option<Duration>, Sender<BResult<Message>>),
+    SetMessage(Message, VerifyMode, Sender<BResult<SetResult>>),
+    CheckReconnected(Sender<BResult<bool>>),
+    GetLinkedApps(Sender<BResult<Pc2Status>>),
+    SetLinkedApp(LinkedApp, Sender<BResult<()>>),
+    SetChangeListener(Option<(Sender<Message>, Duration)>, Sender<BResult<()>>),
+}
+
+// How many parameters the change watcher reads on each tick. Reading every parameter at once
+// would hold up any requests queued behind the poll, so the list is worked through in batches.
+static POLL_BATCH_SIZE: usize = 16;
+
+// Periodically re-reads the device's parameters, and reports those which have changed
+struct ChangeWatcher {
+    sender: Sender<Message>,
+    ticker: Receiver<Instant>,
+
+    // The parameters being watched, and where the next poll should start from
+    messages: Vec<Message>,
+    position: usize,
+
+    // The last known value of each parameter, by key
+    values: HashMap<[u8; 3], BeacnValue>,
+}
+
+impl ChangeWatcher {
+    fn new(device: &dyn BeacnAudioDevice, sender: Sender<Message>, interval: Duration) -> Self {
+        let version = device.get_version();
+        let messages = Message::generate_fetch_message(device.get_device_type())
+            .into_iter()
+            .filter(|message| {
+                device.supports(message)
+                    && version >= message.get_message_minimum_version()
+                    && version <= message.get_message_maximum_version()
+            })
+            .collect();
+
+        Self {
+            sender,
+            ticker: tick(interval),
+            messages,
+            position: 0,
+            values: HashMap::new(),
+        }
+    }
+
+    // Records a value, returning true if it differs from a value we previously had
+    fn update(&mut self, message: Message) -> bool {
+        let Ok(value) = message.try_to_beacn_value() else {
+            return false;
+        };
+        self.values
+            .insert(message.to_beacn_key(), value)
+            .is_some_and(|previous| previous != value)
+    }
+
+    // Reads the next batch of parameters and sends any changes, returns false if the listener
+    // has gone. The first read of a parameter only records its value, so only changes made
+    // after the listener was set are reported.
+    fn poll(&mut self, device: &dyn BeacnAudioDevice) -> bool {
+        let serial = device.get_serial();
+        for _ in 0..POLL_BATCH_SIZE.min(self.messages.len()) {
+            let message = self.messages[self.position];
+            self.position = (self.position + 1) % self.messages.len();
+
+            // A parameter failing to read shouldn't stop the rest from being watched
+            let value = match device.handle_message(message) {
+                Ok(value) => value,
+                Err(e) => {
+                    debug!(serial = serial.as_str(); "Unable to poll {}: {}", message, e);
+                    continue;
+                }
+            };
+
+            if self.update(value) && self.sender.send(value).is_err() {
+                debug!(serial = serial.as_str(); "Change listener closed, no longer polling");
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A thread safe wrapper around a Beacn Audio device. The device itself is owned by a dedicated
+/// thread, and all requests are passed to it over a channel, so calls from multiple threads are
+/// serialised and never interleave their USB reads and writes.
+///
+/// The thread is stopped, and the device released, when this is dropped.
+pub struct SharedAudioDevice {
+    product_id: u16,
+    serial: String,
+    version: VersionNumber,
+    device_type: DeviceType,
+    manufacturer: String,
+    product: String,
+
+    sender: Sender<AudioThreadMessage>,
+}
+
+impl SharedAudioDevice {
+    pub fn open(location: DeviceLocation) -> BResult<Self> {
+        Self::open_with_context(GlobalContext::default(), location)
+    }
+
+    /// The same as open, but the device is found and opened using the given libusb context,
+    /// rather than the global one.
+    pub fn open_with_context<C: UsbContext + RefUnwindSafe + 'static>(
+        context: C,
+        location: DeviceLocation,
+    ) -> BResult<Self> {
+        let (sender, receiver) = unbounded();
+        let (ready_tx, ready_rx) = bounded(1);
+
+        thread::spawn(move || {
+            // The device isn't Send, so it needs to be opened on the thread that'll own it
+            let device = match open_audio_device_with_context(&context, location) {
+                Ok(device) => device,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            let _ = ready_tx.send(Ok((
+                device.get_product_id(),
+                device.get_serial(),
+                device.get_version(),
+                device.get_device_type(),
+                device.get_manufacturer_string(),
+                device.get_product_string(),
+            )));
+
+            debug!(serial = device.get_serial().as_str(); "Spawning Shared Audio Handler");
+            let mut watcher: Option<ChangeWatcher> = None;
+            loop {
+                let poll = match &watcher {
+                    Some(watcher) => watcher.ticker.clone(),
+                    None => never(),
+                };
+
+                let message = select! {
+                    recv(receiver) -> message => match message {
+                        Ok(message) => message,
+                        Err(_) => break,
+                    },
+                    recv(poll) -> _ => {
+                        let active = watcher.as_mut().is_some_and(|w| w.poll(device.as_ref()));
+                        if !active {
+                            watcher = None;
+                        }
+                        continue;
+                    }
+                };
+
+                match message {
+                    AudioThreadMessage::HandleMessage(message, timeout, tx) => {
+                        let result = match timeout {
+                            Some(timeout) => device.handle_message_with_timeout(message, timeout),
+                            None => device.handle_message(message),
+                        };
+
+                        // Our own changes shouldn't be reported to the change listener
+                        if let (Some(watcher), Ok(value)) = (&mut watcher, &result) {
+                            watcher.update(*value);
+                        }
+                        let _ = tx.send(result);
+                    }
+                    AudioThreadMessage::SetMessage(message, verify, tx) => {
+                        let result = device.set_message_with_verify(message, verify);
+                        if let (Some(watcher), Ok(value)) = (&mut watcher, &result) {
+                            watcher.update(value.get_message());
+                        }
+                        let _ = tx.send(result);
+                    }
+                    AudioThreadMessage::CheckReconnected(tx) => {
+                        let _ = tx.send(Ok(device.check_reconnected()));
+                    }
+                    AudioThreadMessage::GetLinkedApps(tx) => {
+                        let _ = tx.send(device.get_linked_app_list());
+                    }
+                    AudioThreadMessage::SetLinkedApp(app, tx) => {
+                        let _ = tx.send(device.set_linked_app(app));
+                    }
+                    AudioThreadMessage::SetChangeListener(listener, tx) => {
+                        watcher = listener.map(|(sender, interval)| {
+                            ChangeWatcher::new(device.as_ref(), sender, interval)
+                        });
+                        let _ = tx.send(Ok(()));
+                    }
+                }
+            }
+            debug!(serial = device.get_serial().as_str(); "Shared Audio Handler Terminated");
+        });
+
+        let Ok(result) = ready_rx.recv() else {
+            beacn_bail!("Audio device thread terminated during setup");
+        };
+        let (product_id, serial, version, device_type, manufacturer, product) = result?;
+
+        Ok(Self {
+            product_id,
+            serial,
+            version,
+            device_type,
+            manufacturer,
+            product,
+            sender,
+        })
+    }
+
+    pub fn get_product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn get_serial(&self) -> String {
+        self.serial.clone()
+    }
+
+    pub fn get_version(&self) -> VersionNumber {
+        self.version
+    }
+
+    pub fn get_device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    pub fn get_manufacturer_string(&self) -> String {
+        self.manufacturer.clone()
+    }
+
+    pub fn get_product_string(&self) -> String {
+        self.product.clone()
+    }
+
+    /// See BeacnAudioMessaging::handle_message
+    pub fn handle_message(&self, message: Message) -> BResult<Message> {
+        self.request(|tx| AudioThreadMessage::HandleMessage(message, None, tx))
+    }
+
+    pub fn handle_message_with_timeout(
+        &self,
+        message: Message,
+        timeout: Duration,
+    ) -> BResult<Message> {
+        self.request(|tx| AudioThreadMessage::HandleMessage(message, Some(timeout), tx))
+    }
+
+    pub fn set_message(&self, message: Message) -> BResult<SetResult> {
+        self.request(|tx| AudioThreadMessage::SetMessage(message, VerifyMode::default(), tx))
+    }
+
+    /// See BeacnAudioMessaging::set_value_clamped
+    pub fn set_value_clamped(&self, message: Message) -> BResult<Message> {
+        Ok(self.set_message(message)?.get_message())
+    }
+
+    pub fn set_message_with_verify(
+        &self,
+        message: Message,
+        verify: VerifyMode,
+    ) -> BResult<SetResult> {
+        self.request(|tx| AudioThreadMessage::SetMessage(message, verify, tx))
+    }
+
+    pub fn check_reconnected(&self) -> BResult<bool> {
+        self.request(AudioThreadMessage::CheckReconnected)
+    }
+
+    pub fn get_linked_app_list(&self) -> BResult<Pc2Status> {
+        self.request(AudioThreadMessage::GetLinkedApps)
+    }
+
+    pub fn set_linked_app(&self, app: LinkedApp) -> BResult<()> {
+        self.request(|tx| AudioThreadMessage::SetLinkedApp(app, tx))
+    }
+
+    /// Polls the device every interval, and sends any parameters which have changed since the
+    /// last poll to the listener, for example when another app has changed a value. Changes
+    /// made through this SharedAudioDevice aren't reported. This replaces any existing listener,
+    /// and polling stops if the listener is dropped.
+    ///
+    /// The Mic and Studio aren't known to send change notifications of their own, so polling
+    /// is the only way to pick these up. Each poll only reads a batch of parameters, working
+    /// through all of them over several intervals, so a change may take a few intervals to be
+    /// reported.
+    pub fn set_change_listener(
+        &self,
+        listener: Sender<Message>,
+        interval: Duration,
+    ) -> BResult<()> {
+        if interval.is_zero() {
+            beacn_bail!("Polling interval must be greater than zero");
+        }
+        self.request(|tx| AudioThreadMessage::SetChangeListener(Some((listener, interval)), tx))
+    }
+
+    /// Stops polling for changes
+    pub fn clear_change_listener(&self) -> BResult<()> {
+        self.request(|tx| AudioThreadMessage::SetChangeListener(None, tx))
+    }
+
+    fn request<T>(
+        &self,
+        message: impl FnOnce(Sender<BResult<T>>) -> AudioThreadMessage,
+    ) -> BResult<T> {
+        let (tx, rx) = bounded(1);
+        if self.sender.send(message(tx)).is_err() {
+            beacn_bail!("Audio device thread has stopped");
+        }
+
+        let Ok(result) = rx.recv() else {
+            beacn_bail!("Audio device thread stopped before responding");
+        };
+        result
+    }
+}
@@ -0,0 +1,62 @@
+use crate::audio::messages::Message;
+use crate::manager::DeviceType;
+use crate::version::VersionNumber;
+
+/// The identity of an audio device along with every parameter it reports, see
+/// BeacnAudioMessaging::snapshot. Parameters are grouped by category (for example
+/// "Compressor"), in the same order as Message::generate_fetch_message.
+#[derive(Debug, Clone)]
+pub struct DeviceSnapshot {
+    pub(crate) serial: String,
+    pub(crate) version: VersionNumber,
+    pub(crate) device_type: DeviceType,
+    pub(crate) parameters: Vec<(&'static str, Vec<Message>)>,
+}
+
+impl DeviceSnapshot {
+    pub fn get_serial(&self) -> String {
+        self.serial.clone()
+    }
+
+    pub fn get_version(&self) -> VersionNumber {
+        self.version
+    }
+
+    pub fn get_device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    /// All the parameter groups, as (category, values)
+    pub fn get_parameters(&self) -> &[(&'static str, Vec<Message>)] {
+        &self.parameters
+    }
+
+    /// The values for a single category, None if the device doesn't have it
+    pub fn get_category(&self, category: &str) -> Option<&[Message]> {
+        self.parameters
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map(|(_, messages)| messages.as_slice())
+    }
+
+    /// Every value in the snapshot, in category order, this can be passed to diff_profiles
+    pub fn get_messages(&self) -> Vec<Message> {
+        self.parameters
+            .iter()
+            .flat_map(|(_, messages)| messages.iter().copied())
+            .collect()
+    }
+
+    // Adds a value to its category, creating the category if this is the first value in it
+    pub(crate) fn push(&mut self, message: Message) {
+        let category = message.get_category();
+        match self
+            .parameters
+            .iter_mut()
+            .find(|(name, _)| *name == category)
+        {
+            Some((_, messages)) => messages.push(message),
+            None => self.parameters.push((category, vec![message])),
+        }
+    }
+}
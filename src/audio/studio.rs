@@ -1,24 +1,32 @@
-use crate::BResult;
 use crate::audio::common::{BeacnAudioMessageLocal, open_beacn};
+use crate::audio::messages::Message;
+use crate::audio::messages::mic_setup::{MicSetup, StudioMicGain};
+use crate::audio::transport::{BeacnTransport, UsbTransport};
 use crate::audio::{
     BeacnAudioDevice, BeacnAudioDeviceAttach, BeacnAudioMessageExecute, BeacnAudioMessaging,
     DeviceDefinition,
 };
-use crate::common::BeacnDeviceHandle;
+use crate::common::{BeacnDeviceHandle, Timeouts};
 use crate::manager::{DeviceType, PID_BEACN_STUDIO};
+use crate::types::HasRange;
 use crate::version::VersionNumber;
-use rusb::{DeviceHandle, GlobalContext};
+use crate::{BResult, beacn_bail, out_of_range};
+use log::debug;
+use rusb::{GlobalContext, UsbContext};
+use std::panic::RefUnwindSafe;
 
-pub struct BeacnStudio {
-    handle: BeacnDeviceHandle,
+pub struct BeacnStudio<C: UsbContext = GlobalContext> {
+    handle: BeacnDeviceHandle<UsbTransport<C>, C>,
 }
 
-impl BeacnAudioDeviceAttach for BeacnStudio {
-    fn connect(definition: DeviceDefinition) -> BResult<Box<dyn BeacnAudioDevice>> {
+impl<C: UsbContext> BeacnAudioDeviceAttach for BeacnStudio<C> {
+    fn connect<D: UsbContext + RefUnwindSafe + 'static>(
+        definition: DeviceDefinition<D>,
+    ) -> BResult<Box<dyn BeacnAudioDevice>> {
         let handle = open_beacn(definition, PID_BEACN_STUDIO)?;
 
         // TODO: Spawn Thread to manage inputs
-        Ok(Box::new(Self { handle }))
+        Ok(Box::new(BeacnStudio { handle }))
     }
 
     fn get_product_id(&self) -> u16 {
@@ -32,18 +40,96 @@ impl BeacnAudioDeviceAttach for BeacnStudio {
     fn get_version(&self) -> VersionNumber {
         self.handle.version
     }
+
+    fn get_extra_device_info(&self) -> Vec<u8> {
+        self.handle.extra_info.clone()
+    }
+
+    fn get_manufacturer_string(&self) -> String {
+        self.handle.manufacturer.clone()
+    }
+
+    fn get_product_string(&self) -> String {
+        self.handle.product.clone()
+    }
 }
 
-impl BeacnAudioMessageExecute for BeacnStudio {
+impl<C: UsbContext> BeacnAudioMessageExecute for BeacnStudio<C> {
     fn get_device_type(&self) -> DeviceType {
         DeviceType::BeacnStudio
     }
 
-    fn get_usb_handle(&self) -> &DeviceHandle<GlobalContext> {
+    fn get_transport(&self) -> &dyn BeacnTransport {
         &self.handle.handle
     }
+
+    fn get_timeouts(&self) -> Timeouts {
+        self.handle.timeouts
+    }
+}
+
+impl<C: UsbContext> BeacnAudioMessaging for BeacnStudio<C> {}
+impl<C: UsbContext> BeacnAudioMessageLocal for BeacnStudio<C> {}
+impl<C: UsbContext + RefUnwindSafe> BeacnAudioDevice for BeacnStudio<C> {}
+
+impl<C: UsbContext> Drop for BeacnStudio<C> {
+    fn drop(&mut self) {
+        debug!(serial = self.handle.serial.as_str(); "Dropping BeacnStudio");
+        self.handle.handle.release();
+    }
+}
+
+/// Convenience methods for the Studio's most common controls, these wrap the relevant
+/// `MicSetup` messages, and are available on any Beacn Audio device, but will fail on anything
+/// other than a Studio.
+pub trait BeacnStudioControls: BeacnAudioMessaging {
+    fn set_phantom_power(&self, enabled: bool) -> BResult<()> {
+        self.check_studio()?;
+        let message = MicSetup::StudioPhantomPower(enabled);
+        self.handle_message(Message::MicSetup(message))?;
+        Ok(())
+    }
+
+    fn get_phantom_power(&self) -> BResult<bool> {
+        self.check_studio()?;
+        let message = MicSetup::GetStudioPhantomPower;
+        match self.handle_message(Message::MicSetup(message))? {
+            Message::MicSetup(MicSetup::StudioPhantomPower(enabled)) => Ok(enabled),
+            response => beacn_bail!("Unexpected Response: {}", response),
+        }
+    }
+
+    fn set_studio_mic_gain(&self, gain: u32) -> BResult<()> {
+        self.check_studio()?;
+        if !StudioMicGain::range().contains(&gain) {
+            return Err(out_of_range(
+                "Studio Mic Gain",
+                gain,
+                &StudioMicGain::range(),
+            ));
+        }
+
+        let message = MicSetup::StudioMicGain(StudioMicGain(gain));
+        self.handle_message(Message::MicSetup(message))?;
+        Ok(())
+    }
+
+    fn get_studio_mic_gain(&self) -> BResult<u32> {
+        self.check_studio()?;
+        let message = MicSetup::GetStudioMicGain;
+        match self.handle_message(Message::MicSetup(message))? {
+            Message::MicSetup(MicSetup::StudioMicGain(gain)) => Ok(gain.0),
+            response => beacn_bail!("Unexpected Response: {}", response),
+        }
+    }
+
+    #[doc(hidden)]
+    fn check_studio(&self) -> BResult<()> {
+        if self.get_device_type() != DeviceType::BeacnStudio {
+            beacn_bail!("This can only be executed on a Beacn Studio");
+        }
+        Ok(())
+    }
 }
 
-impl BeacnAudioMessaging for BeacnStudio {}
-impl BeacnAudioMessageLocal for BeacnStudio {}
-impl BeacnAudioDevice for BeacnStudio {}
+impl<T: BeacnAudioMessaging + ?Sized> BeacnStudioControls for T {}
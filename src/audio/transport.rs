@@ -0,0 +1,162 @@
+use crate::audio::common::attach_beacn;
+use crate::common::{fallback_serial, find_device};
+use crate::manager::DeviceLocation;
+use crate::{BResult, beacn_bail};
+use log::{debug, warn};
+use rusb::{DeviceHandle, UsbContext};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+// How many times, and how often, we'll look for a device which has dropped off the bus
+static RECONNECT_ATTEMPTS: u8 = 5;
+static RECONNECT_DELAY: Duration = Duration::from_millis(200);
+
+/// The raw transport used to talk to a Beacn Audio device. Messages are written to, and
+/// responses read from, the device's bulk endpoints. This is abstracted so that the message
+/// handling can be driven by something other than a real USB device (see `MockTransport`).
+pub trait BeacnTransport {
+    fn write(&self, data: &[u8], timeout: Duration) -> BResult<usize>;
+    fn read(&self, buf: &mut [u8], timeout: Duration) -> BResult<usize>;
+
+    /// Attempts to re-attach to the device after it has been lost, transports which can't
+    /// do this will simply fail.
+    fn reconnect(&self) -> BResult<()> {
+        beacn_bail!("This transport does not support reconnecting");
+    }
+
+    /// Attempts to clear a stalled endpoint, while the device is still attached. Transports which
+    /// can't do this will simply fail.
+    fn clear_stall(&self) -> BResult<()> {
+        beacn_bail!("This transport does not support clearing a stall");
+    }
+
+    /// Returns true if a reconnect has happened since this was last called
+    fn take_reconnected(&self) -> bool {
+        false
+    }
+}
+
+impl<C: UsbContext> BeacnTransport for DeviceHandle<C> {
+    fn write(&self, data: &[u8], timeout: Duration) -> BResult<usize> {
+        Ok(self.write_bulk(0x03, data, timeout)?)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Duration) -> BResult<usize> {
+        Ok(self.read_bulk(0x83, buf, timeout)?)
+    }
+}
+
+/// The USB transport used by the Mic and Studio, this keeps track of where the device is, so
+/// that if it briefly drops off the bus, the handle can be replaced without the device needing
+/// to be re-opened by the caller.
+#[derive(Debug)]
+pub(crate) struct UsbTransport<C: UsbContext> {
+    handle: Mutex<DeviceHandle<C>>,
+    location: DeviceLocation,
+    serial: String,
+    setup_timeout: Duration,
+    reconnected: AtomicBool,
+}
+
+impl<C: UsbContext> UsbTransport<C> {
+    pub(crate) fn new(
+        handle: DeviceHandle<C>,
+        location: DeviceLocation,
+        serial: String,
+        setup_timeout: Duration,
+    ) -> Self {
+        Self {
+            handle: Mutex::new(handle),
+            location,
+            serial,
+            setup_timeout,
+            reconnected: AtomicBool::new(false),
+        }
+    }
+
+    fn attach(&self, context: &C) -> BResult<DeviceHandle<C>> {
+        let Some(definition) = find_device(context, self.location) else {
+            beacn_bail!("Device not found at {}", self.location);
+        };
+
+        let handle = definition.device.open()?;
+        let (_, serial, _) = attach_beacn(&handle, self.setup_timeout)?;
+        let serial = serial.unwrap_or_else(|| fallback_serial(self.location));
+
+        // Make sure we haven't found a different device which has appeared at this location
+        if serial != self.serial {
+            beacn_bail!("Expected device {} but found {}", self.serial, serial);
+        }
+        Ok(handle)
+    }
+
+    // Returns the endpoint and interface to the state they were in before we attached, so the
+    // device can be opened again straight away (by us, or another process)
+    pub(crate) fn release(&self) {
+        let Ok(handle) = self.handle.lock() else {
+            return;
+        };
+        if let Err(e) = handle.clear_halt(0x83) {
+            debug!(serial = self.serial.as_str(); "Unable to clear halt: {}", e);
+        }
+        if let Err(e) = handle.release_interface(3) {
+            debug!(serial = self.serial.as_str(); "Unable to release interface: {}", e);
+        }
+    }
+}
+
+impl<C: UsbContext> BeacnTransport for UsbTransport<C> {
+    fn write(&self, data: &[u8], timeout: Duration) -> BResult<usize> {
+        self.handle.lock().unwrap().write(data, timeout)
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Duration) -> BResult<usize> {
+        self.handle.lock().unwrap().read(buf, timeout)
+    }
+
+    fn clear_stall(&self) -> BResult<()> {
+        let handle = self.handle.lock().unwrap();
+        handle.clear_halt(0x03)?;
+        handle.clear_halt(0x83)?;
+        Ok(())
+    }
+
+    fn reconnect(&self) -> BResult<()> {
+        let mut handle = self.handle.lock().unwrap();
+        let context = handle.context().clone();
+
+        // The old handle is held until it's replaced, so release its claim first, otherwise
+        // claiming the interface on the new handle would fail as busy. The device has normally
+        // gone away, so this failing isn't a problem.
+        if let Err(e) = handle.release_interface(3) {
+            debug!(serial = self.serial.as_str(); "Unable to release interface: {}", e);
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.attach(&context) {
+                Ok(new_handle) => {
+                    debug!(serial = self.serial.as_str(); "Reconnected at {}", self.location);
+                    *handle = new_handle;
+                    self.reconnected.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Err(e) if attempt < RECONNECT_ATTEMPTS => {
+                    debug!(serial = self.serial.as_str(); "Reconnect attempt {} failed: {}", attempt, e);
+                    sleep(RECONNECT_DELAY);
+                }
+                Err(e) => {
+                    warn!(serial = self.serial.as_str(); "Unable to reconnect: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn take_reconnected(&self) -> bool {
+        self.reconnected.swap(false, Ordering::Relaxed)
+    }
+}
@@ -1,34 +1,238 @@
-use crate::manager::{DeviceLocation, VENDOR_BEACN};
+use crate::manager::{DeviceLocation, DeviceType, VENDOR_BEACN};
 use crate::version::VersionNumber;
-use anyhow::Result;
-use byteorder::{LittleEndian, ReadBytesExt};
-use rusb::{Device, DeviceDescriptor, DeviceHandle, GlobalContext};
-use std::io::{Cursor, Read, Seek};
+use crate::{BResult, BeacnError, UsbError, beacn_bail};
+use byteorder::{ByteOrder, LittleEndian};
+use log::{debug, warn};
+use rusb::{Device, DeviceDescriptor, DeviceHandle, GlobalContext, UsbContext};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
-pub(crate) struct DeviceDefinition {
-    pub(crate) device: Device<GlobalContext>,
+pub(crate) struct DeviceDefinition<C: UsbContext = GlobalContext> {
+    pub(crate) device: Device<C>,
     pub(crate) descriptor: DeviceDescriptor,
+
+    // Overrides the device type's default timeouts when opened
+    pub(crate) timeouts: Option<Timeouts>,
+}
+
+impl<C: UsbContext> DeviceDefinition<C> {
+    // The timeouts to use for this device, either those it was opened with, or its defaults
+    pub(crate) fn get_timeouts(&self) -> Timeouts {
+        self.timeouts.unwrap_or_else(|| {
+            match DeviceType::from_product_id(self.descriptor.product_id()) {
+                Some(device_type) => Timeouts::for_device(device_type),
+                None => Timeouts::default(),
+            }
+        })
+    }
+}
+
+// Default USB timeouts, these are the values the library has always used
+static TIMEOUT_DEFAULT_SETUP: Duration = Duration::from_millis(2000);
+static TIMEOUT_DEFAULT_LOOKUP: Duration = Duration::from_secs(3);
+static TIMEOUT_DEFAULT_SET: Duration = Duration::from_millis(200);
+static TIMEOUT_DEFAULT_LINKED_APPS: Duration = Duration::from_secs(3);
+static TIMEOUT_DEFAULT_CONTROL: Duration = Duration::from_millis(2000);
+static TIMEOUT_DEFAULT_INPUT_READ: Duration = Duration::from_millis(100);
+static TIMEOUT_DEFAULT_IMAGE_CHUNK: Duration = Duration::from_millis(100);
+
+/// The timeouts used for USB reads and writes to a device. Each device starts with the defaults
+/// for its type (see `Timeouts::for_device`), these can be replaced when the device is opened,
+/// which may be needed if the device is behind a slow hub or similar.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timeouts {
+    setup: Duration,
+    lookup: Duration,
+    set: Duration,
+    linked_apps: Duration,
+    control: Duration,
+    input_read: Duration,
+    image_chunk: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            setup: TIMEOUT_DEFAULT_SETUP,
+            lookup: TIMEOUT_DEFAULT_LOOKUP,
+            set: TIMEOUT_DEFAULT_SET,
+            linked_apps: TIMEOUT_DEFAULT_LINKED_APPS,
+            control: TIMEOUT_DEFAULT_CONTROL,
+            input_read: TIMEOUT_DEFAULT_INPUT_READ,
+            image_chunk: TIMEOUT_DEFAULT_IMAGE_CHUNK,
+        }
+    }
+}
+
+impl Timeouts {
+    /// The default timeouts for a device type. No differences between the devices (or their
+    /// firmware versions) have been measured yet, so they all currently start from the same
+    /// values, this is where any per device tuning belongs.
+    pub fn for_device(device_type: DeviceType) -> Self {
+        match device_type {
+            DeviceType::BeacnMic
+            | DeviceType::BeacnStudio
+            | DeviceType::BeacnMix
+            | DeviceType::BeacnMixCreate => Self::default(),
+        }
+    }
+
+    /// Used while opening (or re-attaching to) a device, when requesting its serial and version
+    pub fn with_setup(mut self, timeout: Duration) -> Self {
+        self.setup = timeout;
+        self
+    }
+
+    /// Used when reading a parameter from a Mic or Studio
+    pub fn with_lookup(mut self, timeout: Duration) -> Self {
+        self.lookup = timeout;
+        self
+    }
+
+    /// Used when writing a parameter to a Mic or Studio
+    pub fn with_set(mut self, timeout: Duration) -> Self {
+        self.set = timeout;
+        self
+    }
+
+    /// Used when reading or changing the PC2 linked apps on a Studio
+    pub fn with_linked_apps(mut self, timeout: Duration) -> Self {
+        self.linked_apps = timeout;
+        self
+    }
+
+    /// Used for commands sent to a Mix or Mix Create (brightness, colours, input polling, etc)
+    pub fn with_control(mut self, timeout: Duration) -> Self {
+        self.control = timeout;
+        self
+    }
+
+    /// How long each read waits for input from a Mix or Mix Create in notify mode. A timeout
+    /// here isn't an error, the read simply starts again.
+    pub fn with_input_read(mut self, timeout: Duration) -> Self {
+        self.input_read = timeout;
+        self
+    }
+
+    /// Used for each chunk of an image sent to the display of a Mix or Mix Create
+    pub fn with_image_chunk(mut self, timeout: Duration) -> Self {
+        self.image_chunk = timeout;
+        self
+    }
+
+    pub fn get_setup(&self) -> Duration {
+        self.setup
+    }
+
+    pub fn get_lookup(&self) -> Duration {
+        self.lookup
+    }
+
+    pub fn get_set(&self) -> Duration {
+        self.set
+    }
+
+    pub fn get_linked_apps(&self) -> Duration {
+        self.linked_apps
+    }
+
+    pub fn get_control(&self) -> Duration {
+        self.control
+    }
+
+    pub fn get_input_read(&self) -> Duration {
+        self.input_read
+    }
+
+    pub fn get_image_chunk(&self) -> Duration {
+        self.image_chunk
+    }
+
+    // libusb treats a zero timeout as 'wait forever', which would hang the device if it stops
+    // responding, so these are rejected when the device is opened
+    pub(crate) fn validate(&self) -> BResult<()> {
+        let timeouts = [
+            self.setup,
+            self.lookup,
+            self.set,
+            self.linked_apps,
+            self.control,
+            self.input_read,
+            self.image_chunk,
+        ];
+        if timeouts.iter().any(Duration::is_zero) {
+            beacn_bail!("Timeouts must be greater than zero");
+        }
+        Ok(())
+    }
 }
 
+// The handle type is generic so that devices which need to wrap the raw USB handle (for
+// example, Audio devices which can re-attach) can still share this structure. The context is
+// generic so the library can be used with a libusb context owned by the application.
 #[allow(dead_code)]
 #[derive(Debug)]
-pub struct BeacnDeviceHandle {
+pub struct BeacnDeviceHandle<H = DeviceHandle<GlobalContext>, C: UsbContext = GlobalContext> {
     pub(crate) descriptor: DeviceDescriptor,
-    pub(crate) device: Device<GlobalContext>,
-    pub(crate) handle: DeviceHandle<GlobalContext>,
+    pub(crate) device: Device<C>,
+    pub(crate) handle: H,
     pub(crate) version: VersionNumber,
     pub(crate) serial: String,
+    pub(crate) extra_info: Vec<u8>,
+    pub(crate) manufacturer: String,
+    pub(crate) product: String,
+    pub(crate) timeouts: Timeouts,
 }
 
-pub(crate) fn find_device(location: DeviceLocation) -> Option<DeviceDefinition> {
+// Straight after a device appears, udev may still be setting up its permissions, so opening
+// and claiming it can briefly fail. We retry a few times, doubling the delay each time, but only
+// for the errors this causes, and only within a short overall budget.
+static OPEN_ATTEMPTS: u32 = 5;
+static OPEN_INITIAL_DELAY: Duration = Duration::from_millis(50);
+static OPEN_RETRY_BUDGET: Duration = Duration::from_secs(1);
+
+/// Runs the open and setup sequence for a device, retrying with a backoff on the USB errors seen
+/// while udev settles (Access, Busy and Io). Anything else (such as a timeout, or an unexpected
+/// response) is returned straight away, as is the last error once the attempts or the time
+/// budget have been used.
+pub(crate) fn with_open_retry<T>(operation: impl Fn() -> BResult<T>) -> BResult<T> {
+    let started = Instant::now();
+    let mut delay = OPEN_INITIAL_DELAY;
+    let mut attempt = 1;
+    loop {
+        match operation() {
+            Err(BeacnError::Usb(e @ (UsbError::Access | UsbError::Busy | UsbError::Io)))
+                if attempt < OPEN_ATTEMPTS && started.elapsed() + delay <= OPEN_RETRY_BUDGET =>
+            {
+                debug!(
+                    "Open attempt {} failed: {}, retrying in {:?}",
+                    attempt, e, delay
+                );
+                sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(crate) fn find_device<C: UsbContext>(
+    context: &C,
+    location: DeviceLocation,
+) -> Option<DeviceDefinition<C>> {
     // We need to iterate through the devices and find the one at this location
-    if let Ok(devices) = rusb::devices() {
+    if let Ok(devices) = context.devices() {
         for device in devices.iter() {
             if let Ok(descriptor) = device.device_descriptor() {
                 #[allow(clippy::collapsible_if)]
                 if descriptor.vendor_id() == VENDOR_BEACN {
                     if DeviceLocation::from(device.clone()) == location {
-                        return Some(DeviceDefinition { device, descriptor });
+                        return Some(DeviceDefinition {
+                            device,
+                            descriptor,
+                            timeouts: None,
+                        });
                     }
                 }
             }
@@ -37,11 +241,40 @@ pub(crate) fn find_device(location: DeviceLocation) -> Option<DeviceDefinition>
     None
 }
 
-pub(crate) fn get_device_info(input: &[u8]) -> Result<(VersionNumber, String)> {
-    let mut cursor = Cursor::new(input);
-    cursor.seek_relative(4)?;
+/// Reads the manufacturer and product strings from the USB descriptor, if the device doesn't
+/// provide them (or they can't be read) we fall back to names based on the product id.
+pub(crate) fn read_descriptor_strings<C: UsbContext>(
+    handle: &DeviceHandle<C>,
+    descriptor: &DeviceDescriptor,
+) -> (String, String) {
+    let manufacturer = handle
+        .read_manufacturer_string_ascii(descriptor)
+        .unwrap_or_else(|_| String::from("Beacn"));
+
+    let product = handle
+        .read_product_string_ascii(descriptor)
+        .unwrap_or_else(|_| {
+            let name = match DeviceType::from_product_id(descriptor.product_id()) {
+                Some(DeviceType::BeacnMic) => "Beacn Mic",
+                Some(DeviceType::BeacnStudio) => "Beacn Studio",
+                Some(DeviceType::BeacnMix) => "Beacn Mix",
+                Some(DeviceType::BeacnMixCreate) => "Beacn Mix Create",
+                None => "Unknown Beacn Device",
+            };
+            String::from(name)
+        });
 
-    let version = cursor.read_u32::<LittleEndian>()?;
+    (manufacturer, product)
+}
+
+/// Parses the device info response, returning the firmware version and serial. The serial is
+/// None if the device doesn't report one, which has been seen on some refurbished units.
+pub(crate) fn get_device_info(input: &[u8]) -> BResult<(VersionNumber, Option<String>)> {
+    // Four bytes of header, followed by the four byte version
+    if input.len() < 8 {
+        beacn_bail!("Device info response too short ({} bytes)", input.len());
+    }
+    let version = LittleEndian::read_u32(&input[4..8]);
 
     // Break it down
     let major = version >> 0x1c;
@@ -51,21 +284,44 @@ pub(crate) fn get_device_info(input: &[u8]) -> Result<(VersionNumber, String)> {
 
     let version = VersionNumber(major, minor, patch, build);
 
-    // Now grab the Serial...
-    let mut serial_bytes = vec![];
-    for byte in cursor.bytes() {
-        let byte = byte?;
-
-        // Check for Null Termination
-        if byte == 0 {
-            break;
-        }
-        serial_bytes.push(byte);
-    }
-    let serial = String::from_utf8_lossy(&serial_bytes)
+    // Now grab the Serial, which is null terminated (or runs to the end of the response)
+    let serial_bytes = &input[8..];
+    let end = serial_bytes
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(serial_bytes.len());
+    let serial: String = String::from_utf8_lossy(&serial_bytes[..end])
         .chars()
         .filter(|c| c.is_alphanumeric())
         .collect();
 
+    let serial = if serial.is_empty() {
+        None
+    } else {
+        Some(serial)
+    };
     Ok((version, serial))
 }
+
+/// Returns whatever follows the serial in the device info response. The layout of this hasn't
+/// been worked out (it may contain a hardware revision or similar), so it's kept as raw bytes.
+pub(crate) fn get_extra_device_info(input: &[u8]) -> Vec<u8> {
+    if input.len() < 8 {
+        return vec![];
+    }
+    let serial_bytes = &input[8..];
+    match serial_bytes.iter().position(|b| *b == 0) {
+        Some(end) => serial_bytes[end + 1..].to_vec(),
+        None => vec![],
+    }
+}
+
+/// Used in place of the serial when a device doesn't report one, it's based on where the device
+/// is attached, so will change if the device is moved to a different port.
+pub(crate) fn fallback_serial(location: DeviceLocation) -> String {
+    warn!(
+        "Device at {} has no serial, using its location instead",
+        location
+    );
+    format!("unknown-{}", location)
+}
@@ -1,44 +1,85 @@
-use crate::common::{BeacnDeviceHandle, DeviceDefinition, get_device_info};
+use crate::common::{
+    BeacnDeviceHandle, DeviceDefinition, fallback_serial, get_device_info, get_extra_device_info,
+    read_descriptor_strings, with_open_retry,
+};
 use crate::controller::ButtonState::{Press, Release};
 use crate::controller::ControlThreadSender::{
-    KeepAlive, SetActiveBrightness, SetButtonBrightness, SetButtonColour, SetDimTimeout,
-    SetEnabled, SetImage,
+    KeepAlive, SetActiveBrightness, SetAllButtonColours, SetButtonAnimation, SetButtonBrightness,
+    SetButtonColour, SetDialPosition, SetDialRange, SetDimTimeout, SetEnabled, SetImage,
+    SetKeepAliveInterval,
 };
+use crate::controller::config::check_dim_timeout;
+#[cfg(feature = "text")]
+use crate::controller::text::render_text;
 use crate::controller::{
-    BeacnControlDevice, ButtonLighting, Buttons, ControlThreadSender, Dials, Interactions,
+    Animation, BeacnControlDevice, ButtonLighting, ButtonState, Buttons, ControlConfig,
+    ControlThreadError, ControlThreadSender, Dials, InteractionMode, Interactions,
 };
+use crate::manager::DeviceLocation;
 use crate::types::RGBA;
 use crate::version::VersionNumber;
-use crate::{BResult, beacn_bail};
+use crate::{BResult, BeacnError, beacn_bail, out_of_range};
 use anyhow::Error;
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
-use crossbeam::channel::{Receiver, Sender, after, bounded, never, tick};
+use crossbeam::channel::{Receiver, Sender, after, bounded, never, tick, unbounded};
 use crossbeam::select;
-use jpeg_decoder::Decoder;
+use enum_map::EnumMap;
+use jpeg_decoder::{CodingProcess, Decoder, ImageInfo, PixelFormat};
+use jpeg_encoder::{ColorType, Encoder};
 use log::{debug, error, warn};
-use std::sync::Arc;
+use rusb::{DeviceHandle, UsbContext};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
 
-// Default Display 'Active' and 'Dimmed' brightness, and the default dim time
-static DISPLAY_DEFAULT_FULL_BRIGHTNESS: u8 = 40;
-static DISPLAY_DEFAULT_DIM_BRIGHTNESS: u8 = 1;
-static DISPLAY_DEFAULT_DIM_TIME: u64 = 180;
+// How often a keep-alive is sent to stop the device going to sleep. The firmware's sleep timeout
+// isn't documented, but the wake message is tiny, so we can afford to send it fairly often.
+static KEEPALIVE_DEFAULT_INTERVAL: u64 = 10;
+
+// How often running button animations are updated
+static ANIMATION_FRAME_TIME: u64 = 50;
+
+// Quality used when encoding raw pixel data for the display
+static JPEG_QUALITY: u8 = 90;
+
+// Images are sent in 1024 byte packets, a 3 byte chunk index and a marker followed by up to 1020
+// bytes of JPEG data. The final packet carries the image length as a u32, which limits the size,
+// and also keeps the chunk index below 0xffffff (which marks the final packet).
+static IMAGE_CHUNK_SIZE: usize = 1020;
+static IMAGE_MAX_SIZE: usize = u32::MAX as usize;
 
-// Default button brightness
-static BUTTONS_DEFAULT_BRIGHTNESS: u8 = 8;
+// The last image sent to the display, and its position, shared with the event thread
+pub(crate) type LastImage = Arc<Mutex<Option<(u32, u32, Vec<u8>)>>>;
+
+// In 1.2.0 build 81+ the Beacn Mix and Mix Create shifted to a 'polling' method of
+// interaction checks. For versions older we need to use the original notify
+pub(crate) fn interaction_mode(version: VersionNumber, config: &ControlConfig) -> InteractionMode {
+    if let Some(mode) = config.get_interaction_mode() {
+        return mode;
+    }
+
+    let notify_version = VersionNumber(1, 2, 0, 80);
+    if version <= notify_version {
+        InteractionMode::Notify
+    } else {
+        InteractionMode::Poll
+    }
+}
 
 pub trait BeacnControlDeviceAttach {
     // We're specifically allowing the DeviceDefinition to be a private interface, as it's
     // simply used internally for connection up a device, and shouldn't have any visibility
     // from the outside. This also prevents external code from attempting to call connect.
     #[allow(private_interfaces)]
-    fn connect(
-        definition: DeviceDefinition,
+    fn connect<C: UsbContext + 'static>(
+        definition: DeviceDefinition<C>,
         interaction: Option<Sender<Interactions>>,
         health_tx: Sender<()>,
+        config: ControlConfig,
     ) -> BResult<Box<dyn BeacnControlDevice>>
     where
         Self: Sized;
@@ -47,27 +88,46 @@ pub trait BeacnControlDeviceAttach {
     fn get_serial(&self) -> String;
     fn get_version(&self) -> String;
 
+    /// The raw bytes following the serial in the device's info response. What these contain
+    /// hasn't been worked out yet (possibly a hardware revision), so they're exposed as they
+    /// are for inspection, and may be empty.
+    fn get_extra_device_info(&self) -> Vec<u8>;
+    fn get_manufacturer_string(&self) -> String;
+    fn get_product_string(&self) -> String;
+
     #[allow(private_interfaces)]
     fn get_sender(&self) -> &Sender<ControlThreadSender>;
     fn get_display_size(&self) -> (u32, u32);
 }
 
+// Access to the state shared with the event thread, this is internal to the library, consumers
+// should use get_button_states and last_displayed_image instead
+pub(crate) trait BeacnControlDeviceLocal {
+    fn get_button_state(&self) -> &Arc<Mutex<u16>>;
+    fn get_last_image(&self) -> &LastImage;
+}
+
 // For the most part, the Mix and Mix Create handle interactions identically, obviously the
 // mix has fewer buttons, but the firmware seems to do a decent job of handling that, so we
 // can simply use the same behaviour between the
-pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
+#[allow(private_bounds)]
+pub trait BeacnControlInteraction: BeacnControlDeviceAttach + BeacnControlDeviceLocal {
     #[allow(private_interfaces)]
-    fn spawn_event_handler(
+    fn spawn_event_handler<C: UsbContext + 'static>(
         rx: Receiver<ControlThreadSender>,
-        handler: BeacnDeviceHandle,
+        handler: BeacnDeviceHandle<DeviceHandle<C>, C>,
         interaction: Option<Sender<Interactions>>,
-    ) where
+        button_state: Arc<Mutex<u16>>,
+        last_image: LastImage,
+        config: ControlConfig,
+    ) -> Result<(), ControlThreadError>
+    where
         Self: Sized,
     {
-        // In 1.2.0 build 81+ the Beacn Mix and Mix Create shifted to a 'polling' method
-        // of interaction checks. For versions older we need to use the original notify
-        let notify_version = VersionNumber(1, 2, 0, 80);
-        let is_notify = handler.version <= notify_version;
+        let is_notify = interaction_mode(handler.version, &config) == InteractionMode::Notify;
+
+        // Attached to log records from this handler, so multiple devices can be told apart
+        let serial = handler.serial.clone();
 
         // We need a message queue for handling when inputs have been received for parsing, given
         // they can come from one of two places, we'll handle them once. 64 might be a little big.
@@ -75,22 +135,29 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         let mut input_buffer = [0; 64];
 
         // Timeout Handlers
-        let timeout = Duration::from_millis(2000);
+        let timeouts = handler.timeouts;
+        let timeout = timeouts.get_control();
 
         // At this point, we need to pull out the USB handler and wrap it up
         let handle = Arc::new(handler.handle);
+
+        // If the listener thread (used in notify mode) dies, it'll report why here
+        let (listener_tx, listener_rx) = bounded(1);
+        let listener_error = if is_notify { listener_rx } else { never() };
+
         let poll = if is_notify {
             let handler_clone = handle.clone();
             let tx_clone = input_tx.clone();
+            let serial = serial.clone();
             thread::spawn(move || {
-                debug!("Spawning Event Listener");
+                debug!(serial = serial.as_str(); "Spawning Event Listener");
 
                 // Input buffer for messages
                 let mut input = [0; 64];
 
                 let handle = handler_clone;
                 let input_tx = tx_clone;
-                let read = Duration::from_millis(100);
+                let read = timeouts.get_input_read();
 
                 // These are just defensive checks
                 const MAX_NO_DEVICE_RETRIES: u32 = 10;
@@ -103,7 +170,7 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                             no_device_retries = 0;
                             if let Err(e) = input_tx.send(input) {
                                 // Our channel is gone or closed, bail.
-                                warn!("Message Channel Closed, Terminating: {}", e);
+                                warn!(serial = serial.as_str(); "Message Channel Closed, Terminating: {}", e);
                                 break;
                             }
                         }
@@ -111,13 +178,14 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                             no_device_retries += 1;
                             if no_device_retries > MAX_NO_DEVICE_RETRIES {
                                 warn!(
+                                    serial = serial.as_str();
                                     "Device not recovering after {} retries, assuming dead",
                                     MAX_NO_DEVICE_RETRIES
                                 );
 
-                                // TODO: We need to actually fully teardown the device
-                                // If we get here, then the handle is gone, and that's not been detected
-                                // upstream anywhere, which should cause a teardown / reconnect
+                                let error = rusb::Error::NoDevice;
+                                let _ = listener_tx
+                                    .send(ControlThreadError::Usb("receiving inputs", error));
                                 break;
                             }
 
@@ -132,13 +200,15 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                             no_device_retries = 0;
                         }
                         Err(usb_error) => {
-                            warn!("USB Error while receiving inputs: {}", usb_error);
+                            warn!(serial = serial.as_str(); "USB Error while receiving inputs: {}", usb_error);
+                            let _ = listener_tx
+                                .send(ControlThreadError::Usb("receiving inputs", usb_error));
                             break;
                         }
                     }
                 }
 
-                debug!("Event Listener Terminated");
+                debug!(serial = serial.as_str(); "Event Listener Terminated");
             });
             never()
         } else {
@@ -148,9 +218,17 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         // This tracks the button states (so we can message on Send / Receive)
         let mut last_button_state = 0;
 
+        // Tracks the absolute position of each dial, based on the deltas we receive
+        let mut dial_positions: EnumMap<Dials, DialTracker> = EnumMap::default();
+        for tracker in dial_positions.values_mut() {
+            tracker.threshold = config.get_dial_threshold();
+            tracker.report_position = config.get_dial_positions();
+        }
+
         let mut is_dimmed = false;
-        let mut active_brightness = DISPLAY_DEFAULT_FULL_BRIGHTNESS;
-        let mut button_brightness = BUTTONS_DEFAULT_BRIGHTNESS;
+        let mut active_brightness = config.get_active_brightness();
+        let dim_brightness = config.get_dim_brightness();
+        let mut button_brightness = config.get_button_brightness();
 
         let enable = [0, 1, 0, 4, 0, 0, 0, 0];
         let brightness = [0, 0, 0, 4, active_brightness, 0, 0, 0];
@@ -158,81 +236,137 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
 
         // Message to instruct the screen to turn on (default to off after a few seconds)
         if let Err(e) = handle.write_interrupt(0x03, &enable, timeout) {
-            error!("Unable to Turn the Screen on: {}", e);
-            return;
+            error!(serial = serial.as_str(); "Unable to Turn the Screen on: {}", e);
+            return Err(ControlThreadError::Usb("turning the screen on", e));
         }
 
         // Set the default display brightness
         if let Err(e) = handle.write_interrupt(0x03, &brightness, timeout) {
-            error!("Failed to Set Default Brightness: {}", e);
-            return;
+            error!(serial = serial.as_str(); "Failed to Set Default Brightness: {}", e);
+            return Err(ControlThreadError::Usb("setting default brightness", e));
         }
 
         // Set the default button brightness
         if let Err(e) = handle.write_interrupt(0x03, &buttons, timeout) {
-            error!("Unable to Set Default Button Brightness: {}", e);
-            return;
+            error!(serial = serial.as_str(); "Unable to Set Default Button Brightness: {}", e);
+            return Err(ControlThreadError::Usb(
+                "setting default button brightness",
+                e,
+            ));
         }
 
         // Force the device into a 'wake' state if it's currently sleeping
         let wake = [00, 00, 00, 0xf1];
         if let Err(e) = handle.write_interrupt(0x03, &wake, timeout) {
-            error!("Unable to Wake Device: {}", e);
-            return;
+            error!(serial = serial.as_str(); "Unable to Wake Device: {}", e);
+            return Err(ControlThreadError::Usb("waking device", e));
         }
 
         sleep(Duration::from_millis(250));
 
-        let mut dim_duration = Duration::from_secs(DISPLAY_DEFAULT_DIM_TIME);
+        let mut dim_duration = config.get_dim_timeout();
 
         // Create some timers for processing
-        let mut dim_timeout = after(dim_duration);
+        let mut dim_timeout = dim_timer(dim_duration);
+        let mut keepalive = tick(Duration::from_secs(KEEPALIVE_DEFAULT_INTERVAL));
+
+        // Running button animations, along with when they started and the last colour sent
+        let mut animations: EnumMap<ButtonLighting, Option<(Animation, Instant, Option<RGBA>)>> =
+            EnumMap::default();
+        let mut animation_tick = never();
         let mut device_enabled = true;
 
+        // If the handler stops because of an error, this holds why so it can be reported
+        let mut failure = None;
+
+        // Receives every input frame before it's decoded, see BeacnControlRawAccess
+        #[cfg(feature = "raw-access")]
+        let mut raw_listener: Option<Sender<[u8; 64]>> = None;
+
+        // Messages read ahead while sending an image, these are handled (in order) before
+        // anything else is read from rx
+        let (deferred_tx, deferred_rx) = unbounded();
+
         // TODO: I should probably use a Macro or a closure to handle the recv
         // In all cases, if a channel has closed, we should abort.
-        debug!("Spawning Event Handler for {}", handler.serial);
+        debug!(serial = serial.as_str(); "Spawning Event Handler");
         'primary: loop {
+            let source = if deferred_rx.is_empty() {
+                &rx
+            } else {
+                &deferred_rx
+            };
             select! {
-                recv(rx) -> msg => {
+                recv(source) -> msg => {
                     match msg {
                         Ok(msg) => {
                             match msg {
                                 ControlThreadSender::Stop => {
-                                    debug!("Stopping Event Handler");
+                                    debug!(serial = serial.as_str(); "Stopping Event Handler");
                                     break;
                                 }
                                 KeepAlive => {
                                     if let Err(e) = handle.write_interrupt(0x03, &[00, 00, 00, 0xf1], timeout) {
-                                        error!("Error Sending Keep-Alive Request: {}", e);
+                                        error!(serial = serial.as_str(); "Error Sending Keep-Alive Request: {}", e);
+                                        failure = Some(ControlThreadError::Usb("sending keep-alive", e));
                                         break;
                                     }
                                 }
+                                #[cfg(feature = "raw-access")]
+                                ControlThreadSender::SetRawListener(listener) => {
+                                    raw_listener = listener;
+                                }
+                                SetKeepAliveInterval(interval) => {
+                                    keepalive = match interval {
+                                        Some(interval) => tick(interval),
+                                        None => never(),
+                                    };
+                                }
                                 SetEnabled(enabled) => {
                                     let byte = if enabled { 0 } else { 1 };
                                     let message = [0, 1, 0, 4, byte, 0, 0, 0];
 
                                     if let Err(e) = handle.write_interrupt(0x03, &message, timeout) {
-                                        error!("Failed to Send Enabled Message: {}", e);
+                                        error!(serial = serial.as_str(); "Failed to Send Enabled Message: {}", e);
+                                        failure = Some(ControlThreadError::Usb("setting the display state", e));
                                         break 'primary;
                                     }
 
                                     device_enabled = enabled;
                                 }
-                                SetImage(x, y, img) => {
-                                    let chunk_timeout = Duration::from_millis(100);
+                                SetImage(x, y, mut img) => {
+                                    // If newer frames for this position are already waiting, skip
+                                    // straight to the latest one
+                                    match read_ahead(&rx, &deferred_tx, &deferred_rx, x, y) {
+                                        ReadAhead::Stop => {
+                                            debug!(serial = serial.as_str(); "Stopping Event Handler");
+                                            break 'primary;
+                                        }
+                                        ReadAhead::Newer(newer) => {
+                                            debug!(serial = serial.as_str(); "Dropping stale image at {}x{}", x, y);
+                                            img = newer;
+                                        }
+                                        ReadAhead::Nothing => {}
+                                    }
+
+                                    if let Err(e) = check_image_size(&img) {
+                                        error!(serial = serial.as_str(); "Image at {}x{} rejected, dropping frame: {}", x, y, e);
+                                        continue 'primary;
+                                    }
+
+                                    let chunk_timeout = timeouts.get_image_chunk();
                                     let chunk_retry_budget = Duration::from_millis(300);
                                     let overall_budget = Duration::from_secs(10);
 
                                     if !device_enabled {
                                         if let Err(e) = handle.write_interrupt(0x03, &enable, chunk_timeout) {
-                                            warn!("Failed to enable device, attempting to clear halt: {e}");
+                                            warn!(serial = serial.as_str(); "Failed to enable device, attempting to clear halt: {e}");
 
                                             let retry = handle.clear_halt(0x83).is_ok()
                                                 && handle.write_interrupt(0x03, &enable, chunk_timeout).is_ok();
 
                                             if !retry {
-                                                warn!("Failed to enable device, dropping frame");
+                                                warn!(serial = serial.as_str(); "Failed to enable device, dropping frame");
                                                 continue 'primary;
                                             }
                                         }
@@ -249,7 +383,7 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                                 Ok(_) => return Ok(()),
                                                 Err(rusb::Error::Timeout) if started.elapsed() < chunk_retry_budget => {
                                                     retry_count += 1;
-                                                    debug!("Chunk write timed out ({:?} waiting, retry {}), retrying", started.elapsed(), retry_count);
+                                                    debug!(serial = serial.as_str(); "Chunk write timed out ({:?} waiting, retry {}), retrying", started.elapsed(), retry_count);
                                                     sleep(Duration::from_millis(20));
                                                 }
 
@@ -259,17 +393,34 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                     };
 
                                     'image: {
-                                        let overall_started = Instant::now();
+                                        let mut overall_started = Instant::now();
                                         let mut success = false;
                                         let mut attempt = 0;
 
                                         while overall_started.elapsed() < overall_budget {
                                             attempt += 1;
-                                            let mut iter = img.chunks(1020).enumerate().peekable();
+                                            let mut iter = img.chunks(IMAGE_CHUNK_SIZE).enumerate().peekable();
                                             let mut output = [0; 1024];
                                             let mut attempt_ok = true;
+                                            let mut replacement = None;
 
                                             while let Some((index, value)) = iter.next() {
+                                                // Between chunks, check whether we've been asked
+                                                // to stop, or a newer frame has replaced this one
+                                                match read_ahead(&rx, &deferred_tx, &deferred_rx, x, y) {
+                                                    ReadAhead::Stop => {
+                                                        debug!(serial = serial.as_str(); "Stopping Event Handler mid-transfer");
+                                                        break 'primary;
+                                                    }
+                                                    ReadAhead::Newer(newer) => {
+                                                        debug!(serial = serial.as_str(); "Image at {}x{} replaced after {} chunks", x, y, index);
+                                                        replacement = Some(newer);
+                                                        attempt_ok = false;
+                                                        break;
+                                                    }
+                                                    ReadAhead::Nothing => {}
+                                                }
+
                                                 LittleEndian::write_u24(&mut output[0..3], index as u32);
                                                 output[3] = 0x50;
                                                 output[4..value.len() + 4].copy_from_slice(value);
@@ -277,12 +428,12 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                                 match send_chunk(&output) {
                                                     Ok(_) => {}
                                                     Err(rusb::Error::Timeout) => {
-                                                        warn!("Chunk {} failed on attempt {} ({:?} elapsed), restarting transfer from chunk 0", index, attempt, overall_started.elapsed());
+                                                        warn!(serial = serial.as_str(); "Chunk {} failed on attempt {} ({:?} elapsed), restarting transfer from chunk 0", index, attempt, overall_started.elapsed());
                                                         attempt_ok = false;
                                                         break;
                                                     }
                                                     Err(e) => {
-                                                        warn!("Unknown Error Received: {:?}, bailing..", e);
+                                                        warn!(serial = serial.as_str(); "Unknown Error Received: {:?}, bailing..", e);
                                                         continue 'primary;
                                                     }
                                                 }
@@ -299,12 +450,12 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                                     match send_chunk(&output) {
                                                         Ok(_) => {}
                                                         Err(rusb::Error::Timeout) => {
-                                                            warn!("Final chunk failed on attempt {} ({:?} elapsed), restarting transfer from chunk 0", attempt, overall_started.elapsed());
+                                                            warn!(serial = serial.as_str(); "Final chunk failed on attempt {} ({:?} elapsed), restarting transfer from chunk 0", attempt, overall_started.elapsed());
                                                             attempt_ok = false;
                                                             break;
                                                         }
                                                         Err(e) => {
-                                                            warn!("Unknown Error Received: {:?}, bailing..", e);
+                                                            warn!(serial = serial.as_str(); "Unknown Error Received: {:?}, bailing..", e);
                                                             continue 'primary;
                                                         }
                                                     }
@@ -312,6 +463,20 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                                 }
                                             }
 
+                                            if let Some(newer) = replacement {
+                                                if let Err(e) = check_image_size(&newer) {
+                                                    error!(serial = serial.as_str(); "Replacement image at {}x{} rejected, dropping frame: {}", x, y, e);
+                                                    continue 'primary;
+                                                }
+
+                                                // Start again with the new frame, from chunk 0 as
+                                                // we would after a timeout
+                                                img = newer;
+                                                overall_started = Instant::now();
+                                                attempt = 0;
+                                                continue;
+                                            }
+
                                             if attempt_ok {
                                                 success = true;
                                                 break;
@@ -319,49 +484,104 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                                         }
 
                                         if !success {
-                                            error!("Failed to send image after {} attempts over {:?}, dropping frame", attempt, overall_started.elapsed());
+                                            error!(serial = serial.as_str(); "Failed to send image after {} attempts over {:?}, dropping frame", attempt, overall_started.elapsed());
                                             break 'image;
                                         }
 
+                                        if let Ok(mut last_image) = last_image.lock() {
+                                            *last_image = Some((x, y, img));
+                                        }
+
                                         sleep(Duration::from_millis(10));
                                     }
                                 }
-                                SetDimTimeout(timeout) => {
-                                    dim_duration = timeout;
+                                SetDimTimeout(duration) => {
+                                    dim_duration = duration;
+                                    if is_dimmed && duration.is_none() {
+                                        // Dimming has been disabled, so we need to wake the screen
+                                        is_dimmed = false;
+                                        if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 4, active_brightness, 0, 0, 0], timeout) {
+                                            error!(serial = serial.as_str(); "Failed to Set Brightness: {}", e);
+                                            failure = Some(ControlThreadError::Usb("setting brightness", e));
+                                            break;
+                                        }
+                                        send_interaction(&interaction, Interactions::DisplayWoke);
+                                    }
                                     if !is_dimmed {
                                         // If we're not already dimmed, reset the timer
-                                        dim_timeout = after(timeout);
+                                        dim_timeout = dim_timer(dim_duration);
                                     }
                                 }
                                 SetActiveBrightness(percent) => {
+                                    let was_dimmed = is_dimmed;
                                     if is_dimmed {
                                         is_dimmed = false;
-                                        dim_timeout = after(dim_duration);
+                                        dim_timeout = dim_timer(dim_duration);
                                     }
                                     active_brightness = percent;
                                     if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 4, active_brightness, 0, 0, 0], timeout) {
-                                        error!("Failed to Set Brightness: {}", e);
+                                        error!(serial = serial.as_str(); "Failed to Set Brightness: {}", e);
+                                        failure = Some(ControlThreadError::Usb("setting brightness", e));
                                         break;
                                     }
+                                    if was_dimmed {
+                                        send_interaction(&interaction, Interactions::DisplayWoke);
+                                    }
                                 }
                                 SetButtonBrightness(value) => {
                                     button_brightness = value;
                                     if let Err(e) = handle.write_interrupt(0x03, &[1, 7, 0, 4, button_brightness, 0, 0, 0], timeout) {
-                                        error!("Failed to Set Button Brightness: {}", e);
+                                        error!(serial = serial.as_str(); "Failed to Set Button Brightness: {}", e);
+                                        failure = Some(ControlThreadError::Usb("setting button brightness", e));
                                         break;
                                     }
                                 }
+                                SetDialRange(dial, range) => {
+                                    dial_positions[dial].set_range(range);
+                                }
+                                SetDialPosition(dial, position) => {
+                                    dial_positions[dial].set_position(position);
+                                }
+                                SetButtonAnimation(button, animation) => {
+                                    animations[button] = Some((animation, Instant::now(), None));
+                                    animation_tick = tick(Duration::from_millis(ANIMATION_FRAME_TIME));
+                                }
                                 SetButtonColour(button, colour) => {
+                                    // Setting a colour directly cancels any running animation
+                                    for lighting in ButtonLighting::iter() {
+                                        if lighting as u8 == button {
+                                            animations[lighting] = None;
+                                        }
+                                    }
+                                    if animations.values().all(Option::is_none) {
+                                        animation_tick = never();
+                                    }
+
                                     let message = [1, button, 0, 4, colour.blue, colour.green, colour.red, colour.alpha];
                                     if let Err(e) = handle.write_interrupt(0x03,&message,timeout) {
-                                        error!("Failed to Set Button Colour: {}", e);
+                                        error!(serial = serial.as_str(); "Failed to Set Button Colour: {}", e);
+                                        failure = Some(ControlThreadError::Usb("setting button colour", e));
                                         break;
                                     }
                                 }
+                                SetAllButtonColours(colours) => {
+                                    // As with a single colour, this cancels all running animations
+                                    animations.clear();
+                                    animation_tick = never();
+
+                                    for (button, colour) in colours {
+                                        let message = [1, button as u8, 0, 4, colour.blue, colour.green, colour.red, colour.alpha];
+                                        if let Err(e) = handle.write_interrupt(0x03, &message, timeout) {
+                                            error!(serial = serial.as_str(); "Failed to Set All Button Colours: {}", e);
+                                            failure = Some(ControlThreadError::Usb("setting all button colours", e));
+                                            break 'primary;
+                                        }
+                                    }
+                                }
                             }
                         }
                         Err(e) => {
-                            error!("Main Event Receiver Error: {}", e);
+                            error!(serial = serial.as_str(); "Main Event Receiver Error: {}", e);
                             break;
                         }
                     }
@@ -370,13 +590,61 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                     match msg {
                         Ok(_) => {
                             is_dimmed = true;
-                            if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 4, DISPLAY_DEFAULT_DIM_BRIGHTNESS, 0, 0, 0], timeout) {
-                                error!("Failed to Set DIM brightness: {}", e);
+                            if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 4, dim_brightness, 0, 0, 0], timeout) {
+                                error!(serial = serial.as_str(); "Failed to Set DIM brightness: {}", e);
+                                failure = Some(ControlThreadError::Usb("setting dim brightness", e));
                                 break;
                             }
+                            send_interaction(&interaction, Interactions::DisplayDimmed);
                         }
                         Err(e) => {
-                            error!("DIM Timeout Receiver broken {}", e);
+                            error!(serial = serial.as_str(); "DIM Timeout Receiver broken {}", e);
+                            failure = Some(ControlThreadError::ChannelClosed("Dim Timeout"));
+                            break;
+                        }
+                    }
+                }
+                recv(animation_tick) -> msg => {
+                    match msg {
+                        Ok(_) => {
+                            for (button, animation) in animations.iter_mut() {
+                                let Some((animation, started, last)) = animation else {
+                                    continue;
+                                };
+
+                                let colour = animation.colour_at(started.elapsed());
+                                if *last == Some(colour) {
+                                    continue;
+                                }
+                                *last = Some(colour);
+
+                                let message = [1, button as u8, 0, 4, colour.blue, colour.green, colour.red, colour.alpha];
+                                if let Err(e) = handle.write_interrupt(0x03, &message, timeout) {
+                                    error!(serial = serial.as_str(); "Failed to Set Button Animation Colour: {}", e);
+                                    failure = Some(ControlThreadError::Usb("setting button animation colour", e));
+                                    break 'primary;
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!(serial = serial.as_str(); "Animation Receiver broken {}", e);
+                            failure = Some(ControlThreadError::ChannelClosed("Animation"));
+                            break;
+                        }
+                    }
+                }
+                recv(keepalive) -> msg => {
+                    match msg {
+                        Ok(_) => {
+                            if let Err(e) = handle.write_interrupt(0x03, &wake, timeout) {
+                                error!(serial = serial.as_str(); "Error Sending Keep-Alive Request: {}", e);
+                                failure = Some(ControlThreadError::Usb("sending keep-alive", e));
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            error!(serial = serial.as_str(); "Keep-Alive Receiver broken {}", e);
+                            failure = Some(ControlThreadError::ChannelClosed("Keep-Alive"));
                             break;
                         }
                     }
@@ -384,49 +652,76 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                 recv(input_rx) -> msg => {
                     match msg {
                         Ok(input) => {
-                            let (changed, button_state) = Self::handle_interaction(input, last_button_state, &interaction);
-                            last_button_state = button_state;
+                            #[cfg(feature = "raw-access")]
+                            #[allow(clippy::collapsible_if)]
+                            if let Some(listener) = &raw_listener {
+                                if listener.send(input).is_err() {
+                                    debug!(serial = serial.as_str(); "Raw Listener Closed, removing");
+                                    raw_listener = None;
+                                }
+                            }
+
+                            let (changed, current_buttons) = Self::handle_interaction(input, last_button_state, &mut dial_positions, &interaction, &serial);
+                            last_button_state = current_buttons;
+                            if let Ok(mut state) = button_state.lock() {
+                                *state = current_buttons;
+                            }
 
                             if changed {
                                 if is_dimmed {
                                     // We need to wake up screen
                                     is_dimmed = false;
                                     if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 4, active_brightness, 0, 0, 0], timeout) {
-                                        error!("Failed to Set DIM brightness: {}", e);
+                                        error!(serial = serial.as_str(); "Failed to Set DIM brightness: {}", e);
+                                        failure = Some(ControlThreadError::Usb("setting brightness", e));
                                         break;
                                     }
+                                    send_interaction(&interaction, Interactions::DisplayWoke);
                                 }
 
                                 // Set a new Dim timeout
-                                dim_timeout = after(dim_duration);
+                                dim_timeout = dim_timer(dim_duration);
                             }
                         },
                         Err(e) => {
-                            error!("Input Receiver Terminated: {:?}", e);
+                            error!(serial = serial.as_str(); "Input Receiver Terminated: {:?}", e);
+                            failure = Some(ControlThreadError::ChannelClosed("Input"));
                             break;
                         }
                     }
                 }
+                recv(listener_error) -> msg => {
+                    // The listener thread has stopped, so we won't receive any more inputs
+                    match msg {
+                        Ok(e) => failure = Some(e),
+                        Err(_) => failure = Some(ControlThreadError::ChannelClosed("Listener")),
+                    }
+                    break;
+                }
                 recv(poll) -> msg => {
                     // Ok, we're at a poll interval, we need to fetch changes to inputs
                     match msg {
                         Ok(_) => {
                             if let Err(e) = handle.write_interrupt(0x03, &[0, 0, 0, 5], timeout) {
-                                debug!("Error Sending Poll Request: {}", e);
+                                debug!(serial = serial.as_str(); "Error Sending Poll Request: {}", e);
+                                failure = Some(ControlThreadError::Usb("sending poll request", e));
                                 break;
                             }
                             if let Err(e) = handle.read_interrupt(0x83, &mut input_buffer, timeout) {
-                                debug!("Error Reading Poll Response: {}", e);
+                                debug!(serial = serial.as_str(); "Error Reading Poll Response: {}", e);
+                                failure = Some(ControlThreadError::Usb("reading poll response", e));
                                 break;
                             } else {
                                 if let Err(e) = input_tx.send(input_buffer) {
-                                    debug!("Failed to Send Poll Response Data: {}", e);
+                                    debug!(serial = serial.as_str(); "Failed to Send Poll Response Data: {}", e);
+                                    failure = Some(ControlThreadError::ChannelClosed("Input"));
                                     break;
                                 };
                             }
                         }
                         Err(e) => {
-                            error!("Poll Receiver Terminated: {:?}", e);
+                            error!(serial = serial.as_str(); "Poll Receiver Terminated: {:?}", e);
+                            failure = Some(ControlThreadError::ChannelClosed("Poll"));
                             break;
                         }
                     }
@@ -434,13 +729,20 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
             }
         }
 
-        debug!("Event Handler Terminated");
+        debug!(serial = serial.as_str(); "Event Handler Terminated");
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
     }
 
+    #[allow(private_interfaces)]
     fn handle_interaction(
         message: [u8; 64],
         last: u16,
+        dial_positions: &mut EnumMap<Dials, DialTracker>,
         tx: &Option<Sender<Interactions>>,
+        serial: &str,
     ) -> (bool, u16)
     where
         Self: Sized,
@@ -450,12 +752,22 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         let dials = &message[4..8];
         for dial in Dials::iter() {
             if dials[dial as usize] != 0 {
-                let change = dials[dial as usize] as i8;
+                has_interacted = true;
+
+                // Movement below the threshold is held until enough has accumulated
+                let raw = dials[dial as usize] as i8;
+                let Some(change) = dial_positions[dial].accumulate(raw) else {
+                    continue;
+                };
+
+                let position = dial_positions[dial].apply(change);
                 if let Some(tx) = tx {
                     let _ = tx.send(Interactions::DialChanged(dial, change));
+                    if dial_positions[dial].report_position {
+                        let _ = tx.send(Interactions::DialPosition(dial, position));
+                    }
                 }
-                debug!("Dial Moved: {} - {}", dial, change);
-                has_interacted = true;
+                debug!(serial = serial; "Dial Moved: {} - {} ({})", dial, change, position);
             }
         }
 
@@ -467,13 +779,13 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
                     if let Some(tx) = tx {
                         let _ = tx.send(Interactions::ButtonPress(button, Press));
                     }
-                    debug!("Button Pressed: {}", button);
+                    debug!(serial = serial; "Button Pressed: {}", button);
                     has_interacted = true;
                 } else {
                     if let Some(tx) = tx {
                         let _ = tx.send(Interactions::ButtonPress(button, Release));
                     }
-                    debug!("Button Released: {}", button);
+                    debug!(serial = serial; "Button Released: {}", button);
                     has_interacted = true;
                 }
             }
@@ -481,6 +793,33 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         (has_interacted, buttons)
     }
 
+    /// Returns the state of all buttons as of the last input received from the device, this
+    /// doesn't trigger a new poll, the event thread keeps the state up to date.
+    fn get_button_states(&self) -> BResult<HashMap<Buttons, ButtonState>> {
+        let Ok(state) = self.get_button_state().lock() else {
+            beacn_bail!("Button State Lock Poisoned");
+        };
+
+        let mut states = HashMap::new();
+        for button in Buttons::iter() {
+            let button_state = if (*state >> button as u8) & 1 == 1 {
+                Press
+            } else {
+                Release
+            };
+            states.insert(button, button_state);
+        }
+        Ok(states)
+    }
+
+    /// Returns the last image successfully sent to the display, along with the x / y position
+    /// it was drawn at, or None if nothing has been drawn since the device was opened. The
+    /// device doesn't support reading back what's on screen, so this is tracked as images are
+    /// sent. As regions can be updated independently, this may only cover part of the display.
+    fn last_displayed_image(&self) -> Option<(u32, u32, Vec<u8>)> {
+        self.get_last_image().lock().ok()?.clone()
+    }
+
     fn set_enabled(&self, enabled: bool) -> BResult<()> {
         self.get_sender()
             .send(SetEnabled(enabled))
@@ -493,6 +832,19 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         Ok(())
     }
 
+    /// Sets how often the event thread sends a keep-alive to the device, `None` disables them
+    /// entirely, in which case `send_keepalive` will need to be called to keep the device awake.
+    fn set_keepalive_interval(&self, interval: Option<Duration>) -> BResult<()> {
+        if interval.is_some_and(|interval| interval.is_zero()) {
+            beacn_bail!("Keep-Alive interval must be greater than zero");
+        }
+
+        self.get_sender()
+            .send(SetKeepAliveInterval(interval))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
     fn set_image(&self, x: u32, y: u32, jpeg_image: &[u8]) -> BResult<()> {
         // TODO: This might be too heavy for a frequent update check (for example, metering)
 
@@ -507,27 +859,24 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
             );
         }
 
+        // Make sure the image length can be sent in the final packet
+        check_image_size(jpeg_image)?;
+
         // Load out the image, and get the width + height
-        let mut decoder = Decoder::new(jpeg_image);
-        decoder.read_info().map_err(Error::from)?;
-
-        if let Some(info) = decoder.info() {
-            if (x + info.width as u32) > display_size.0 {
-                beacn_bail!(
-                    "Image overflows display width, {}>{}",
-                    x + info.width as u32,
-                    display_size.0
-                );
-            }
-            if (y + info.height as u32) > display_size.1 {
-                beacn_bail!(
-                    "Image overflows display height, {}>{}",
-                    x + info.height as u32,
-                    display_size.1
-                );
-            }
-        } else {
-            beacn_bail!("Unable to Fetch Image Info");
+        let info = read_jpeg_info(jpeg_image)?;
+        if (x + info.width as u32) > display_size.0 {
+            beacn_bail!(
+                "Image overflows display width, {}>{}",
+                x + info.width as u32,
+                display_size.0
+            );
+        }
+        if (y + info.height as u32) > display_size.1 {
+            beacn_bail!(
+                "Image overflows display height, {}>{}",
+                y + info.height as u32,
+                display_size.1
+            );
         }
 
         self.get_sender()
@@ -536,9 +885,109 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         Ok(())
     }
 
+    /// Updates a sub-rectangle of the display. The final packet of an image transfer only
+    /// carries the JPEG length and its x / y position, so only the region is sent over USB and
+    /// the rest of the display is left untouched. The JPEG must be exactly `width` x `height`.
+    ///
+    /// Transfer time scales with the JPEG size (1020 bytes per packet), so keeping regions
+    /// small is the most effective way to increase the update rate.
+    fn set_image_region(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        jpeg_image: &[u8],
+    ) -> BResult<()> {
+        let info = read_jpeg_info(jpeg_image)?;
+        if info.width as u32 != width || info.height as u32 != height {
+            beacn_bail!(
+                "Image size {}x{} does not match region size {}x{}",
+                info.width,
+                info.height,
+                width,
+                height
+            );
+        }
+
+        self.set_image(x, y, jpeg_image)
+    }
+
+    /// Sends raw RGB or RGBA pixel data to the display. The device only accepts JPEGs, so the
+    /// pixels are encoded here before being passed through to `set_image`. The pixel format is
+    /// determined by the length of the buffer (3 or 4 bytes per pixel).
+    fn set_image_raw(&self, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) -> BResult<()> {
+        // The region must fit on the display, checked without overflowing for large values
+        let display_size = self.get_display_size();
+        if x.checked_add(width)
+            .is_none_or(|right| right > display_size.0)
+        {
+            let range = 0..=display_size.0.saturating_sub(x);
+            return Err(out_of_range("Image Width", width, &range));
+        }
+        if y.checked_add(height)
+            .is_none_or(|bottom| bottom > display_size.1)
+        {
+            let range = 0..=display_size.1.saturating_sub(y);
+            return Err(out_of_range("Image Height", height, &range));
+        }
+
+        let pixel_count = (width as usize).checked_mul(height as usize);
+        let rgb_length = pixel_count.and_then(|count| count.checked_mul(3));
+        let rgba_length = pixel_count.and_then(|count| count.checked_mul(4));
+        let colour_type = if Some(pixels.len()) == rgb_length {
+            ColorType::Rgb
+        } else if Some(pixels.len()) == rgba_length {
+            ColorType::Rgba
+        } else {
+            beacn_bail!(
+                "Pixel buffer length {} doesn't match a {}x{} RGB or RGBA image",
+                pixels.len(),
+                width,
+                height
+            );
+        };
+
+        let mut jpeg = Vec::new();
+        let encoder = Encoder::new(&mut jpeg, JPEG_QUALITY);
+        encoder
+            .encode(pixels, width as u16, height as u16, colour_type)
+            .map_err(Error::from)?;
+
+        self.set_image(x, y, &jpeg)
+    }
+
+    /// Renders a single line of text with the bundled font, and draws it on the display with
+    /// its top left corner at x, y. Fails if the rendered text doesn't fit on the display.
+    #[cfg(feature = "text")]
+    fn draw_text(
+        &self,
+        x: u32,
+        y: u32,
+        text: &str,
+        font_size: f32,
+        colour: RGBA,
+        background: RGBA,
+    ) -> BResult<()> {
+        let display_size = self.get_display_size();
+        if x >= display_size.0 || y >= display_size.1 {
+            beacn_bail!(
+                "Position should be between 0..{}, 0..{}",
+                display_size.0,
+                display_size.1
+            );
+        }
+
+        // The position is on the display, so this can't underflow
+        let available = (display_size.0 - x, display_size.1 - y);
+        let rendered = render_text(text, font_size, colour, background, available)?;
+
+        self.set_image_raw(x, y, rendered.width, rendered.height, &rendered.pixels)
+    }
+
     fn set_display_brightness(&self, brightness: u8) -> BResult<()> {
         if !(1..=100).contains(&brightness) {
-            beacn_bail!("Display Brightness must be a percentage");
+            return Err(out_of_range("Display Brightness", brightness, &(1..=100)));
         }
 
         self.get_sender()
@@ -549,7 +998,7 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
 
     fn set_button_brightness(&self, brightness: u8) -> BResult<()> {
         if !(0..=10).contains(&brightness) {
-            beacn_bail!("Button Brightness must be between 0 and 10");
+            return Err(out_of_range("Button Brightness", brightness, &(0..=10)));
         }
         self.get_sender()
             .send(SetButtonBrightness(brightness))
@@ -557,12 +1006,10 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         Ok(())
     }
 
-    fn set_dim_timeout(&self, timeout: Duration) -> BResult<()> {
-        if timeout > Duration::from_secs(300) || timeout < Duration::from_secs(30) {
-            beacn_bail!(
-                "For display safety, dim timeout must be lower than 5 minutes, and greater than 30 seconds"
-            );
-        }
+    /// Sets how long the display waits without interaction before dimming, `None` disables
+    /// dimming entirely so the display stays at its active brightness.
+    fn set_dim_timeout(&self, timeout: Option<Duration>) -> BResult<()> {
+        check_dim_timeout(timeout)?;
 
         self.get_sender()
             .send(SetDimTimeout(timeout))
@@ -570,6 +1017,44 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
         Ok(())
     }
 
+    /// Limits the absolute position reported by `Interactions::DialPosition` for a dial, if the
+    /// current position is outside the new range it'll be clamped. `None` removes the limit.
+    fn set_dial_range(&self, dial: Dials, range: Option<RangeInclusive<i32>>) -> BResult<()> {
+        if range.as_ref().is_some_and(|range| range.is_empty()) {
+            beacn_bail!("Dial range {:?} is empty", range);
+        }
+
+        self.get_sender()
+            .send(SetDialRange(dial, range))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Sets the current absolute position of a dial, useful for syncing a dial with the value
+    /// it controls. The position will be clamped to the dial's range if one is set.
+    fn set_dial_position(&self, dial: Dials, position: i32) -> BResult<()> {
+        self.get_sender()
+            .send(SetDialPosition(dial, position))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Runs an animation on a button's lighting, this is handled by the event thread so no
+    /// further calls are needed. Setting the button's colour stops the animation.
+    fn set_button_animation(&self, button: ButtonLighting, animation: Animation) -> BResult<()> {
+        let period = match animation {
+            Animation::Pulse { period, .. } | Animation::Blink { period, .. } => period,
+        };
+        if period.is_zero() {
+            beacn_bail!("Animation period must be greater than zero");
+        }
+
+        self.get_sender()
+            .send(SetButtonAnimation(button, animation))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
     fn set_button_colour(&self, button: ButtonLighting, colour: RGBA) -> BResult<()> {
         let button = button as u8;
         self.get_sender()
@@ -577,34 +1062,208 @@ pub trait BeacnControlInteraction: BeacnControlDeviceAttach {
             .map_err(Error::from)?;
         Ok(())
     }
+
+    fn set_all_button_colours(&self, colours: EnumMap<ButtonLighting, RGBA>) -> BResult<()> {
+        self.get_sender()
+            .send(SetAllButtonColours(colours))
+            .map_err(Error::from)?;
+        Ok(())
+    }
 }
 
-/// Simple function to Open a libusb connection to a Beacn Audio device, do initial setup and
-/// grab the firmware version from the device.
-pub(crate) fn open_beacn(def: DeviceDefinition, product_id: &[u16]) -> BResult<BeacnDeviceHandle> {
-    if !product_id.contains(&def.descriptor.product_id()) {
+// Creates the timer for the next dim, which never fires if dimming is disabled
+fn dim_timer(duration: Option<Duration>) -> Receiver<Instant> {
+    match duration {
+        Some(duration) => after(duration),
+        None => never(),
+    }
+}
+
+// Sends an interaction to the application, if it's listening
+fn send_interaction(tx: &Option<Sender<Interactions>>, interaction: Interactions) {
+    if let Some(tx) = tx {
+        let _ = tx.send(interaction);
+    }
+}
+
+impl Animation {
+    // Calculates the colour of the animation at a point in time since it started
+    fn colour_at(&self, elapsed: Duration) -> RGBA {
+        match *self {
+            Animation::Pulse { from, to, period } => {
+                let phase = elapsed.as_secs_f32() % period.as_secs_f32() / period.as_secs_f32();
+
+                // Fade towards 'to' in the first half, and back in the second
+                let amount = if phase < 0.5 {
+                    phase * 2.0
+                } else {
+                    2.0 - phase * 2.0
+                };
+                RGBA::lerp(from, to, amount)
+            }
+            Animation::Blink { on, off, period } => {
+                let phase = elapsed.as_secs_f32() % period.as_secs_f32() / period.as_secs_f32();
+                if phase < 0.5 { on } else { off }
+            }
+        }
+    }
+}
+
+// Images must have at least one byte (the final packet sends the length - 1), and their length
+// must fit in the final packet. This is checked for every image the event thread sends,
+// including ones which replace an image mid-transfer.
+fn check_image_size(image: &[u8]) -> BResult<()> {
+    if image.is_empty() {
+        beacn_bail!("Image is empty");
+    }
+    if image.len() > IMAGE_MAX_SIZE {
         beacn_bail!(
-            "Expecting PIDs {:?} but got {}",
-            product_id,
-            def.descriptor.product_id()
+            "Image is too large, {} > {} bytes",
+            image.len(),
+            IMAGE_MAX_SIZE
         );
     }
+    Ok(())
+}
 
-    let handle = def.device.open()?;
-    handle.claim_interface(0)?;
-    handle.set_alternate_setting(0, 1)?;
-    handle.clear_halt(0x83)?;
+enum ReadAhead {
+    Nothing,
+    Stop,
+    Newer(Vec<u8>),
+}
 
-    let setup_timeout = Duration::from_millis(2000);
+// Looks at the messages waiting behind an image, if any are newer images for the same position
+// the latest is returned so the stale one can be dropped. This only looks at messages at the
+// front of the queue, the first message which isn't an image for this position is deferred and
+// the rest are left in place, so everything else is still handled in the order it was sent.
+fn read_ahead(
+    rx: &Receiver<ControlThreadSender>,
+    deferred_tx: &Sender<ControlThreadSender>,
+    deferred_rx: &Receiver<ControlThreadSender>,
+    x: u32,
+    y: u32,
+) -> ReadAhead {
+    let mut newer = None;
+    while deferred_rx.is_empty() {
+        match rx.try_recv() {
+            Ok(ControlThreadSender::Stop) => return ReadAhead::Stop,
+            Ok(SetImage(next_x, next_y, img)) if next_x == x && next_y == y => newer = Some(img),
+            Ok(message) => {
+                let _ = deferred_tx.send(message);
+            }
+            Err(_) => break,
+        }
+    }
 
-    // Unlike the Mic and Studio, we use an interrupt, rather a bulk read
-    let mut input = [0; 64];
-    handle.write_interrupt(0x03, &[00, 00, 00, 1], setup_timeout)?;
-    handle.read_interrupt(0x83, &mut input, setup_timeout)?;
+    match newer {
+        Some(img) => ReadAhead::Newer(img),
+        None => ReadAhead::Nothing,
+    }
+}
+
+// Keeps a running total of a dial's movement, optionally clamped to a range
+#[derive(Debug, Default)]
+pub(crate) struct DialTracker {
+    position: i32,
+    range: Option<RangeInclusive<i32>>,
+
+    // Movement that hasn't yet reached the threshold to be reported
+    pending: i32,
+    threshold: u8,
+
+    // Whether the position is sent after each change, see ControlConfig::with_dial_positions
+    report_position: bool,
+}
+
+impl DialTracker {
+    // Adds a change to the pending movement, returning it once it reaches the threshold
+    fn accumulate(&mut self, change: i8) -> Option<i8> {
+        self.pending = self.pending.saturating_add(change as i32);
+        if self.pending.unsigned_abs() < self.threshold.max(1) as u32 {
+            return None;
+        }
 
-    let (version, serial) = get_device_info(&input)?;
+        let change = self.pending.clamp(i8::MIN as i32, i8::MAX as i32);
+        self.pending = 0;
+        Some(change as i8)
+    }
+
+    fn apply(&mut self, change: i8) -> i32 {
+        self.set_position(self.position.saturating_add(change as i32));
+        self.position
+    }
+
+    fn set_position(&mut self, position: i32) {
+        self.pending = 0;
+        self.position = match &self.range {
+            Some(range) => position.clamp(*range.start(), *range.end()),
+            None => position,
+        };
+    }
+
+    fn set_range(&mut self, range: Option<RangeInclusive<i32>>) {
+        self.range = range;
+        self.set_position(self.position);
+    }
+}
+
+/// Reads the header of a JPEG, and checks that it's something the display can actually render.
+/// The display only handles baseline (sequential) JPEGs, and CMYK isn't supported, anything
+/// else is accepted by the device but drawn as garbage, so we reject it here with the detected
+/// format. Greyscale JPEGs are accepted.
+fn read_jpeg_info(jpeg_image: &[u8]) -> BResult<ImageInfo> {
+    let mut decoder = Decoder::new(jpeg_image);
+    decoder.read_info().map_err(Error::from)?;
+
+    let Some(info) = decoder.info() else {
+        beacn_bail!("Unable to Fetch Image Info");
+    };
+
+    if info.width == 0 || info.height == 0 {
+        beacn_bail!("Image has no size ({}x{})", info.width, info.height);
+    }
+    if info.coding_process != CodingProcess::DctSequential {
+        beacn_bail!(
+            "Unsupported JPEG encoding {:?}, the image must be a baseline (sequential) JPEG",
+            info.coding_process
+        );
+    }
+    if !matches!(info.pixel_format, PixelFormat::RGB24 | PixelFormat::L8) {
+        beacn_bail!(
+            "Unsupported JPEG pixel format {:?}, the image must be a YCbCr / RGB or greyscale JPEG",
+            info.pixel_format
+        );
+    }
+    Ok(info)
+}
+
+/// Simple function to Open a libusb connection to a Beacn Audio device, do initial setup and
+/// grab the firmware version from the device.
+pub(crate) fn open_beacn<C: UsbContext>(
+    def: DeviceDefinition<C>,
+    product_id: &[u16],
+) -> BResult<BeacnDeviceHandle<DeviceHandle<C>, C>> {
+    if !product_id.contains(&def.descriptor.product_id()) {
+        return Err(BeacnError::UnexpectedPid {
+            expected: product_id.to_vec(),
+            found: def.descriptor.product_id(),
+        });
+    }
+
+    let timeouts = def.get_timeouts();
+    timeouts.validate()?;
+
+    let (handle, version, serial, extra_info) = with_open_retry(|| {
+        let handle = def.device.open()?;
+        let (version, serial, extra_info) = attach_control(&handle, timeouts.get_setup())?;
+        Ok((handle, version, serial, extra_info))
+    })?;
+
+    let location = DeviceLocation::from(def.device.clone());
+    let serial = serial.unwrap_or_else(|| fallback_serial(location));
 
     debug!(
+        serial = serial.as_str();
         "Loaded Device, Location: {}.{}, Serial: {}, Version: {}",
         def.device.bus_number(),
         def.device.address(),
@@ -612,11 +1271,36 @@ pub(crate) fn open_beacn(def: DeviceDefinition, product_id: &[u16]) -> BResult<B
         version
     );
 
+    let (manufacturer, product) = read_descriptor_strings(&handle, &def.descriptor);
+
     Ok(BeacnDeviceHandle {
         descriptor: def.descriptor,
         device: def.device,
         handle,
         version,
         serial,
+        extra_info,
+        manufacturer,
+        product,
+        timeouts,
     })
 }
+
+/// Claims the control interface on an open handle and performs the initial setup, returning the
+/// firmware version, serial and extra info reported by the device.
+pub(crate) fn attach_control<C: UsbContext>(
+    handle: &DeviceHandle<C>,
+    setup_timeout: Duration,
+) -> BResult<(VersionNumber, Option<String>, Vec<u8>)> {
+    handle.claim_interface(0)?;
+    handle.set_alternate_setting(0, 1)?;
+    handle.clear_halt(0x83)?;
+
+    // Unlike the Mic and Studio, we use an interrupt, rather a bulk read
+    let mut input = [0; 64];
+    handle.write_interrupt(0x03, &[00, 00, 00, 1], setup_timeout)?;
+    let length = handle.read_interrupt(0x83, &mut input, setup_timeout)?;
+
+    let (version, serial) = get_device_info(&input[..length])?;
+    Ok((version, serial, get_extra_device_info(&input[..length])))
+}
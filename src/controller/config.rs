@@ -0,0 +1,197 @@
+use crate::common::Timeouts;
+use crate::controller::{ControlThreadError, InteractionMode};
+use crate::{BResult, beacn_bail, out_of_range};
+use crossbeam::channel::Sender;
+use std::time::Duration;
+
+// Default Display 'Active' and 'Dimmed' brightness, and the default dim time
+static DISPLAY_DEFAULT_FULL_BRIGHTNESS: u8 = 40;
+static DISPLAY_DEFAULT_DIM_BRIGHTNESS: u8 = 1;
+static DISPLAY_DEFAULT_DIM_TIME: u64 = 180;
+
+// Default button brightness
+static BUTTONS_DEFAULT_BRIGHTNESS: u8 = 8;
+
+// By default, every dial movement is reported
+static DIAL_DEFAULT_THRESHOLD: u8 = 1;
+
+/// The initial display and button settings applied when a control device is opened, this
+/// allows them to be set before anything is shown, rather than changing them after connecting.
+/// Start from `ControlConfig::default()` and change what's needed with the `with_` methods.
+#[derive(Debug, Clone)]
+pub struct ControlConfig {
+    active_brightness: u8,
+    dim_brightness: u8,
+    dim_timeout: Option<Duration>,
+    button_brightness: u8,
+    dial_threshold: u8,
+    dial_positions: bool,
+    interaction_mode: Option<InteractionMode>,
+    timeouts: Option<Timeouts>,
+    error_tx: Option<Sender<ControlThreadError>>,
+}
+
+// Senders can't be compared by value, so they're equal if they send to the same channel
+impl PartialEq for ControlConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let same_error_tx = match (&self.error_tx, &other.error_tx) {
+            (Some(a), Some(b)) => a.same_channel(b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        same_error_tx
+            && self.active_brightness == other.active_brightness
+            && self.dim_brightness == other.dim_brightness
+            && self.dim_timeout == other.dim_timeout
+            && self.button_brightness == other.button_brightness
+            && self.dial_threshold == other.dial_threshold
+            && self.dial_positions == other.dial_positions
+            && self.interaction_mode == other.interaction_mode
+            && self.timeouts == other.timeouts
+    }
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            active_brightness: DISPLAY_DEFAULT_FULL_BRIGHTNESS,
+            dim_brightness: DISPLAY_DEFAULT_DIM_BRIGHTNESS,
+            dim_timeout: Some(Duration::from_secs(DISPLAY_DEFAULT_DIM_TIME)),
+            button_brightness: BUTTONS_DEFAULT_BRIGHTNESS,
+            dial_threshold: DIAL_DEFAULT_THRESHOLD,
+            dial_positions: false,
+            interaction_mode: None,
+            timeouts: None,
+            error_tx: None,
+        }
+    }
+}
+
+impl ControlConfig {
+    pub fn with_active_brightness(mut self, brightness: u8) -> Self {
+        self.active_brightness = brightness;
+        self
+    }
+
+    pub fn with_dim_brightness(mut self, brightness: u8) -> Self {
+        self.dim_brightness = brightness;
+        self
+    }
+
+    /// Sets how long before the display dims, `None` disables dimming
+    pub fn with_dim_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.dim_timeout = timeout;
+        self
+    }
+
+    pub fn with_button_brightness(mut self, brightness: u8) -> Self {
+        self.button_brightness = brightness;
+        self
+    }
+
+    /// Sets how far a dial has to move before a `DialChanged` is sent, smaller movements are
+    /// accumulated (and cancel out if the dial moves back) until they reach the threshold.
+    /// The default of 1 reports every movement.
+    pub fn with_dial_threshold(mut self, threshold: u8) -> Self {
+        self.dial_threshold = threshold;
+        self
+    }
+
+    /// Sends an `Interactions::DialPosition` with the dial's absolute position after every
+    /// `DialChanged`. This is off by default, as most consumers only need the changes.
+    pub fn with_dial_positions(mut self, enabled: bool) -> Self {
+        self.dial_positions = enabled;
+        self
+    }
+
+    /// Forces the interaction mode rather than picking it from the firmware version, `None`
+    /// (the default) picks automatically. This is intended for testing, a device running in the
+    /// wrong mode may not report interactions at all.
+    pub fn with_interaction_mode(mut self, mode: Option<InteractionMode>) -> Self {
+        self.interaction_mode = mode;
+        self
+    }
+
+    /// Replaces the default USB timeouts for the device's type, `None` (the default) uses the
+    /// defaults, see Timeouts
+    pub fn with_timeouts(mut self, timeouts: Option<Timeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// If the device's event thread stops because of an error, the reason is sent here, see
+    /// ControlThreadError
+    pub fn with_error_sender(mut self, error_tx: Option<Sender<ControlThreadError>>) -> Self {
+        self.error_tx = error_tx;
+        self
+    }
+
+    pub fn get_active_brightness(&self) -> u8 {
+        self.active_brightness
+    }
+
+    pub fn get_dim_brightness(&self) -> u8 {
+        self.dim_brightness
+    }
+
+    pub fn get_dim_timeout(&self) -> Option<Duration> {
+        self.dim_timeout
+    }
+
+    pub fn get_button_brightness(&self) -> u8 {
+        self.button_brightness
+    }
+
+    pub fn get_dial_threshold(&self) -> u8 {
+        self.dial_threshold
+    }
+
+    pub fn get_dial_positions(&self) -> bool {
+        self.dial_positions
+    }
+
+    pub fn get_interaction_mode(&self) -> Option<InteractionMode> {
+        self.interaction_mode
+    }
+
+    pub fn get_timeouts(&self) -> Option<Timeouts> {
+        self.timeouts
+    }
+
+    pub fn get_error_sender(&self) -> Option<Sender<ControlThreadError>> {
+        self.error_tx.clone()
+    }
+
+    // These follow the same rules as the setters on BeacnControlInteraction
+    pub(crate) fn validate(&self) -> BResult<()> {
+        if !(1..=100).contains(&self.active_brightness) {
+            let value = self.active_brightness;
+            return Err(out_of_range("Display Brightness", value, &(1..=100)));
+        }
+        if !(0..=100).contains(&self.dim_brightness) {
+            let value = self.dim_brightness;
+            return Err(out_of_range("Dim Brightness", value, &(0..=100)));
+        }
+        if !(0..=10).contains(&self.button_brightness) {
+            let value = self.button_brightness;
+            return Err(out_of_range("Button Brightness", value, &(0..=10)));
+        }
+        if self.dial_threshold == 0 {
+            beacn_bail!("Dial threshold must be at least 1");
+        }
+        if let Some(timeouts) = &self.timeouts {
+            timeouts.validate()?;
+        }
+        check_dim_timeout(self.dim_timeout)
+    }
+}
+
+// Very short timeouts would have the display constantly switching brightness, so we keep a
+// minimum, there's no upper limit (a permanently powered display may want to dim after hours).
+pub(crate) fn check_dim_timeout(timeout: Option<Duration>) -> BResult<()> {
+    if timeout.is_some_and(|timeout| timeout < Duration::from_secs(30)) {
+        beacn_bail!("Dim timeout must be at least 30 seconds");
+    }
+    Ok(())
+}
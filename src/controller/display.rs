@@ -0,0 +1,225 @@
+use crate::controller::BeacnControlDevice;
+use crate::types::RGBA;
+use crate::{BResult, beacn_bail};
+
+// The display is compared in horizontal bands of this many rows, each band with changes is sent
+// as a single region covering the changed columns. 16 matches the JPEG MCU height.
+const BAND_HEIGHT: u32 = 16;
+
+/// An RGB framebuffer matching the size of a control device's display. Draw into it, then call
+/// `present` to send it to the device, only the parts which have changed since the last present
+/// are sent.
+///
+/// The display has no concept of transparency, so the alpha channel of any colour is ignored.
+#[derive(Debug, Clone)]
+pub struct DisplayBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+
+    // What's currently on the display, None until the first present
+    presented: Option<Vec<u8>>,
+}
+
+impl DisplayBuffer {
+    /// Creates a buffer sized for the device's display, filled with black
+    pub fn new(device: &dyn BeacnControlDevice) -> Self {
+        let (width, height) = device.get_display_size();
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 3) as usize],
+            presented: None,
+        }
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, colour: RGBA) -> BResult<()> {
+        if x >= self.width || y >= self.height {
+            beacn_bail!(
+                "Pixel {},{} is outside the display ({}x{})",
+                x,
+                y,
+                self.width,
+                self.height
+            );
+        }
+
+        let position = self.offset(x, y);
+        self.pixels[position..position + 3].copy_from_slice(&[
+            colour.red,
+            colour.green,
+            colour.blue,
+        ]);
+        Ok(())
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        colour: RGBA,
+    ) -> BResult<()> {
+        self.check_region(x, y, width, height)?;
+
+        let pixel = [colour.red, colour.green, colour.blue];
+        for row in y..y + height {
+            let start = self.offset(x, row);
+            let end = start + (width * 3) as usize;
+            for target in self.pixels[start..end].chunks_exact_mut(3) {
+                target.copy_from_slice(&pixel);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies a block of RGB pixels (3 bytes per pixel, row by row) into the buffer
+    pub fn blit(&mut self, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) -> BResult<()> {
+        self.check_region(x, y, width, height)?;
+        let length = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|count| count.checked_mul(3));
+        if Some(pixels.len()) != length {
+            beacn_bail!(
+                "Pixel buffer length {} doesn't match a {}x{} RGB image",
+                pixels.len(),
+                width,
+                height
+            );
+        }
+
+        // An empty region has nothing to copy (and chunks_exact can't take a zero length)
+        if pixels.is_empty() {
+            return Ok(());
+        }
+
+        let row_length = width as usize * 3;
+        for (index, source) in pixels.chunks_exact(row_length).enumerate() {
+            let start = self.offset(x, y + index as u32);
+            self.pixels[start..start + row_length].copy_from_slice(source);
+        }
+        Ok(())
+    }
+
+    /// Sends any changes since the last present to the device. The first present sends the
+    /// entire buffer.
+    pub fn present(&mut self, device: &dyn BeacnControlDevice) -> BResult<()> {
+        if device.get_display_size() != (self.width, self.height) {
+            beacn_bail!("Display buffer doesn't match the size of the device's display");
+        }
+
+        let row_length = (self.width * 3) as usize;
+        let mut band_y = 0;
+        while band_y < self.height {
+            let band_height = BAND_HEIGHT.min(self.height - band_y);
+            let start = self.offset(0, band_y);
+            let end = start + row_length * band_height as usize;
+
+            // Find the left and right most columns which have changed in this band
+            let columns = match &self.presented {
+                None => Some((0, self.width - 1)),
+                Some(presented) => {
+                    let mut columns: Option<(u32, u32)> = None;
+                    let current = self.pixels[start..end].chunks_exact(row_length);
+                    let previous = presented[start..end].chunks_exact(row_length);
+                    for (current, previous) in current.zip(previous) {
+                        let current = current.chunks_exact(3);
+                        let previous = previous.chunks_exact(3);
+                        for (column, (a, b)) in current.zip(previous).enumerate() {
+                            if a != b {
+                                let column = column as u32;
+                                columns = Some(match columns {
+                                    None => (column, column),
+                                    Some((left, right)) => (left.min(column), right.max(column)),
+                                });
+                            }
+                        }
+                    }
+                    columns
+                }
+            };
+
+            if let Some((left, right)) = columns {
+                let width = right - left + 1;
+                let mut region = Vec::with_capacity((width * band_height * 3) as usize);
+                for row in band_y..band_y + band_height {
+                    let row_start = self.offset(left, row);
+                    region.extend_from_slice(
+                        &self.pixels[row_start..row_start + (width * 3) as usize],
+                    );
+                }
+                device.set_image_raw(left, band_y, width, band_height, &region)?;
+            }
+            band_y += band_height;
+        }
+
+        self.presented = Some(self.pixels.clone());
+        Ok(())
+    }
+
+    /// Forces the next present to send the entire buffer, useful if something else has drawn
+    /// on the display.
+    pub fn invalidate(&mut self) {
+        self.presented = None;
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * 3) as usize
+    }
+
+    fn check_region(&self, x: u32, y: u32, width: u32, height: u32) -> BResult<()> {
+        // Checked, so a large width or height can't wrap around and pass
+        let right = x.checked_add(width);
+        let bottom = y.checked_add(height);
+        if right.is_none_or(|right| right > self.width)
+            || bottom.is_none_or(|bottom| bottom > self.height)
+        {
+            beacn_bail!(
+                "Region {}x{} at {},{} overflows the display ({}x{})",
+                width,
+                height,
+                x,
+                y,
+                self.width,
+                self.height
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(width: u32, height: u32) -> DisplayBuffer {
+        DisplayBuffer {
+            width,
+            height,
+            pixels: vec![0; (width * height * 3) as usize],
+            presented: None,
+        }
+    }
+
+    #[test]
+    fn oversized_regions_are_rejected() {
+        let mut buffer = buffer(8, 8);
+        let colour = RGBA {
+            red: 0xff,
+            green: 0,
+            blue: 0,
+            alpha: 0xff,
+        };
+
+        assert!(buffer.fill_rect(1, 0, u32::MAX, 1, colour).is_err());
+        assert!(buffer.fill_rect(0, u32::MAX, 1, 1, colour).is_err());
+        assert!(buffer.blit(u32::MAX, 0, 2, 1, &[0; 6]).is_err());
+        assert!(buffer.fill_rect(0, 0, 8, 8, colour).is_ok());
+        assert!(buffer.blit(8, 8, 0, 0, &[]).is_ok());
+    }
+}
@@ -1,29 +1,53 @@
 use crate::BResult;
 use crate::common::DeviceDefinition;
-use crate::controller::common::{BeacnControlDeviceAttach, BeacnControlInteraction, open_beacn};
-use crate::controller::{BeacnControlDevice, ControlThreadSender, Interactions};
+use crate::controller::common::{
+    BeacnControlDeviceAttach, BeacnControlDeviceLocal, BeacnControlInteraction, LastImage,
+    interaction_mode, open_beacn,
+};
+use crate::controller::{
+    ALL_DIALS, BeacnControlDevice, Buttons, ControlConfig, ControlThreadSender, Dials,
+    InteractionMode, Interactions,
+};
 use crate::manager::PID_BEACN_MIX;
 use crate::version::VersionNumber;
 use crossbeam::channel::{Sender, bounded};
 use log::debug;
+use rusb::UsbContext;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
 
+static MIX_BUTTONS: &[Buttons] = &[
+    Buttons::PageLeft,
+    Buttons::PageRight,
+    Buttons::Dial1,
+    Buttons::Dial2,
+    Buttons::Dial3,
+    Buttons::Dial4,
+];
+
 pub struct BeacnMix {
     pid: u16,
 
     serial: String,
     version: VersionNumber,
+    extra_info: Vec<u8>,
+    manufacturer: String,
+    product: String,
+    interaction_mode: InteractionMode,
 
     sender: Sender<ControlThreadSender>,
+    button_state: Arc<Mutex<u16>>,
+    last_image: LastImage,
 }
 
 impl BeacnControlDeviceAttach for BeacnMix {
-    fn connect(
-        definition: DeviceDefinition,
+    fn connect<C: UsbContext + 'static>(
+        definition: DeviceDefinition<C>,
         interaction: Option<Sender<Interactions>>,
         health_tx: Sender<()>,
+        config: ControlConfig,
     ) -> BResult<Box<dyn BeacnControlDevice>>
     where
         Self: Sized,
@@ -33,19 +57,42 @@ impl BeacnControlDeviceAttach for BeacnMix {
         let handle = open_beacn(definition, PID_BEACN_MIX)?;
         let serial = handle.serial.clone();
         let version = handle.version;
+        let extra_info = handle.extra_info.clone();
+        let manufacturer = handle.manufacturer.clone();
+        let product = handle.product.clone();
         let pid = handle.descriptor.product_id();
+        let interaction_mode = interaction_mode(version, &config);
+        let error_tx = config.get_error_sender();
 
         let (sender, receiver) = bounded(64);
+        let button_state = Arc::new(Mutex::new(0));
+        let last_image = Arc::new(Mutex::new(None));
 
         let control_attach = Self {
             pid,
             serial,
             version,
+            extra_info,
+            manufacturer,
+            product,
+            interaction_mode,
             sender,
+            button_state: button_state.clone(),
+            last_image: last_image.clone(),
         };
 
         thread::spawn(move || {
-            Self::spawn_event_handler(receiver, handle, interaction);
+            let result = Self::spawn_event_handler(
+                receiver,
+                handle,
+                interaction,
+                button_state,
+                last_image,
+                config,
+            );
+            if let (Err(error), Some(error_tx)) = (result, error_tx) {
+                let _ = error_tx.send(error);
+            }
             sleep(Duration::from_millis(500));
             let _ = health_tx.send(());
         });
@@ -64,6 +111,18 @@ impl BeacnControlDeviceAttach for BeacnMix {
         self.version.to_string()
     }
 
+    fn get_extra_device_info(&self) -> Vec<u8> {
+        self.extra_info.clone()
+    }
+
+    fn get_manufacturer_string(&self) -> String {
+        self.manufacturer.clone()
+    }
+
+    fn get_product_string(&self) -> String {
+        self.product.clone()
+    }
+
     fn get_sender(&self) -> &Sender<ControlThreadSender> {
         &self.sender
     }
@@ -73,12 +132,34 @@ impl BeacnControlDeviceAttach for BeacnMix {
     }
 }
 
-impl BeacnControlDevice for BeacnMix {}
+impl BeacnControlDeviceLocal for BeacnMix {
+    fn get_button_state(&self) -> &Arc<Mutex<u16>> {
+        &self.button_state
+    }
+
+    fn get_last_image(&self) -> &LastImage {
+        &self.last_image
+    }
+}
+
+impl BeacnControlDevice for BeacnMix {
+    fn available_buttons(&self) -> &'static [Buttons] {
+        MIX_BUTTONS
+    }
+
+    fn available_dials(&self) -> &'static [Dials] {
+        ALL_DIALS
+    }
+
+    fn get_interaction_mode(&self) -> InteractionMode {
+        self.interaction_mode
+    }
+}
 impl BeacnControlInteraction for BeacnMix {}
 
 impl Drop for BeacnMix {
     fn drop(&mut self) {
-        debug!("Dropping BeacnMix");
+        debug!(serial = self.serial.as_str(); "Dropping BeacnMix");
         let _ = self.sender.send(ControlThreadSender::Stop);
     }
 }
@@ -0,0 +1,69 @@
+use crate::controller::ButtonState::Press;
+use crate::controller::{Buttons, Dials, Interactions};
+use strum::Display;
+
+/// What a `Mixer` reports after handling an interaction
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MixerEvent {
+    /// The current page has changed
+    PageChanged(u8),
+
+    /// A dial was turned, carrying the page it was on, the logical channel the dial maps to on
+    /// that page, and how far it moved
+    ChannelVolumeChanged(u8, u8, i8),
+}
+
+/// Turns the raw interactions from a control device into something a mixing application can
+/// use directly. The PageLeft and PageRight buttons move between pages, and on each page the
+/// four dials map to their own set of channels, so on page 1 Dial1 is channel 4.
+///
+/// Pass every interaction received from the device to `handle`, the Mixer doesn't talk to the
+/// device itself. Pages don't wrap, paging past either end is ignored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Mixer {
+    pages: u8,
+    page: u8,
+}
+
+impl Mixer {
+    /// Creates a Mixer with the given number of pages, starting on page 0
+    pub fn new(pages: u8) -> Self {
+        Self {
+            pages: pages.max(1),
+            page: 0,
+        }
+    }
+
+    pub fn get_page(&self) -> u8 {
+        self.page
+    }
+
+    pub fn get_page_count(&self) -> u8 {
+        self.pages
+    }
+
+    /// The logical channel a dial controls on the current page
+    pub fn get_channel(&self, dial: Dials) -> u8 {
+        self.page.saturating_mul(4).saturating_add(dial as u8)
+    }
+
+    /// Processes an interaction, returning an event if it changed the page or a channel
+    pub fn handle(&mut self, interaction: Interactions) -> Option<MixerEvent> {
+        match interaction {
+            Interactions::ButtonPress(Buttons::PageLeft, Press) if self.page > 0 => {
+                self.page -= 1;
+                Some(MixerEvent::PageChanged(self.page))
+            }
+            Interactions::ButtonPress(Buttons::PageRight, Press) if self.page + 1 < self.pages => {
+                self.page += 1;
+                Some(MixerEvent::PageChanged(self.page))
+            }
+            Interactions::DialChanged(dial, delta) => Some(MixerEvent::ChannelVolumeChanged(
+                self.page,
+                self.get_channel(dial),
+                delta,
+            )),
+            _ => None,
+        }
+    }
+}
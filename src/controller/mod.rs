@@ -1,40 +1,109 @@
 use crate::common::find_device;
+pub(crate) use crate::controller::common::attach_control;
 use crate::controller::common::{BeacnControlDeviceAttach, BeacnControlInteraction};
+pub use crate::controller::config::ControlConfig;
+pub use crate::controller::display::DisplayBuffer;
 use crate::controller::mix::BeacnMix;
 use crate::controller::mix_create::BeacnMixCreate;
-use crate::manager::{DeviceLocation, PID_BEACN_MIX, PID_BEACN_MIX_CREATE};
+pub use crate::controller::mixer::{Mixer, MixerEvent};
+#[cfg(feature = "raw-access")]
+pub use crate::controller::raw::BeacnControlRawAccess;
+use crate::manager::{DeviceLocation, PID_BEACN_MIX, PID_BEACN_MIX_CREATE, find_device_by_serial};
 use crate::types::RGBA;
-use crate::{BResult, beacn_bail};
+use crate::{BResult, BeacnError, UsbError, beacn_bail};
 use crossbeam::channel::Sender;
-use enum_map::Enum;
+use enum_map::{Enum, EnumMap};
+use rusb::{GlobalContext, UsbContext};
+use std::ops::RangeInclusive;
 use std::panic::RefUnwindSafe;
 use std::time::Duration;
 use strum::{Display, EnumIter};
+use thiserror::Error;
 
 mod common;
+mod config;
+mod display;
 mod mix;
 mod mix_create;
+mod mixer;
+#[cfg(feature = "raw-access")]
+mod raw;
+#[cfg(feature = "text")]
+mod text;
 
 pub trait BeacnControlDevice:
-    BeacnControlDeviceAttach + BeacnControlInteraction + RefUnwindSafe
+    BeacnControlDeviceAttach + BeacnControlInteraction + RefUnwindSafe + Send + Sync
 {
+    /// The buttons physically present on this device, the Mix doesn't have the Audience buttons
+    fn available_buttons(&self) -> &'static [Buttons];
+
+    /// The dials physically present on this device
+    fn available_dials(&self) -> &'static [Dials];
+
+    /// How the event thread receives button presses and dial turns from this device
+    fn get_interaction_mode(&self) -> InteractionMode;
 }
 
+// Shared by the Mix and Mix Create, which both have four dials
+static ALL_DIALS: &[Dials] = &[Dials::Dial1, Dials::Dial2, Dials::Dial3, Dials::Dial4];
+
+/// Opens a Beacn Mix or Mix Create, interactions with the device (button presses and dial turns)
+/// will be sent to `interaction`. If the device's event thread stops because of an error, the
+/// reason is sent to the config's error sender, see ControlConfig::with_error_sender.
+///
+/// All channels used by the library are crossbeam channels (re-exported as `beacn_lib::crossbeam`),
+/// the event thread sends on the same `Sender` it's given, so no bridging is needed.
 pub fn open_control_device(
     location: DeviceLocation,
     interaction: Option<Sender<Interactions>>,
     health_tx: Sender<()>,
+    config: Option<ControlConfig>,
 ) -> BResult<Box<dyn BeacnControlDevice>> {
-    if let Some(device) = find_device(location) {
-        return if PID_BEACN_MIX.contains(&device.descriptor.product_id()) {
-            BeacnMix::connect(device, interaction, health_tx)
-        } else if PID_BEACN_MIX_CREATE.contains(&device.descriptor.product_id()) {
-            BeacnMixCreate::connect(device, interaction, health_tx)
+    let context = GlobalContext::default();
+    open_control_device_with_context(&context, location, interaction, health_tx, config)
+}
+
+/// The same as open_control_device, but the device is found and opened using the given libusb
+/// context, rather than the global one.
+pub fn open_control_device_with_context<C: UsbContext + 'static>(
+    context: &C,
+    location: DeviceLocation,
+    interaction: Option<Sender<Interactions>>,
+    health_tx: Sender<()>,
+    config: Option<ControlConfig>,
+) -> BResult<Box<dyn BeacnControlDevice>> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+
+    if let Some(mut device) = find_device(context, location) {
+        device.timeouts = config.get_timeouts();
+        let product_id = device.descriptor.product_id();
+        return if PID_BEACN_MIX.contains(&product_id) {
+            BeacnMix::connect(device, interaction, health_tx, config)
+        } else if PID_BEACN_MIX_CREATE.contains(&product_id) {
+            BeacnMixCreate::connect(device, interaction, health_tx, config)
         } else {
-            beacn_bail!("Unknown Device");
+            Err(BeacnError::UnexpectedPid {
+                expected: [PID_BEACN_MIX, PID_BEACN_MIX_CREATE].concat(),
+                found: product_id,
+            })
         };
     }
-    beacn_bail!("Unknown Device")
+    Err(BeacnError::DeviceNotFound(location))
+}
+
+/// Opens the Beacn Mix or Mix Create with the given USB serial number, see find_device_by_serial.
+/// The remaining parameters are the same as open_control_device.
+pub fn open_control_device_by_serial(
+    serial: &str,
+    interaction: Option<Sender<Interactions>>,
+    health_tx: Sender<()>,
+    config: Option<ControlConfig>,
+) -> BResult<Box<dyn BeacnControlDevice>> {
+    let Some(location) = find_device_by_serial(serial) else {
+        beacn_bail!("No Beacn device found with serial {}", serial);
+    };
+    open_control_device(location, interaction, health_tx, config)
 }
 
 // These are some helper enums, generally used in messaging :)
@@ -42,6 +111,24 @@ pub fn open_control_device(
 pub enum Interactions {
     ButtonPress(Buttons, ButtonState),
     DialChanged(Dials, i8),
+
+    // The absolute position of a dial, sent after every DialChanged when enabled with
+    // ControlConfig::with_dial_positions
+    DialPosition(Dials, i32),
+
+    // The display has dimmed after the dim timeout, or woken back up. While dimmed, applications
+    // may want to stop sending frames to the display.
+    DisplayDimmed,
+    DisplayWoke,
+}
+
+/// How interactions are received from a control device. Firmware up to 1.2.0 build 80 sends
+/// them as they happen (Notify), newer firmware needs to be polled for them (Poll), which can
+/// add up to 50ms of latency.
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InteractionMode {
+    Poll,
+    Notify,
 }
 
 #[derive(Display, Debug, Copy, Clone, Enum, EnumIter, PartialEq)]
@@ -50,7 +137,7 @@ pub enum ButtonState {
     Release,
 }
 
-#[derive(Display, Debug, Copy, Clone, Enum, EnumIter, PartialEq)]
+#[derive(Display, Debug, Copy, Clone, Enum, EnumIter, PartialEq, Eq, Hash)]
 pub enum Buttons {
     AudienceMix = 0,
 
@@ -88,14 +175,89 @@ pub enum ButtonLighting {
     Right = 6,
 }
 
-#[derive(Display, Debug, Clone, PartialEq)]
+// An animation applied to a button's lighting by the event thread, it runs until the button's
+// colour is set, or another animation is applied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Animation {
+    // Smoothly fades from one colour to the other and back again, once per period
+    Pulse {
+        from: RGBA,
+        to: RGBA,
+        period: Duration,
+    },
+
+    // Shows the 'on' colour for the first half of the period, and 'off' for the second
+    Blink {
+        on: RGBA,
+        off: RGBA,
+        period: Duration,
+    },
+}
+
+/// Sent when a control device's event thread stops unexpectedly. Once this has been received
+/// the device will no longer respond to commands or report interactions, so the device should
+/// be dropped and re-opened with open_control_device.
+#[derive(Debug, Copy, Clone, PartialEq, Error)]
+pub enum ControlThreadError {
+    #[error("USB Error while {0}: {1}")]
+    Usb(&'static str, UsbError),
+
+    #[error("{0} channel closed unexpectedly")]
+    ChannelClosed(&'static str),
+}
+
+#[derive(Display, Debug, Clone)]
 pub enum ControlThreadSender {
     Stop,
     KeepAlive,
+    SetKeepAliveInterval(Option<Duration>),
     SetEnabled(bool),
     SetImage(u32, u32, Vec<u8>),
-    SetDimTimeout(Duration),
+    SetDimTimeout(Option<Duration>),
     SetActiveBrightness(u8),
     SetButtonBrightness(u8),
     SetButtonColour(u8, RGBA),
+    SetAllButtonColours(EnumMap<ButtonLighting, RGBA>),
+    SetButtonAnimation(ButtonLighting, Animation),
+    SetDialRange(Dials, Option<RangeInclusive<i32>>),
+    SetDialPosition(Dials, i32),
+    #[cfg(feature = "raw-access")]
+    SetRawListener(Option<Sender<[u8; 64]>>),
+}
+
+// Channel senders can't be compared by value, so listeners are equal if they share a channel
+impl PartialEq for ControlThreadSender {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Stop, Self::Stop) | (Self::KeepAlive, Self::KeepAlive) => true,
+            (Self::SetKeepAliveInterval(a), Self::SetKeepAliveInterval(b)) => a == b,
+            (Self::SetEnabled(a), Self::SetEnabled(b)) => a == b,
+            (Self::SetImage(a_width, a_height, a), Self::SetImage(b_width, b_height, b)) => {
+                a_width == b_width && a_height == b_height && a == b
+            }
+            (Self::SetDimTimeout(a), Self::SetDimTimeout(b)) => a == b,
+            (Self::SetActiveBrightness(a), Self::SetActiveBrightness(b)) => a == b,
+            (Self::SetButtonBrightness(a), Self::SetButtonBrightness(b)) => a == b,
+            (Self::SetButtonColour(a_button, a), Self::SetButtonColour(b_button, b)) => {
+                a_button == b_button && a == b
+            }
+            (Self::SetAllButtonColours(a), Self::SetAllButtonColours(b)) => a == b,
+            (Self::SetButtonAnimation(a_button, a), Self::SetButtonAnimation(b_button, b)) => {
+                a_button == b_button && a == b
+            }
+            (Self::SetDialRange(a_dial, a), Self::SetDialRange(b_dial, b)) => {
+                a_dial == b_dial && a == b
+            }
+            (Self::SetDialPosition(a_dial, a), Self::SetDialPosition(b_dial, b)) => {
+                a_dial == b_dial && a == b
+            }
+            #[cfg(feature = "raw-access")]
+            (Self::SetRawListener(a), Self::SetRawListener(b)) => match (a, b) {
+                (Some(a), Some(b)) => a.same_channel(b),
+                (None, None) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
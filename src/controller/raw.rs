@@ -0,0 +1,22 @@
+use crate::BResult;
+use crate::controller::BeacnControlInteraction;
+use crate::controller::ControlThreadSender::SetRawListener;
+use anyhow::Error;
+use crossbeam::channel::Sender;
+
+/// Access to the raw input frames read from a control device, before they're decoded into
+/// Interactions. This is intended for exploring the protocol (for example, working out what new
+/// firmware sends), so frames which the decoder ignores are included.
+pub trait BeacnControlRawAccess: BeacnControlInteraction {
+    /// Sends a copy of every 64 byte input frame received from the device to `listener`, this
+    /// happens alongside the normal decoding, so Interactions are still sent as usual. `None`
+    /// stops sending frames, as does the receiver being dropped.
+    fn set_raw_listener(&self, listener: Option<Sender<[u8; 64]>>) -> BResult<()> {
+        self.get_sender()
+            .send(SetRawListener(listener))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+}
+
+impl<T: BeacnControlInteraction + ?Sized> BeacnControlRawAccess for T {}
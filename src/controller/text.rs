@@ -0,0 +1,123 @@
+use crate::types::RGBA;
+use crate::{BResult, beacn_bail};
+use fontdue::{Font, FontSettings};
+use std::sync::OnceLock;
+
+static FONT_DATA: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+static FONT: OnceLock<Font> = OnceLock::new();
+
+fn get_font() -> BResult<&'static Font> {
+    if let Some(font) = FONT.get() {
+        return Ok(font);
+    }
+
+    let font = match Font::from_bytes(FONT_DATA, FontSettings::default()) {
+        Ok(font) => font,
+        Err(e) => beacn_bail!("Unable to load bundled font: {}", e),
+    };
+    Ok(FONT.get_or_init(|| font))
+}
+
+/// A rendered line of text, as RGB pixels
+pub(crate) struct RenderedText {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pixels: Vec<u8>,
+}
+
+/// Renders a single line of text onto a background colour. The text colour's alpha is used as
+/// its opacity, the background is always opaque (the display doesn't support transparency).
+///
+/// The text must fit inside `max_size` (the space available on the display), this is checked
+/// before anything is rasterised or allocated.
+pub(crate) fn render_text(
+    text: &str,
+    font_size: f32,
+    colour: RGBA,
+    background: RGBA,
+    max_size: (u32, u32),
+) -> BResult<RenderedText> {
+    if text.is_empty() {
+        beacn_bail!("Cannot render empty text");
+    }
+    if !font_size.is_finite() || font_size <= 0.0 {
+        beacn_bail!("Font size must be greater than zero");
+    }
+
+    let font = get_font()?;
+    let Some(line) = font.horizontal_line_metrics(font_size) else {
+        beacn_bail!("Bundled font is missing horizontal metrics");
+    };
+
+    // Work out the size from the glyph metrics first, so text which won't fit on the display
+    // is rejected before anything is rasterised
+    let advance: f32 = text
+        .chars()
+        .map(|character| font.metrics(character, font_size).advance_width)
+        .sum();
+    let width = advance.ceil();
+    let height = (line.ascent - line.descent).ceil();
+    if width > max_size.0 as f32 || height > max_size.1 as f32 {
+        beacn_bail!(
+            "Text renders at {}x{}, which doesn't fit in the available {}x{}",
+            width,
+            height,
+            max_size.0,
+            max_size.1
+        );
+    }
+
+    let (width, height) = (width as u32, height as u32);
+    if width == 0 || height == 0 {
+        beacn_bail!("Text '{}' has no visible size", text);
+    }
+
+    let Some(pixel_count) = width.checked_mul(height) else {
+        beacn_bail!("Text size {}x{} is too large", width, height);
+    };
+    let Some(length) = (pixel_count as usize).checked_mul(3) else {
+        beacn_bail!("Text size {}x{} is too large", width, height);
+    };
+
+    // Rasterise each character, and work out where it sits on the line
+    let baseline = line.ascent.ceil() as i32;
+    let mut glyphs = vec![];
+    let mut pen: f32 = 0.0;
+    for character in text.chars() {
+        let (metrics, bitmap) = font.rasterize(character, font_size);
+        glyphs.push((pen.round() as i32, metrics, bitmap));
+        pen += metrics.advance_width;
+    }
+
+    let mut pixels = Vec::with_capacity(length);
+    for _ in 0..pixel_count {
+        pixels.extend_from_slice(&[background.red, background.green, background.blue]);
+    }
+
+    let opacity = colour.alpha as u32;
+    for (offset, metrics, bitmap) in glyphs {
+        let left = offset + metrics.xmin;
+        let top = baseline - metrics.height as i32 - metrics.ymin;
+
+        for (index, coverage) in bitmap.iter().enumerate() {
+            let x = left + (index % metrics.width) as i32;
+            let y = top + (index / metrics.width) as i32;
+            if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                continue;
+            }
+
+            let amount = *coverage as u32 * opacity / 255;
+            let position = (y as u32 * width + x as u32) as usize * 3;
+            let source = [colour.red, colour.green, colour.blue];
+            for (pixel, source) in pixels[position..position + 3].iter_mut().zip(source) {
+                *pixel = ((*pixel as u32 * (255 - amount) + source as u32 * amount) / 255) as u8;
+            }
+        }
+    }
+
+    Ok(RenderedText {
+        width,
+        height,
+        pixels,
+    })
+}
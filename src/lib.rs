@@ -1,4 +1,3 @@
-//pub mod device;
 pub mod audio;
 mod common;
 pub mod controller;
@@ -6,10 +5,14 @@ pub mod manager;
 pub mod types;
 pub mod version;
 
+pub use common::Timeouts;
 pub use crossbeam;
 pub use rusb::Error as UsbError;
 
+use crate::audio::messages::Message;
+use crate::manager::DeviceLocation;
 use crate::version::VersionNumber;
+use std::ops::RangeInclusive;
 use thiserror::Error;
 
 // These are some helper versions, which can be used to determine feature availability
@@ -23,10 +26,70 @@ pub enum BeacnError {
     #[error(transparent)]
     Usb(#[from] UsbError),
 
+    #[error("No Beacn device found at {0}")]
+    DeviceNotFound(DeviceLocation),
+
+    #[error("Expected product id {expected:04x?} but found {found:04x}")]
+    UnexpectedPid { expected: Vec<u16>, found: u16 },
+
+    #[error("{name} {value} is outside of {min}..={max}")]
+    ValueOutOfRange {
+        name: String,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+
+    #[error("{name} value {value} is not finite")]
+    ValueNotFinite { name: String, value: f32 },
+
+    #[error("Requires firmware {required} or later, device is {found}")]
+    FirmwareTooOld {
+        required: VersionNumber,
+        found: VersionNumber,
+    },
+
+    #[error("Requires firmware {maximum} or earlier, device is {found}")]
+    FirmwareTooNew {
+        maximum: VersionNumber,
+        found: VersionNumber,
+    },
+
+    #[error("Value was not changed on the device, sent {sent} but the device is {found}")]
+    ValueMismatch { sent: Message, found: Message },
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+// Builds a ValueOutOfRange error for a named value and the range it should have been in. All
+// the ranges in the library are small enough for an f32 to hold them exactly.
+pub(crate) fn out_of_range<T: Into<f64> + Copy>(
+    name: &str,
+    value: T,
+    range: &RangeInclusive<T>,
+) -> BeacnError {
+    BeacnError::ValueOutOfRange {
+        name: name.to_string(),
+        value: value.into() as f32,
+        min: (*range.start()).into() as f32,
+        max: (*range.end()).into() as f32,
+    }
+}
+
+// Checks a value is finite before it's range checked, a NaN would otherwise be reported as being
+// outside of the range, and an infinity could pass a check against an open ended f32 range
+pub(crate) fn check_finite<T: Into<f64> + Copy>(name: &str, value: T) -> BResult<()> {
+    let value = value.into();
+    if !value.is_finite() {
+        return Err(BeacnError::ValueNotFinite {
+            name: name.to_string(),
+            value: value as f32,
+        });
+    }
+    Ok(())
+}
+
 #[macro_export]
 macro_rules! beacn_bail {
     // formatted string form
@@ -1,13 +1,25 @@
-use anyhow::Result;
-use crossbeam::channel::{Receiver, Sender, TryRecvError, bounded};
+use crate::audio::{
+    BeacnAudioDevice, SharedAudioDevice, attach_beacn, open_audio_device_with_context,
+};
+use crate::common::{Timeouts, fallback_serial, find_device};
+use crate::controller::{
+    BeacnControlDevice, ControlConfig, Interactions, attach_control,
+    open_control_device_with_context,
+};
+use crate::{BResult, BeacnError, beacn_bail};
+use anyhow::{Result, anyhow};
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender, TryRecvError, bounded, unbounded};
 use log::{debug, error, warn};
-use rusb::{Device, GlobalContext, Hotplug, HotplugBuilder, UsbContext, has_hotplug};
+use rusb::{
+    Device, DeviceDescriptor, GlobalContext, Hotplug, HotplugBuilder, UsbContext, has_hotplug,
+};
 use std::cmp::PartialEq;
 use std::fmt::{Display, Formatter};
+use std::panic::RefUnwindSafe;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub(crate) const VENDOR_BEACN: u16 = 0x33ae;
 pub(crate) const PID_BEACN_MIC: &[u16] = &[0x0001, 0x8001];
@@ -15,6 +27,11 @@ pub(crate) const PID_BEACN_STUDIO: &[u16] = &[0x0003];
 pub(crate) const PID_BEACN_MIX: &[u16] = &[0x0004];
 pub(crate) const PID_BEACN_MIX_CREATE: &[u16] = &[0x0007];
 
+// How often the hot plug handler checks for changes when running without libusb hot plug
+// support, and how long it waits on libusb events when it does have it
+static HOTPLUG_DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+static HOTPLUG_DEFAULT_EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DeviceType {
     #[default]
@@ -24,62 +41,178 @@ pub enum DeviceType {
     BeacnMixCreate,
 }
 
+pub const ALL_DEVICE_TYPES: &[DeviceType] = &[
+    DeviceType::BeacnMic,
+    DeviceType::BeacnStudio,
+    DeviceType::BeacnMix,
+    DeviceType::BeacnMixCreate,
+];
+
+impl DeviceType {
+    pub(crate) fn from_product_id(product_id: u16) -> Option<Self> {
+        if PID_BEACN_MIC.contains(&product_id) {
+            Some(DeviceType::BeacnMic)
+        } else if PID_BEACN_STUDIO.contains(&product_id) {
+            Some(DeviceType::BeacnStudio)
+        } else if PID_BEACN_MIX.contains(&product_id) {
+            Some(DeviceType::BeacnMix)
+        } else if PID_BEACN_MIX_CREATE.contains(&product_id) {
+            Some(DeviceType::BeacnMixCreate)
+        } else {
+            None
+        }
+    }
+}
+
+/// Timings used by the hot plug handler, start from `HotplugConfig::default()` and change what's
+/// needed with the `with_` methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HotplugConfig {
+    poll_interval: Duration,
+    event_timeout: Duration,
+}
+
+impl Default for HotplugConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: HOTPLUG_DEFAULT_POLL_INTERVAL,
+            event_timeout: HOTPLUG_DEFAULT_EVENT_TIMEOUT,
+        }
+    }
+}
+
+impl HotplugConfig {
+    /// Sets how long the polling handler sleeps between bus scans, only used when libusb
+    /// doesn't support hot plug notifications (see `HotplugMode`).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets how long the notify handler waits for libusb events before checking for a Quit
+    /// message and device health.
+    pub fn with_event_timeout(mut self, timeout: Duration) -> Self {
+        self.event_timeout = timeout;
+        self
+    }
+
+    pub fn get_poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    pub fn get_event_timeout(&self) -> Duration {
+        self.event_timeout
+    }
+}
+
 struct KnownDevice {
     location: DeviceLocation,
     device_type: DeviceType,
+    serial: Option<String>,
     health_rx: Receiver<()>,
 }
 
+/// Spawns a hot plug handler which monitors all Beacn devices
 pub fn spawn_hotplug_handler(
     sender: Sender<HotPlugMessage>,
     receiver: Receiver<HotPlugThreadManagement>,
-) -> Result<()> {
-    debug!("Spawning Beacn Mic Hot Plug Handler");
-
-    // Create the object for managing devices
-    let manager = BeacnMicManager::new(sender.clone());
+) -> Result<HotplugMode> {
+    spawn_filtered_hotplug_handler(sender, receiver, ALL_DEVICE_TYPES)
+}
 
-    // Create a libusb context
+/// Spawns a hot plug handler which only sends messages for the specified device types
+pub fn spawn_filtered_hotplug_handler(
+    sender: Sender<HotPlugMessage>,
+    receiver: Receiver<HotPlugThreadManagement>,
+    watch: &[DeviceType],
+) -> Result<HotplugMode> {
     let context = GlobalContext::default();
+    let config = HotplugConfig::default();
+    spawn_filtered_hotplug_handler_with_context(context, sender, receiver, watch, config)
+}
+
+/// A simpler way to subscribe to hot plug events, this spawns a handler for the specified device
+/// types and returns the channel its messages arrive on, along with a function which stops it.
+/// HotPlugMessage::ThreadStopped is sent once the handler has stopped.
+pub fn hotplug_channel(
+    watch: &[DeviceType],
+) -> Result<(Receiver<HotPlugMessage>, impl FnOnce() + Send + 'static)> {
+    let (sender, receiver) = unbounded();
+    let (manage_tx, manage_rx) = bounded(1);
+    spawn_filtered_hotplug_handler(sender, manage_rx, watch)?;
+
+    let stop = move || {
+        let _ = manage_tx.send(HotPlugThreadManagement::Quit);
+    };
+    Ok((receiver, stop))
+}
+
+/// The same as spawn_filtered_hotplug_handler, but devices are monitored using the given libusb
+/// context, rather than the global one, with the timings in `config`. Devices reported by this
+/// handler should be opened with the same context.
+pub fn spawn_filtered_hotplug_handler_with_context<C: UsbContext + 'static>(
+    context: C,
+    sender: Sender<HotPlugMessage>,
+    receiver: Receiver<HotPlugThreadManagement>,
+    watch: &[DeviceType],
+    config: HotplugConfig,
+) -> Result<HotplugMode> {
+    debug!("Spawning Beacn Hot Plug Handler for {:?}", watch);
+
+    // Create the object for managing devices
+    let manager = BeacnDeviceManager::new(context.clone(), sender.clone(), watch.to_vec());
 
     // Work out which type of hot plug handler we need to create
     if has_hotplug() {
-        thread::spawn(move || hotplug_notify(context, manager, receiver, sender));
+        thread::spawn(move || hotplug_notify(context, manager, receiver, sender, config));
+        Ok(HotplugMode::Notify)
     } else {
-        thread::spawn(move || hotplug_poll(context, manager, receiver));
+        thread::spawn(move || hotplug_poll(context, manager, receiver, config));
+        Ok(HotplugMode::Poll)
     }
-
-    Ok(())
 }
 
-struct BeacnMicManager {
-    inner: Arc<Mutex<BeacnMicManagerInner>>,
+struct BeacnDeviceManager<C: UsbContext> {
+    inner: Arc<Mutex<BeacnDeviceManagerInner<C>>>,
 }
 
-struct BeacnMicManagerInner {
+struct BeacnDeviceManagerInner<C: UsbContext> {
+    context: C,
     known_devices: Vec<KnownDevice>,
+    watch: Vec<DeviceType>,
     sender: Sender<HotPlugMessage>,
+
+    // Devices which have arrived, but haven't been announced yet, see announce_pending
+    pending: Vec<(DeviceLocation, Sender<()>)>,
 }
 
-impl BeacnMicManager {
-    fn new(sender: Sender<HotPlugMessage>) -> Self {
+impl<C: UsbContext> BeacnDeviceManager<C> {
+    fn new(context: C, sender: Sender<HotPlugMessage>, watch: Vec<DeviceType>) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(BeacnMicManagerInner {
+            inner: Arc::new(Mutex::new(BeacnDeviceManagerInner {
+                context,
                 sender,
+                watch,
                 known_devices: vec![],
+                pending: vec![],
             })),
         }
     }
 }
 
-impl BeacnMicManagerInner {
+impl<C: UsbContext> BeacnDeviceManagerInner<C> {
+    // Returns the device type for a product id, but only if we've been asked to watch it
+    fn watched_type(&self, product_id: u16) -> Option<DeviceType> {
+        DeviceType::from_product_id(product_id).filter(|t| self.watch.contains(t))
+    }
+
     fn thread_stopped(&self) {
         let _ = self.sender.send(HotPlugMessage::ThreadStopped);
     }
 
+    // This can be called from inside libusb's hot plug callback, where no USB I/O is allowed,
+    // so the device is only recorded here, and announced later by announce_pending
     fn device_connected(&mut self, device: DeviceLocation, device_type: DeviceType) {
-        //let mut inner = self.inner.lock().unwrap();
-
         if self.known_devices.iter().any(|k| k.location == device) {
             warn!("Received 'Arrived' Message for already present device!");
             return;
@@ -92,8 +225,18 @@ impl BeacnMicManagerInner {
         self.known_devices.push(KnownDevice {
             location: device,
             device_type,
+            serial: None,
             health_rx,
         });
+        self.pending.push((device, health_tx));
+    }
+
+    // Reads the serials of newly arrived devices, and sends their DeviceAttached messages. This
+    // must be called from the manager thread, outside of any libusb callback.
+    fn announce_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
 
         // We're actually going to sleep on this for a quarter of a second because there appears
         // to be situations where if we run through this too quickly, the udev rules may not have
@@ -103,17 +246,36 @@ impl BeacnMicManagerInner {
         // Shoutout to Jordahn on Discord for helping diagnose this issue.
         sleep(Duration::from_millis(250));
 
-        let _ = self.sender.send(HotPlugMessage::DeviceAttached(
-            device,
-            device_type,
-            health_tx,
-        ));
+        for (location, health_tx) in std::mem::take(&mut self.pending) {
+            let serial = read_serial(&self.context, location);
+            let Some(known) = self
+                .known_devices
+                .iter_mut()
+                .find(|k| k.location == location)
+            else {
+                continue;
+            };
+            known.serial = serial.clone();
+
+            let _ = self.sender.send(HotPlugMessage::DeviceAttached {
+                location,
+                device_type: known.device_type,
+                serial,
+                health_tx,
+            });
+        }
     }
 
     fn device_removed(&mut self, device: DeviceLocation) {
         debug!("Device Removed from {}", device);
         self.known_devices.retain(|e| e.location != device);
-        let _ = self.sender.send(HotPlugMessage::DeviceRemoved(device));
+
+        // If the device never got announced, there's nothing to tell anyone about
+        let pending = self.pending.len();
+        self.pending.retain(|(location, _)| *location != device);
+        if self.pending.len() == pending {
+            let _ = self.sender.send(HotPlugMessage::DeviceRemoved(device));
+        }
     }
 
     fn check_device_health(&mut self) {
@@ -122,7 +284,9 @@ impl BeacnMicManagerInner {
                 // We're going to do a rusb iteration to see if the device is still here, this
                 // makes sure that if a device is unplugged but the removal callback hasn't fired
                 // yet, we don't double-up the removal messages.
-                let still_present = rusb::devices()
+                let still_present = self
+                    .context
+                    .devices()
                     .ok()
                     .map(|devices| {
                         devices
@@ -133,6 +297,7 @@ impl BeacnMicManagerInner {
 
                 if still_present {
                     warn!(
+                        serial = known.serial.as_deref().unwrap_or_default();
                         "Device {} health failed, but still present, sending faux reconnect",
                         known.location
                     );
@@ -147,75 +312,63 @@ impl BeacnMicManagerInner {
 
                     // Sleep for a moment, just to give things time to settle
                     sleep(Duration::from_millis(250));
-                    let _ = self.sender.send(HotPlugMessage::DeviceAttached(
-                        known.location,
-                        known.device_type,
+                    let _ = self.sender.send(HotPlugMessage::DeviceAttached {
+                        location: known.location,
+                        device_type: known.device_type,
+                        serial: known.serial.clone(),
                         health_tx,
-                    ));
+                    });
                 }
             }
         }
     }
 }
 
-impl Hotplug<GlobalContext> for BeacnMicManager {
-    fn device_arrived(&mut self, device: Device<GlobalContext>) {
+impl<C: UsbContext> Hotplug<C> for BeacnDeviceManager<C> {
+    #[allow(clippy::collapsible_if)]
+    fn device_arrived(&mut self, device: Device<C>) {
         let location = DeviceLocation::from(device.clone());
 
         let mut inner = self.inner.lock().unwrap();
 
         // We need to work out what kind of device this is
         if let Ok(desc) = device.device_descriptor() {
-            if PID_BEACN_MIC.contains(&desc.product_id()) {
-                debug!("Found Beacn Mic!");
-                inner.device_connected(location, DeviceType::BeacnMic);
-            }
-            if PID_BEACN_STUDIO.contains(&desc.product_id()) {
-                debug!("Found Beacn Studio!");
-                inner.device_connected(location, DeviceType::BeacnStudio);
-            }
-            if PID_BEACN_MIX.contains(&desc.product_id()) {
-                debug!("Found Beacn Mix!");
-                inner.device_connected(location, DeviceType::BeacnMix)
-            }
-            if PID_BEACN_MIX_CREATE.contains(&desc.product_id()) {
-                debug!("Found Beacn Mix Create!");
-                inner.device_connected(location, DeviceType::BeacnMixCreate)
+            if let Some(device_type) = inner.watched_type(desc.product_id()) {
+                debug!("Found {:?}!", device_type);
+                inner.device_connected(location, device_type);
             }
         }
     }
 
     #[allow(clippy::collapsible_if)]
-    fn device_left(&mut self, device: Device<GlobalContext>) {
-        // Only flag a device removal if it's a Mic or Studio
+    fn device_left(&mut self, device: Device<C>) {
+        // Only flag a device removal if it's a device we're watching
         if let Ok(desc) = device.device_descriptor() {
-            if PID_BEACN_MIC.contains(&desc.product_id())
-                || PID_BEACN_STUDIO.contains(&desc.product_id())
-                || PID_BEACN_MIX.contains(&desc.product_id())
-                || PID_BEACN_MIX_CREATE.contains(&desc.product_id())
-            {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.watched_type(desc.product_id()).is_some() {
                 let location = DeviceLocation::from(device.clone());
-                self.inner.lock().unwrap().device_removed(location);
+                inner.device_removed(location);
             }
         }
     }
 }
 
-fn hotplug_notify(
-    context: GlobalContext,
-    manager: BeacnMicManager,
+fn hotplug_notify<C: UsbContext + 'static>(
+    context: C,
+    manager: BeacnDeviceManager<C>,
     receiver: Receiver<HotPlugThreadManagement>,
     sender: Sender<HotPlugMessage>,
+    config: HotplugConfig,
 ) {
     let inner = manager.inner.clone();
 
     let _handler = HotplugBuilder::new()
         .vendor_id(VENDOR_BEACN)
         .enumerate(true)
-        .register(context, Box::new(manager))
+        .register(context.clone(), Box::new(manager))
         .expect("Cannot Register hot plug Handler");
 
-    let loop_duration = Some(Duration::from_millis(100));
+    let loop_duration = Some(config.event_timeout);
     loop {
         let message = receiver.try_recv();
         if should_stop(message) {
@@ -224,16 +377,18 @@ fn hotplug_notify(
 
         inner.lock().unwrap().check_device_health();
         context.handle_events(loop_duration).unwrap();
+        inner.lock().unwrap().announce_pending();
     }
 
     // We need to send this ourselves, manager has been moved into the handler
     let _ = sender.send(HotPlugMessage::ThreadStopped);
 }
 
-fn hotplug_poll(
-    context: GlobalContext,
-    manager: BeacnMicManager,
+fn hotplug_poll<C: UsbContext>(
+    context: C,
+    manager: BeacnDeviceManager<C>,
     receiver: Receiver<HotPlugThreadManagement>,
+    config: HotplugConfig,
 ) {
     loop {
         let message = receiver.try_recv();
@@ -251,35 +406,12 @@ fn hotplug_poll(
                     if desc.vendor_id() == VENDOR_BEACN {
                         let device = DeviceLocation::from(dev);
 
-                        #[allow(clippy::collapsible_if)]
-                        if PID_BEACN_MIC.contains(&desc.product_id()) {
-                            if !inner.known_devices.iter().any(|k| k.location == device) {
-                                found_devices.push(device);
-                                inner.device_connected(device, DeviceType::BeacnMic);
-                            }
-                        }
-
-                        #[allow(clippy::collapsible_if)]
-                        if PID_BEACN_STUDIO.contains(&desc.product_id()) {
-                            if !inner.known_devices.iter().any(|k| k.location == device) {
-                                found_devices.push(device);
-                                inner.device_connected(device, DeviceType::BeacnStudio);
-                            }
-                        }
-
-                        #[allow(clippy::collapsible_if)]
-                        if PID_BEACN_MIX.contains(&desc.product_id()) {
+                        // Every watched device that's present is recorded, not just new ones,
+                        // otherwise known devices would be treated as removed below
+                        if let Some(device_type) = inner.watched_type(desc.product_id()) {
+                            found_devices.push(device);
                             if !inner.known_devices.iter().any(|k| k.location == device) {
-                                found_devices.push(device);
-                                inner.device_connected(device, DeviceType::BeacnMix);
-                            }
-                        }
-
-                        #[allow(clippy::collapsible_if)]
-                        if PID_BEACN_MIX_CREATE.contains(&desc.product_id()) {
-                            if !inner.known_devices.iter().any(|k| k.location == device) {
-                                found_devices.push(device);
-                                inner.device_connected(device, DeviceType::BeacnMixCreate);
+                                inner.device_connected(device, device_type);
                             }
                         }
                     }
@@ -296,14 +428,57 @@ fn hotplug_poll(
         }
 
         // We're done, sleep for now
+        inner.announce_pending();
         inner.check_device_health();
-        sleep(Duration::from_millis(100));
+        sleep(config.poll_interval);
     }
 
     let inner = manager.inner.lock().unwrap();
     inner.thread_stopped();
 }
 
+// Reads the serial the device reports in its info response, the same serial returned by
+// get_serial() once the device is open. Devices which don't report one get a serial based on
+// their location (as they do when opened). This needs to briefly claim the device, so returns
+// None if it can't be (for example, because it's busy, or permissions aren't set up yet).
+fn read_serial<C: UsbContext>(context: &C, location: DeviceLocation) -> Option<String> {
+    let definition = find_device(context, location)?;
+    read_device_serial(&definition.device, &definition.descriptor)
+}
+
+fn read_device_serial<C: UsbContext>(
+    device: &Device<C>,
+    descriptor: &DeviceDescriptor,
+) -> Option<String> {
+    let device_type = DeviceType::from_product_id(descriptor.product_id())?;
+    let location = DeviceLocation::from(device.clone());
+    let timeout = Timeouts::for_device(device_type).get_setup();
+
+    let result = device
+        .open()
+        .map_err(BeacnError::from)
+        .and_then(|handle| match device_type {
+            DeviceType::BeacnMic | DeviceType::BeacnStudio => {
+                let info = attach_beacn(&handle, timeout);
+                let _ = handle.release_interface(3);
+                info
+            }
+            DeviceType::BeacnMix | DeviceType::BeacnMixCreate => {
+                let info = attach_control(&handle, timeout);
+                let _ = handle.release_interface(0);
+                info
+            }
+        });
+
+    match result {
+        Ok((_, serial, _)) => Some(serial.unwrap_or_else(|| fallback_serial(location))),
+        Err(e) => {
+            debug!("Unable to read serial for {}: {}", location, e);
+            None
+        }
+    }
+}
+
 fn should_stop(message: Result<HotPlugThreadManagement, TryRecvError>) -> bool {
     match message {
         Ok(message) => match message {
@@ -321,11 +496,27 @@ fn should_stop(message: Result<HotPlugThreadManagement, TryRecvError>) -> bool {
 
 #[derive(Debug, Clone)]
 pub enum HotPlugMessage {
-    DeviceAttached(DeviceLocation, DeviceType, Sender<()>),
+    DeviceAttached {
+        location: DeviceLocation,
+        device_type: DeviceType,
+
+        // The serial reported by the device (see get_serial), None if it couldn't be read
+        serial: Option<String>,
+        health_tx: Sender<()>,
+    },
     DeviceRemoved(DeviceLocation),
     ThreadStopped,
 }
 
+/// How the hot plug handler is detecting device changes. Notify uses libusb's hot plug support
+/// and reports changes as they happen, Poll is used on platforms without it, and periodically
+/// scans the bus instead, so changes may take a little longer to be reported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HotplugMode {
+    Notify,
+    Poll,
+}
+
 #[derive(PartialEq)]
 pub enum HotPlugThreadManagement {
     Quit,
@@ -358,32 +549,433 @@ impl<T: UsbContext> From<Device<T>> for DeviceLocation {
 /// This function is useful during prototyping, but shouldn't be used long term, instead
 /// use the regular hot plug thread.
 pub fn get_beacn_mic_devices() -> Vec<DeviceLocation> {
-    get_beacn_device(PID_BEACN_MIC)
+    get_beacn_device(DeviceType::BeacnMic)
 }
 
 pub fn get_beacn_studio_devices() -> Vec<DeviceLocation> {
-    get_beacn_device(PID_BEACN_STUDIO)
+    get_beacn_device(DeviceType::BeacnStudio)
 }
 
 pub fn get_beacn_mix_device() -> Vec<DeviceLocation> {
-    get_beacn_device(PID_BEACN_MIX)
+    get_beacn_device(DeviceType::BeacnMix)
 }
 
 pub fn get_beacn_mix_create_device() -> Vec<DeviceLocation> {
-    get_beacn_device(PID_BEACN_MIX_CREATE)
+    get_beacn_device(DeviceType::BeacnMixCreate)
+}
+
+/// An opened Beacn device, of either kind
+pub enum BeacnDevice {
+    Audio(Box<dyn BeacnAudioDevice>),
+    Control(Box<dyn BeacnControlDevice>),
+}
+
+/// Opens whatever Beacn device is at a location, based on its product id. The interaction,
+/// error and config parameters are the same as open_control_device, and are only used if
+/// the device is a Mix or Mix Create.
+pub fn open_device(
+    location: DeviceLocation,
+    interaction: Option<Sender<Interactions>>,
+    health_tx: Sender<()>,
+    config: Option<ControlConfig>,
+) -> BResult<BeacnDevice> {
+    let context = GlobalContext::default();
+    open_device_with_context(&context, location, interaction, health_tx, config)
+}
+
+/// The same as open_device, but the device is found and opened using the given libusb context,
+/// rather than the global one.
+pub fn open_device_with_context<C: UsbContext + RefUnwindSafe + 'static>(
+    context: &C,
+    location: DeviceLocation,
+    interaction: Option<Sender<Interactions>>,
+    health_tx: Sender<()>,
+    config: Option<ControlConfig>,
+) -> BResult<BeacnDevice> {
+    let Some(device) = find_device(context, location) else {
+        return Err(BeacnError::DeviceNotFound(location));
+    };
+
+    let product_id = device.descriptor.product_id();
+    match DeviceType::from_product_id(product_id) {
+        Some(DeviceType::BeacnMic | DeviceType::BeacnStudio) => {
+            let device = open_audio_device_with_context(context, location)?;
+            Ok(BeacnDevice::Audio(device))
+        }
+        Some(DeviceType::BeacnMix | DeviceType::BeacnMixCreate) => {
+            let device = open_control_device_with_context(
+                context,
+                location,
+                interaction,
+                health_tx,
+                config,
+            )?;
+            Ok(BeacnDevice::Control(device))
+        }
+        None => Err(BeacnError::UnexpectedPid {
+            expected: [
+                PID_BEACN_MIC,
+                PID_BEACN_STUDIO,
+                PID_BEACN_MIX,
+                PID_BEACN_MIX_CREATE,
+            ]
+            .concat(),
+            found: product_id,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub location: DeviceLocation,
+    pub device_type: DeviceType,
+
+    // The serial reported by the device (see get_serial), None if it couldn't be read
+    pub serial: Option<String>,
+}
+
+/// Returns all attached Beacn devices, along with their type and serial. Reading the serial
+/// requires briefly opening the device, so if you only need locations, use the per-type
+/// functions above.
+pub fn list_beacn_devices() -> Vec<DeviceInfo> {
+    enumerate_beacn_devices()
+        .into_iter()
+        .map(|(device, descriptor, device_type)| DeviceInfo {
+            location: DeviceLocation::from(device.clone()),
+            device_type,
+            serial: read_device_serial(&device, &descriptor),
+        })
+        .collect()
+}
+
+/// Finds the location of the attached Beacn device with the given serial, as returned by
+/// get_serial() on an open device. Unlike locations, serials stay the same when a device is
+/// re-plugged, so they're better suited to remembering a specific device between sessions.
+///
+/// Reading the serial requires briefly claiming each device, so devices which are busy (for
+/// example, already open in this or another process) are skipped.
+pub fn find_device_by_serial(serial: &str) -> Option<DeviceLocation> {
+    enumerate_beacn_devices()
+        .into_iter()
+        .find(|(device, descriptor, _)| {
+            read_device_serial(device, descriptor).is_some_and(|s| s == serial)
+        })
+        .map(|(device, _, _)| DeviceLocation::from(device))
+}
+
+/// Blocks until a device of one of the given types is attached, and returns it. If a matching
+/// device is already present, it's returned straight away. A timeout of `None` waits forever.
+///
+/// This uses the hot plug handler internally, so falls back to polling where hot plug isn't
+/// available.
+pub fn wait_for_device(
+    device_types: &[DeviceType],
+    timeout: Option<Duration>,
+) -> BResult<DeviceInfo> {
+    if device_types.is_empty() {
+        beacn_bail!("No device types to wait for");
+    }
+
+    let (sender, receiver) = unbounded();
+    let (manage_tx, manage_rx) = bounded(1);
+    spawn_filtered_hotplug_handler(sender, manage_rx, device_types)?;
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let result = loop {
+        let message = match deadline {
+            Some(deadline) => receiver.recv_deadline(deadline),
+            None => receiver.recv().map_err(RecvTimeoutError::from),
+        };
+
+        match message {
+            Ok(HotPlugMessage::DeviceAttached {
+                location,
+                device_type,
+                serial,
+                ..
+            }) => {
+                break Ok(DeviceInfo {
+                    location,
+                    device_type,
+                    serial,
+                });
+            }
+            Ok(HotPlugMessage::DeviceRemoved(_)) => continue,
+            Ok(HotPlugMessage::ThreadStopped) | Err(RecvTimeoutError::Disconnected) => {
+                break Err(anyhow!("Hot plug handler stopped unexpectedly").into());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                break Err(anyhow!("Timed out waiting for a {:?} device", device_types).into());
+            }
+        }
+    };
+
+    // We're done with the handler, stop it
+    let _ = manage_tx.send(HotPlugThreadManagement::Quit);
+    result
+}
+
+fn get_beacn_device(device_type: DeviceType) -> Vec<DeviceLocation> {
+    enumerate_beacn_devices()
+        .into_iter()
+        .filter(|(_, _, t)| *t == device_type)
+        .map(|(device, _, _)| DeviceLocation::from(device))
+        .collect()
 }
 
 #[allow(clippy::collapsible_if)]
-fn get_beacn_device(pid: &[u16]) -> Vec<DeviceLocation> {
+fn enumerate_beacn_devices() -> Vec<(Device<GlobalContext>, DeviceDescriptor, DeviceType)> {
     let mut devices = vec![];
     if let Ok(devs) = rusb::devices() {
         for dev in devs.iter() {
             if let Ok(desc) = dev.device_descriptor() {
-                if desc.vendor_id() == VENDOR_BEACN && pid.contains(&desc.product_id()) {
-                    devices.push(DeviceLocation::from(dev));
+                if desc.vendor_id() == VENDOR_BEACN {
+                    if let Some(device_type) = DeviceType::from_product_id(desc.product_id()) {
+                        devices.push((dev, desc, device_type));
+                    }
                 }
             }
         }
     }
     devices
 }
+
+/// A device held by the DeviceRegistry, these can be cloned and shared between threads
+#[derive(Clone)]
+pub enum RegisteredDevice {
+    Audio(Arc<SharedAudioDevice>),
+    Control(Arc<dyn BeacnControlDevice>),
+}
+
+#[derive(Debug, Clone)]
+pub enum RegistryEvent {
+    /// A device has been opened, and is available from the registry
+    DeviceAdded {
+        serial: String,
+        device_type: DeviceType,
+    },
+
+    /// A device has gone away, and has been dropped from the registry
+    DeviceRemoved {
+        serial: String,
+        device_type: DeviceType,
+    },
+
+    /// A device arrived, but couldn't be opened. The serial is None if it couldn't be read
+    OpenFailed {
+        location: DeviceLocation,
+        device_type: DeviceType,
+        serial: Option<String>,
+        error: String,
+    },
+
+    /// A button or dial on a control device, along with the device's serial
+    Interaction(String, Interactions),
+
+    /// The hot plug handler has stopped, no further devices will be added or removed
+    Stopped,
+}
+
+struct RegistryEntry {
+    location: DeviceLocation,
+    serial: String,
+    device_type: DeviceType,
+    device: RegisteredDevice,
+}
+
+/// Keeps every attached Beacn device open. A hot plug handler is spawned for the watched device
+/// types, devices are opened as they arrive (audio devices as a SharedAudioDevice), and dropped
+/// when they're removed. Changes, and interactions from control devices, are sent to `events`.
+///
+/// Devices are looked up by the serial they report (see get_serial), which is the same serial
+/// used by find_device_by_serial and the hot plug handler.
+///
+/// The hot plug handler is stopped, and all devices released, when the registry is dropped.
+pub struct DeviceRegistry {
+    entries: Arc<Mutex<Vec<RegistryEntry>>>,
+    manage_tx: Sender<HotPlugThreadManagement>,
+}
+
+impl DeviceRegistry {
+    pub fn new(
+        watch: &[DeviceType],
+        events: Option<Sender<RegistryEvent>>,
+        config: Option<ControlConfig>,
+    ) -> BResult<Self> {
+        let context = GlobalContext::default();
+        let hotplug = HotplugConfig::default();
+        Self::new_with_context(context, watch, events, config, hotplug)
+    }
+
+    /// The same as new, but devices are monitored and opened using the given libusb context,
+    /// with the hot plug timings in `hotplug`.
+    pub fn new_with_context<C: UsbContext + RefUnwindSafe + 'static>(
+        context: C,
+        watch: &[DeviceType],
+        events: Option<Sender<RegistryEvent>>,
+        config: Option<ControlConfig>,
+        hotplug: HotplugConfig,
+    ) -> BResult<Self> {
+        let (hotplug_tx, hotplug_rx) = unbounded();
+        let (manage_tx, manage_rx) = bounded(1);
+        spawn_filtered_hotplug_handler_with_context(
+            context.clone(),
+            hotplug_tx,
+            manage_rx,
+            watch,
+            hotplug,
+        )?;
+
+        let entries = Arc::new(Mutex::new(vec![]));
+        let thread_entries = entries.clone();
+        thread::spawn(move || {
+            for message in hotplug_rx {
+                let event = match message {
+                    HotPlugMessage::DeviceAttached {
+                        location,
+                        device_type,
+                        serial,
+                        health_tx,
+                    } => match registry_open(
+                        &context,
+                        location,
+                        device_type,
+                        health_tx,
+                        &events,
+                        config.clone(),
+                    ) {
+                        Ok(entry) => {
+                            let event = RegistryEvent::DeviceAdded {
+                                serial: entry.serial.clone(),
+                                device_type,
+                            };
+                            thread_entries.lock().unwrap().push(entry);
+                            event
+                        }
+                        Err(e) => {
+                            warn!(serial = serial.as_deref().unwrap_or_default(); "Unable to open {:?} at {}: {}", device_type, location, e);
+                            RegistryEvent::OpenFailed {
+                                location,
+                                device_type,
+                                serial,
+                                error: e.to_string(),
+                            }
+                        }
+                    },
+                    HotPlugMessage::DeviceRemoved(location) => {
+                        let mut entries = thread_entries.lock().unwrap();
+                        let Some(index) = entries.iter().position(|e| e.location == location)
+                        else {
+                            continue;
+                        };
+                        let entry = entries.remove(index);
+                        RegistryEvent::DeviceRemoved {
+                            serial: entry.serial,
+                            device_type: entry.device_type,
+                        }
+                    }
+                    HotPlugMessage::ThreadStopped => {
+                        thread_entries.lock().unwrap().clear();
+                        if let Some(events) = &events {
+                            let _ = events.send(RegistryEvent::Stopped);
+                        }
+                        break;
+                    }
+                };
+
+                if let Some(events) = &events {
+                    let _ = events.send(event);
+                }
+            }
+            debug!("Device Registry Terminated");
+        });
+
+        Ok(Self { entries, manage_tx })
+    }
+
+    pub fn get_by_serial(&self, serial: &str) -> Option<RegisteredDevice> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.iter().find(|e| e.serial == serial)?;
+        Some(entry.device.clone())
+    }
+
+    pub fn get_by_type(&self, device_type: DeviceType) -> Vec<RegisteredDevice> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|e| e.device_type == device_type)
+            .map(|e| e.device.clone())
+            .collect()
+    }
+
+    /// Returns the details of every device currently in the registry
+    pub fn list(&self) -> Vec<DeviceInfo> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .map(|e| DeviceInfo {
+                location: e.location,
+                device_type: e.device_type,
+                serial: Some(e.serial.clone()),
+            })
+            .collect()
+    }
+}
+
+impl Drop for DeviceRegistry {
+    fn drop(&mut self) {
+        let _ = self.manage_tx.send(HotPlugThreadManagement::Quit);
+    }
+}
+
+fn registry_open<C: UsbContext + RefUnwindSafe + 'static>(
+    context: &C,
+    location: DeviceLocation,
+    device_type: DeviceType,
+    health_tx: Sender<()>,
+    events: &Option<Sender<RegistryEvent>>,
+    config: Option<ControlConfig>,
+) -> BResult<RegistryEntry> {
+    let (serial, device) = match device_type {
+        DeviceType::BeacnMic | DeviceType::BeacnStudio => {
+            let device = SharedAudioDevice::open_with_context(context.clone(), location)?;
+            (
+                device.get_serial(),
+                RegisteredDevice::Audio(Arc::new(device)),
+            )
+        }
+        DeviceType::BeacnMix | DeviceType::BeacnMixCreate => {
+            // Interactions are tagged with the device's serial before being passed on
+            let (interaction_tx, interaction_rx) = unbounded();
+            let interaction_tx = Some(interaction_tx);
+            let device = open_control_device_with_context(
+                context,
+                location,
+                interaction_tx,
+                health_tx,
+                config,
+            )?;
+            let serial = device.get_serial();
+
+            if let Some(events) = events.clone() {
+                let serial = serial.clone();
+                thread::spawn(move || {
+                    for interaction in interaction_rx {
+                        let event = RegistryEvent::Interaction(serial.clone(), interaction);
+                        if events.send(event).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            (serial, RegisteredDevice::Control(Arc::from(device)))
+        }
+    };
+
+    Ok(RegistryEntry {
+        location,
+        serial,
+        device_type,
+        device,
+    })
+}
@@ -1,6 +1,8 @@
 use crate::types::sealed::Sealed;
+use crate::{BResult, beacn_bail, check_finite, out_of_range};
 use byteorder::{ByteOrder, LittleEndian};
-use std::fmt::Debug;
+use log::debug;
+use std::fmt::{Debug, Display, Formatter};
 use std::ops::RangeInclusive;
 
 // Create the base values which everything comes from
@@ -37,6 +39,14 @@ where
     }
 }
 
+/// A colour, used for lighting on the Mic and Studio, and the buttons and display on the Mix
+/// and Mix Create. Whether alpha means anything depends on where the colour is used:
+///
+/// * Mic / Studio lighting colours don't store alpha, it's written as 0 and ignored. Brightness
+///   is set separately for the whole device (see Lighting::Brightness).
+/// * Control device button colours pass alpha through to the device as is.
+/// * Text drawn on the control device display uses alpha as its opacity, the display itself
+///   has no transparency.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct RGBA {
     pub red: u8,
@@ -45,6 +55,47 @@ pub struct RGBA {
     pub alpha: u8,
 }
 
+// Approximate gamma of the LEDs, used to make brightness changes look even
+static LED_GAMMA: f32 = 2.2;
+
+impl RGBA {
+    /// Scales the colour's brightness by a factor between 0 (off) and 1 (unchanged). The LEDs
+    /// aren't perceptually linear, so this is gamma corrected, a factor of 0.5 looks roughly
+    /// half as bright rather than only slightly dimmer. Alpha is left as is.
+    pub fn with_brightness(&self, factor: f32) -> RGBA {
+        let scale = factor.clamp(0.0, 1.0).powf(LED_GAMMA);
+        let channel = |value: u8| (value as f32 * scale).round() as u8;
+        RGBA {
+            red: channel(self.red),
+            green: channel(self.green),
+            blue: channel(self.blue),
+            alpha: self.alpha,
+        }
+    }
+
+    /// Linearly blends between two colours, an amount of 0 returns 'from', and 1 returns 'to'
+    pub fn lerp(from: RGBA, to: RGBA, amount: f32) -> RGBA {
+        let amount = amount.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        RGBA {
+            red: mix(from.red, to.red),
+            green: mix(from.green, to.green),
+            blue: mix(from.blue, to.blue),
+            alpha: mix(from.alpha, to.alpha),
+        }
+    }
+}
+
+impl Display for RGBA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.red, self.green, self.blue, self.alpha
+        )
+    }
+}
+
 pub(crate) mod sealed {
     use crate::types::RGBA;
 
@@ -91,11 +142,19 @@ impl WriteBeacn for bool {
 }
 impl ReadBeacn for bool {
     fn read_beacn(buf: &BeacnValue) -> Self {
+        match Self::try_read_beacn(buf) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+impl TryReadBeacn for bool {
+    fn try_read_beacn(buf: &BeacnValue) -> BResult<Self> {
         let value = LittleEndian::read_u32(buf);
         if (0..=1).contains(&value) {
-            return value == 1;
+            return Ok(value == 1);
         }
-        panic!("Incorrect Boolean Received: {}", value);
+        beacn_bail!("Incorrect Boolean Received: {}", value);
     }
 }
 
@@ -256,6 +315,14 @@ impl ToInner<f32> for f32 {
 
 impl WriteBeacn for RGBA {
     fn write_beacn(&self) -> BeacnValue {
+        // Both 0 and 255 are commonly used for 'no transparency', anything else suggests the
+        // caller expected alpha to do something
+        if self.alpha != 0 && self.alpha != u8::MAX {
+            debug!(
+                "Alpha of {} ignored, lighting colours don't store alpha",
+                self.alpha
+            );
+        }
         [self.blue, self.green, self.red, 0]
     }
 }
@@ -291,6 +358,11 @@ impl ToInner<f32> for TimeFrame {
         self.0
     }
 }
+impl Display for TimeFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ms", self.0)
+    }
+}
 
 // -----------------------------------------------------------------------------------------------
 // Make-up Gain is used in a couple of places
@@ -312,6 +384,11 @@ impl ToInner<f32> for MakeUpGain {
         self.0
     }
 }
+impl Display for MakeUpGain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} dB", self.0)
+    }
+}
 
 // -----------------------------------------------------------------------------------------------
 
@@ -332,6 +409,11 @@ impl ToInner<f32> for Percent {
         self.0
     }
 }
+impl Display for Percent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
 
 // -----------------------------------------------------------------------------------------------
 
@@ -344,18 +426,16 @@ impl ToInner<f32> for Percent {
 /// before returning the HeadphoneLevel.
 ///
 /// This code is configured to panic! if something goes wrong, we shouldn't be sending or receiving
-/// bad data, so we'll just crash.
+/// bad data, so we'll just crash. Use try_read_value if the bytes come from somewhere untrusted.
 pub fn read_value<T, U>(bytes: &BeacnValue) -> T
 where
-    U: ReadBeacn + PartialOrd + Copy + Debug,
+    U: ReadBeacn + PartialOrd + Copy + Debug + Into<f64>,
     T: HasRange<U> + FromInner<U>,
 {
-    let inner: U = U::read_beacn(bytes);
-    let range = T::range();
-    if !range.contains(&inner) {
-        panic!("Value {:?} is out of expected range {:?}", inner, range);
+    match try_read_value::<T, U>(bytes) {
+        Ok(value) => value,
+        Err(e) => panic!("{}", e),
     }
-    T::from_inner(inner)
 }
 
 /// Similar to above, except for writing values, you pass in <HeadphoneLevel, f32>, it'll convert
@@ -363,17 +443,68 @@ where
 pub fn write_value<T, U>(value: &T) -> BeacnValue
 where
     T: HasRange<U> + ToInner<U>,
-    U: WriteBeacn + PartialOrd + Copy + Debug,
+    U: WriteBeacn + PartialOrd + Copy + Debug + Into<f64>,
+{
+    match try_write_value::<T, U>(value) {
+        Ok(value) => value,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Maps a 0..=100 percentage (for example, from a UI slider) linearly onto a range, so for
+/// HPLevel::range() 0% is -70dB, 50% is -35dB and 100% is 0dB. Percentages outside of 0..=100
+/// are clamped.
+pub fn percent_to_db(range: RangeInclusive<f32>, percent: f32) -> f32 {
+    let percent = percent.clamp(0.0, 100.0);
+    range.start() + (range.end() - range.start()) * (percent / 100.0)
+}
+
+/// The reverse of percent_to_db, returns where a value sits in a range as a 0..=100 percentage
+pub fn db_to_percent(range: RangeInclusive<f32>, db: f32) -> f32 {
+    let width = range.end() - range.start();
+    if width == 0.0 {
+        return 0.0;
+    }
+    let db = db.clamp(*range.start(), *range.end());
+    (db - range.start()) / width * 100.0
+}
+
+/// The same as read_value, but returns a ValueNotFinite or ValueOutOfRange error rather than
+/// panicking
+pub fn try_read_value<T, U>(bytes: &BeacnValue) -> BResult<T>
+where
+    U: ReadBeacn + PartialOrd + Copy + Debug + Into<f64>,
+    T: HasRange<U> + FromInner<U>,
+{
+    let inner: U = U::read_beacn(bytes);
+    check_finite(type_label::<T>(), inner)?;
+    let range = T::range();
+    if !range.contains(&inner) {
+        return Err(out_of_range(type_label::<T>(), inner, &range));
+    }
+    Ok(T::from_inner(inner))
+}
+
+/// The same as write_value, but returns a ValueNotFinite or ValueOutOfRange error rather than
+/// panicking
+pub fn try_write_value<T, U>(value: &T) -> BResult<BeacnValue>
+where
+    T: HasRange<U> + ToInner<U>,
+    U: WriteBeacn + PartialOrd + Copy + Debug + Into<f64>,
 {
     let inner = value.to_inner();
-    if !T::range().contains(&inner) {
-        panic!(
-            "Attempted to write value {:?} outside of valid range {:?}",
-            inner,
-            T::range()
-        );
+    check_finite(type_label::<T>(), inner)?;
+    let range = T::range();
+    if !range.contains(&inner) {
+        return Err(out_of_range(type_label::<T>(), inner, &range));
     }
-    U::write_beacn(&inner)
+    Ok(U::write_beacn(&inner))
+}
+
+// Strips the module path from a type name, so errors read 'HeadphoneLevel' rather than the full path
+fn type_label<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
 }
 
 impl From<BeacnValue> for MessageValue<RGBA> {
@@ -397,6 +528,27 @@ impl From<MessageValue<RGBA>> for BeacnValue {
 #[macro_export]
 macro_rules! generate_range {
     ($name:ident, $type:ty, $range:expr) => {
+        $crate::generate_range!(@define $name, $type, $range);
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+
+    // As above, but the value is displayed with a unit (eg. "4.5 dB")
+    ($name:ident, $type:ty, $range:expr, $unit:literal) => {
+        $crate::generate_range!(@define $name, $type, $range);
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{} {}", self.0, $unit)
+            }
+        }
+    };
+
+    (@define $name:ident, $type:ty, $range:expr) => {
         #[derive(Debug, Clone, Copy, PartialEq)]
         pub struct $name(pub $type);
 
@@ -419,3 +571,105 @@ macro_rules! generate_range {
         }
     };
 }
+
+pub trait TryReadBeacn: Sized {
+    fn try_read_beacn(buf: &BeacnValue) -> BResult<Self>;
+}
+
+// Implements Sealed, ReadBeacn, TryReadBeacn and WriteBeacn for a simple enum, where the value
+// sent to the device is the variant's discriminant. Most are sent as a u32, but some (such as
+// BassPreset) are sent as an f32, which can be specified as a second parameter.
+macro_rules! beacn_enum {
+    ($name:ident) => {
+        $crate::types::beacn_enum!(@impl $name, read_u32, write_u32, u32);
+    };
+    ($name:ident, f32) => {
+        $crate::types::beacn_enum!(@impl $name, read_f32, write_f32, f32);
+    };
+
+    (@impl $name:ident, $read:ident, $write:ident, $type:ty) => {
+        impl $crate::types::sealed::Sealed for $name {}
+
+        impl $crate::types::WriteBeacn for $name {
+            fn write_beacn(&self) -> $crate::types::BeacnValue {
+                let mut buf = [0; 4];
+                <byteorder::LittleEndian as byteorder::ByteOrder>::$write(
+                    &mut buf,
+                    *self as u8 as $type,
+                );
+                buf
+            }
+        }
+
+        impl $crate::types::TryReadBeacn for $name {
+            fn try_read_beacn(buf: &$crate::types::BeacnValue) -> $crate::BResult<Self> {
+                let value = <byteorder::LittleEndian as byteorder::ByteOrder>::$read(buf);
+                for var in <Self as strum::IntoEnumIterator>::iter() {
+                    if var as u8 as $type == value {
+                        return Ok(var);
+                    }
+                }
+                $crate::beacn_bail!("Unknown {} value {:?}", stringify!($name), value);
+            }
+        }
+
+        impl $crate::types::ReadBeacn for $name {
+            fn read_beacn(buf: &$crate::types::BeacnValue) -> Self {
+                match <Self as $crate::types::TryReadBeacn>::try_read_beacn(buf) {
+                    Ok(value) => value,
+                    Err(e) => panic!("{}", e),
+                }
+            }
+        }
+    };
+}
+pub(crate) use beacn_enum;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::messages::headphones::HPLevel;
+
+    static COLOUR: RGBA = RGBA {
+        red: 200,
+        green: 100,
+        blue: 50,
+        alpha: 255,
+    };
+
+    static BLACK: RGBA = RGBA {
+        red: 0,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    };
+
+    #[test]
+    fn brightness_extremes() {
+        assert_eq!(COLOUR.with_brightness(0.0), BLACK);
+        assert_eq!(COLOUR.with_brightness(1.0), COLOUR);
+    }
+
+    #[test]
+    fn lerp_extremes() {
+        assert_eq!(RGBA::lerp(BLACK, COLOUR, 0.0), BLACK);
+        assert_eq!(RGBA::lerp(BLACK, COLOUR, 1.0), COLOUR);
+    }
+
+    #[test]
+    fn percent_to_db_on_headphone_range() {
+        let range = HPLevel::range();
+        assert_eq!(percent_to_db(range.clone(), 0.0), -70.0);
+        assert_eq!(percent_to_db(range.clone(), 50.0), -35.0);
+        assert_eq!(percent_to_db(range.clone(), 100.0), 0.0);
+    }
+
+    #[test]
+    fn percent_round_trip() {
+        let range = HPLevel::range();
+        for percent in [0.0, 25.0, 50.0, 75.0, 100.0] {
+            let db = percent_to_db(range.clone(), percent);
+            assert_eq!(db_to_percent(range.clone(), db), percent);
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::{BeacnError, beacn_bail};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Default, Hash, PartialEq, Eq)]
 pub struct VersionNumber(pub u32, pub u32, pub u32, pub u32);
@@ -87,3 +89,50 @@ impl From<String> for VersionNumber {
         version
     }
 }
+
+// Unlike the From<String> implementation above, this is strict, and will error if the version
+// isn't made up of exactly four numeric parts.
+impl FromStr for VersionNumber {
+    type Err = BeacnError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split('.').collect();
+        if parts.len() != 4 {
+            beacn_bail!(
+                "Version '{}' should have 4 parts, but has {}",
+                value,
+                parts.len()
+            );
+        }
+
+        let mut numbers = [0; 4];
+        for (number, part) in numbers.iter_mut().zip(parts) {
+            *number = match part.parse() {
+                Ok(number) => number,
+                Err(e) => beacn_bail!("Invalid part '{}' in version '{}': {}", part, value, e),
+            };
+        }
+
+        Ok(VersionNumber(
+            numbers[0], numbers[1], numbers[2], numbers[3],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_version() {
+        let version: VersionNumber = "1.2.0.80".parse().unwrap();
+        assert_eq!(version, VersionNumber(1, 2, 0, 80));
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        assert!("1.2".parse::<VersionNumber>().is_err());
+        assert!("1.a.0.0".parse::<VersionNumber>().is_err());
+        assert!("".parse::<VersionNumber>().is_err());
+    }
+}